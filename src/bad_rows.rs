@@ -0,0 +1,274 @@
+//! Row-level error policy (`--on-error`) for malformed records.
+//!
+//! Commands iterate records through [`Records`] instead of `csv.records()`.
+//! With `fail`, the first ragged or non-UTF-8 row aborts the run, as before.
+//! With `skip` and `report`, bad rows are dropped and counted; `report` also
+//! writes each of them to a side file as `file,line,byte,error,record`.
+
+use crate::error::{CliError, Result};
+use clap::ValueEnum;
+use csv::{ByteRecord, StringRecord};
+use std::fs::File;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnError {
+    /// Abort on the first malformed row
+    #[default]
+    Fail,
+    /// Drop malformed rows, with a warning counting them
+    Skip,
+    /// Drop malformed rows and write them to the --error-report file
+    Report,
+}
+
+/// Shared state for the error policy: the policy itself, the report file and
+/// the number of rows dropped so far across all inputs of a command.
+#[derive(Debug, Clone, Default)]
+pub struct BadRows {
+    pub policy: OnError,
+    report: Option<(String, Arc<Mutex<csv::Writer<File>>>)>,
+    skipped: Arc<AtomicUsize>,
+    /// The file being read, `None` for stdin
+    file: Option<String>,
+    /// Lines and bytes before the point the reader started at
+    origin: (u64, u64),
+}
+
+impl BadRows {
+    pub fn new(policy: OnError, report_path: Option<&str>) -> Result<Self> {
+        let report = match (policy, report_path) {
+            (OnError::Report, Some(path)) => {
+                let file = File::create(path).map_err(|source| CliError::Open {
+                    path: path.to_string(),
+                    source,
+                })?;
+                let mut writer = csv::Writer::from_writer(file);
+                writer.write_record(["file", "line", "byte", "error", "record"])?;
+                Some((path.to_string(), Arc::new(Mutex::new(writer))))
+            }
+            (OnError::Report, None) => {
+                return Err(CliError::Usage(
+                    "--on-error report requires --error-report <FILE>".to_string(),
+                ))
+            }
+            (_, Some(_)) => {
+                return Err(CliError::Usage(
+                    "--error-report only applies with --on-error report".to_string(),
+                ))
+            }
+            (_, None) => None,
+        };
+        Ok(BadRows {
+            policy,
+            report,
+            skipped: Arc::new(AtomicUsize::new(0)),
+            file: None,
+            origin: (0, 0),
+        })
+    }
+
+    /// The same policy, report and count, for rows read from `path` (or
+    /// stdin), which the report names in its `file` column.
+    pub fn reading(&self, path: Option<&str>) -> BadRows {
+        BadRows {
+            file: path.map(str::to_string),
+            ..self.clone()
+        }
+    }
+
+    /// Whether dropped rows are written to a report file.
+    pub fn reports(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// Report positions of a reader that starts `lines` line breaks and
+    /// `bytes` bytes into its file, so they stay relative to the file start.
+    pub fn resumed_at(&mut self, lines: u64, bytes: u64) {
+        self.origin = (lines, bytes);
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Flush the report file and return the warning to show, if any row was dropped.
+    pub fn finish(&self) -> Result<Option<String>> {
+        if let Some((_, writer)) = &self.report {
+            writer.lock().unwrap().flush()?;
+        }
+        let n = self.skipped();
+        if n == 0 {
+            return Ok(None);
+        }
+        let rows = if n == 1 { "row" } else { "rows" };
+        Ok(Some(match &self.report {
+            Some((path, _)) => format!("Skipped {} malformed {}; see '{}'", n, rows, path),
+            None => format!("Skipped {} malformed {}", n, rows),
+        }))
+    }
+
+    fn drop_row(&self, raw: &ByteRecord, error: &str) -> Result<()> {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        if let Some((_, writer)) = &self.report {
+            let (line, byte) = raw
+                .position()
+                .map(|p| (self.origin.0 + p.line(), self.origin.1 + p.byte()))
+                .unwrap_or_default();
+            let mut row = csv::Writer::from_writer(Vec::new());
+            row.write_byte_record(raw)?;
+            let row = row.into_inner().map_err(|e| e.into_error())?;
+            let row = String::from_utf8_lossy(&row);
+            writer.lock().unwrap().write_record([
+                self.file.as_deref().unwrap_or("-"),
+                line.to_string().as_str(),
+                byte.to_string().as_str(),
+                error,
+                row.trim_end_matches(['\r', '\n']),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the data records of a reader, applying the error policy.
+///
+/// `expected` is the number of fields every row must have, or `None` for
+/// commands that accept ragged rows (e.g. `info`, `transpose`).
+pub struct Records<'r, R> {
+    csv: &'r mut csv::Reader<R>,
+    expected: Option<usize>,
+    bad_rows: &'r BadRows,
+    raw: ByteRecord,
+}
+
+impl<'r, R: io::Read> Records<'r, R> {
+    pub fn new(
+        csv: &'r mut csv::Reader<R>,
+        expected: Option<usize>,
+        bad_rows: &'r BadRows,
+    ) -> Self {
+        Records {
+            csv,
+            expected,
+            bad_rows,
+            raw: ByteRecord::new(),
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for Records<'_, R> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_record(self.csv, self.expected, self.bad_rows, &mut self.raw)
+    }
+}
+
+/// Read the next record of `csv`, applying the error policy of `bad_rows`.
+/// `raw` is scratch space reused between calls.
+pub fn next_record<R: io::Read>(
+    csv: &mut csv::Reader<R>,
+    expected: Option<usize>,
+    bad_rows: &BadRows,
+    raw: &mut ByteRecord,
+) -> Option<Result<StringRecord>> {
+    if bad_rows.policy == OnError::Fail {
+        let mut record = StringRecord::new();
+        return match csv.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e.into())),
+        };
+    }
+    read_checked(csv, expected, bad_rows, raw).transpose()
+}
+
+fn read_checked<R: io::Read>(
+    csv: &mut csv::Reader<R>,
+    expected: Option<usize>,
+    bad_rows: &BadRows,
+    raw: &mut ByteRecord,
+) -> Result<Option<StringRecord>> {
+    loop {
+        if !csv.read_byte_record(raw)? {
+            return Ok(None);
+        }
+        let error = match expected {
+            Some(n) if raw.len() != n => format!(
+                "found record with {} fields, but the header has {} fields",
+                raw.len(),
+                n
+            ),
+            _ => match StringRecord::from_byte_record(raw.clone()) {
+                Ok(record) => return Ok(Some(record)),
+                Err(e) => format!("invalid UTF-8 in field {}", e.utf8_error().field() + 1),
+            },
+        };
+        bad_rows.drop_row(raw, &error)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(data: &str, flexible: bool) -> csv::Reader<&[u8]> {
+        csv::ReaderBuilder::new()
+            .flexible(flexible)
+            .from_reader(data.as_bytes())
+    }
+
+    #[test]
+    fn test_fail_stops_at_ragged_row() {
+        let bad_rows = BadRows::default();
+        let mut csv = reader("a,b\n1,2\n3,4,5\n6,7\n", false);
+        let mut records = Records::new(&mut csv, Some(2), &bad_rows);
+        assert!(records.next().unwrap().is_ok());
+        let err = records.next().unwrap().unwrap_err();
+        assert!(matches!(err, CliError::Malformed(_)));
+    }
+
+    #[test]
+    fn test_skip_drops_ragged_and_invalid_utf8_rows() {
+        let bad_rows = BadRows::new(OnError::Skip, None).unwrap();
+        let data = b"a,b\n1,2\n3,4,5\n\xff,8\n6,7\n";
+        let mut csv = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(&data[..]);
+        let rows: Vec<StringRecord> = Records::new(&mut csv, Some(2), &bad_rows)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(rows, vec![vec!["1", "2"], vec!["6", "7"]]);
+        assert_eq!(bad_rows.skipped(), 2);
+        assert_eq!(
+            bad_rows.finish().unwrap().as_deref(),
+            Some("Skipped 2 malformed rows")
+        );
+    }
+
+    #[test]
+    fn test_unchecked_length_keeps_ragged_rows() {
+        let bad_rows = BadRows::new(OnError::Skip, None).unwrap();
+        let mut csv = reader("a,b\n1\n2,3,4\n", true);
+        let rows: Vec<StringRecord> = Records::new(&mut csv, None, &bad_rows)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(bad_rows.skipped(), 0);
+    }
+
+    #[test]
+    fn test_report_requires_file() {
+        let err = BadRows::new(OnError::Report, None).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+    }
+
+    #[test]
+    fn test_report_file_requires_report_policy() {
+        let err = BadRows::new(OnError::Skip, Some("bad.csv")).unwrap_err();
+        assert!(matches!(err, CliError::Usage(_)));
+    }
+}
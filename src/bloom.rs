@@ -0,0 +1,219 @@
+//! Bloom filter for `filter --bloom`: a fixed-size bit set answering "maybe
+//! present" or "certainly absent", so that millions of keys fit in a few
+//! megabytes at the cost of a chosen false-positive rate. Its "maybe"
+//! answers are confirmed against [`SortedValues`], the keys sorted on disk.
+
+use crate::error::{CliError, Result};
+use crate::input::CsvInput;
+use crate::sort::{sort, SortKey};
+use crate::utils::CsvOptions;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+/// Memory for sorting the keys of [`SortedValues`] before runs spill to disk.
+const SORT_MEMORY: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// A filter sized for `capacity` keys with a false-positive rate of about
+    /// `fp_rate` once full.
+    pub fn with_rate(capacity: usize, fp_rate: f64) -> BloomFilter {
+        let n = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-n * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 30.0) as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        for bit in self.bit_positions(key) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// False means `key` was never inserted; true means it probably was.
+    pub fn contains(&self, key: &str) -> bool {
+        self.bit_positions(key)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Size of the bit set in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// The `num_hashes` bits of `key`, derived from two hashes by double hashing.
+    fn bit_positions(&self, key: &str) -> impl Iterator<Item = u64> {
+        let h1 = hash(0, key);
+        let h2 = hash(1, key) | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+/// Distinct keys sorted into a temporary file, with the end offset of each
+/// in a second one, so that a key is looked up by binary search on disk.
+pub struct SortedValues {
+    keys: RefCell<File>,
+    ends: RefCell<File>,
+    len: u64,
+    /// First I/O error met by [`SortedValues::confirm`]
+    failure: RefCell<Option<CliError>>,
+}
+
+impl SortedValues {
+    /// Sort `keys` with an external merge sort, dropping duplicates.
+    pub fn new(keys: impl Iterator<Item = Result<String>>) -> Result<SortedValues> {
+        let mut unsorted = tempfile::tempfile()?;
+        {
+            let mut writer = csv::Writer::from_writer(BufWriter::new(&mut unsorted));
+            writer.write_record(["key"])?;
+            for key in keys {
+                writer.write_record([key?])?;
+            }
+            writer.flush()?;
+        }
+        unsorted.seek(SeekFrom::Start(0))?;
+        let input = CsvInput::with_delimiter(
+            Box::new(BufReader::new(unsorted)),
+            ',',
+            &CsvOptions::default(),
+        )?;
+        let order = SortKey::parse_list("key:lexical", &input.headers)?;
+
+        let mut sorted = SortedValues {
+            keys: RefCell::new(tempfile::tempfile()?),
+            ends: RefCell::new(tempfile::tempfile()?),
+            len: 0,
+            failure: RefCell::new(None),
+        };
+        {
+            let mut keys = BufWriter::new(sorted.keys.get_mut());
+            let mut ends = BufWriter::new(sorted.ends.get_mut());
+            let mut previous: Option<String> = None;
+            let mut end = 0u64;
+            for record in sort(input, &order, SORT_MEMORY, None)? {
+                let record = record?;
+                let key = record.get(0).unwrap_or("");
+                if previous.as_deref() == Some(key) {
+                    continue;
+                }
+                keys.write_all(key.as_bytes())?;
+                end += key.len() as u64;
+                ends.write_all(&end.to_le_bytes())?;
+                sorted.len += 1;
+                previous = Some(key.to_string());
+            }
+            keys.flush()?;
+            ends.flush()?;
+        }
+        Ok(sorted)
+    }
+
+    /// Whether `key` is one of the sorted keys.
+    pub fn contains(&self, key: &str) -> Result<bool> {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.key(mid)?.as_slice().cmp(key.as_bytes()) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+
+    /// [`SortedValues::contains`] for use in a predicate: an I/O error
+    /// counts as absent and is kept for [`SortedValues::finish`].
+    pub fn confirm(&self, key: &str) -> bool {
+        self.contains(key).unwrap_or_else(|e| {
+            self.failure.borrow_mut().get_or_insert(e);
+            false
+        })
+    }
+
+    /// The first error met by [`SortedValues::confirm`], if any.
+    pub fn finish(&self) -> Result<()> {
+        match self.failure.borrow_mut().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// The bytes of the `i`-th key.
+    fn key(&self, i: u64) -> Result<Vec<u8>> {
+        let mut ends = self.ends.borrow_mut();
+        let mut bounds = [0u8; 16];
+        let (start, end) = if i == 0 {
+            ends.seek(SeekFrom::Start(0))?;
+            ends.read_exact(&mut bounds[8..])?;
+            (0, u64::from_le_bytes(bounds[8..].try_into().unwrap()))
+        } else {
+            ends.seek(SeekFrom::Start((i - 1) * 8))?;
+            ends.read_exact(&mut bounds)?;
+            (
+                u64::from_le_bytes(bounds[..8].try_into().unwrap()),
+                u64::from_le_bytes(bounds[8..].try_into().unwrap()),
+            )
+        };
+        let mut key = vec![0; (end - start) as usize];
+        let mut keys = self.keys.borrow_mut();
+        keys.seek(SeekFrom::Start(start))?;
+        keys.read_exact(&mut key)?;
+        Ok(key)
+    }
+}
+
+fn hash(seed: u64, key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives_and_few_false_positives() {
+        let mut bloom = BloomFilter::with_rate(10_000, 0.01);
+        for i in 0..10_000 {
+            bloom.insert(&format!("key{}", i));
+        }
+        assert!((0..10_000).all(|i| bloom.contains(&format!("key{}", i))));
+
+        let false_positives = (0..10_000)
+            .filter(|i| bloom.contains(&format!("other{}", i)))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+        assert!(bloom.size_bytes() < 16 * 1024);
+    }
+
+    #[test]
+    fn test_sorted_values_are_exact() {
+        let keys = ["b", "a,\"quoted\"", "c", "b", "multi\nline"];
+        let sorted = SortedValues::new(keys.iter().map(|k| Ok(k.to_string()))).unwrap();
+        for key in keys {
+            assert!(sorted.contains(key).unwrap(), "{:?}", key);
+        }
+        for key in ["a", "bb", "d", "multi"] {
+            assert!(!sorted.contains(key).unwrap(), "{:?}", key);
+        }
+        assert!(sorted.finish().is_ok());
+    }
+}
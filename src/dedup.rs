@@ -0,0 +1,513 @@
+//! Duplicate removal on whole rows or key columns.
+//!
+//! A first pass counts the rows of each key, holding about `--memory` bytes
+//! of keys; beyond that they are partitioned by hash into temporary bucket
+//! files, each small enough to count on its own, so that no two rows with
+//! the same key land in different buckets. A second pass picks the rows.
+//! Stdin is spooled to a temporary file for it. The report takes one pass.
+
+use crate::error::Result;
+use crate::input::{counting_opts, CsvInput, IntoRecords, Source};
+use crate::selector::Selector;
+use crate::sort::temp_file;
+use crate::utils::CsvOptions;
+use clap::ValueEnum;
+use csv::StringRecord;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of bucket files the keys are partitioned into once they outgrow
+/// the memory budget.
+const BUCKETS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+    /// The first row of each group
+    #[default]
+    First,
+    /// The last row of each group
+    Last,
+    /// No row of a duplicated group: only rows whose key is unique
+    None,
+}
+
+/// What makes two rows duplicates, and which one survives.
+#[derive(Debug, Clone)]
+pub struct DedupSpec {
+    /// Key column selector; `None` compares whole rows
+    pub columns: Option<String>,
+    pub keep: Keep,
+    /// Bytes of keys held in memory before they spill to bucket files
+    pub memory: usize,
+    /// Directory for the bucket files (the system's temporary directory if `None`)
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl Default for DedupSpec {
+    fn default() -> Self {
+        DedupSpec {
+            columns: None,
+            keep: Keep::First,
+            memory: 512 * 1024 * 1024,
+            temp_dir: None,
+        }
+    }
+}
+
+/// The key fields of a record, each prefixed with its length.
+type Key = Box<[u8]>;
+
+/// Builds the key of a record.
+struct KeyMaker {
+    columns: Option<Vec<usize>>,
+}
+
+impl KeyMaker {
+    fn new(spec: &DedupSpec, headers: &StringRecord) -> Result<KeyMaker> {
+        let columns = match &spec.columns {
+            Some(columns) => Some(Selector::parse(columns)?.select(headers)?),
+            None => None,
+        };
+        Ok(KeyMaker { columns })
+    }
+
+    fn fields<'r>(&'r self, record: &'r StringRecord) -> Box<dyn Iterator<Item = &'r str> + 'r> {
+        match &self.columns {
+            Some(columns) => Box::new(columns.iter().map(|&i| record.get(i).unwrap_or(""))),
+            None => Box::new(record.iter()),
+        }
+    }
+
+    fn key(&self, record: &StringRecord) -> Key {
+        let mut bytes = Vec::new();
+        for field in self.fields(record) {
+            bytes.extend_from_slice(&(field.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(field.as_bytes());
+        }
+        bytes.into_boxed_slice()
+    }
+
+    /// The key fields of `record` as a record, for reports.
+    fn record(&self, record: &StringRecord) -> StringRecord {
+        self.fields(record).collect()
+    }
+
+    /// The names of the key columns.
+    fn headers(&self, headers: &StringRecord) -> StringRecord {
+        self.record(headers)
+    }
+}
+
+/// The fields of `key`, as a record.
+fn key_record(key: &[u8]) -> StringRecord {
+    let mut record = StringRecord::new();
+    let mut rest = key;
+    while rest.len() >= 8 {
+        let len = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+        record.push_field(&String::from_utf8_lossy(&rest[8..8 + len]));
+        rest = &rest[8 + len..];
+    }
+    record
+}
+
+/// The bucket file of `key`.
+fn bucket_of(key: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % BUCKETS as u64) as usize
+}
+
+/// Where and how often a key occurs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Group {
+    first: u64,
+    last: u64,
+    count: u64,
+}
+
+impl Group {
+    fn add(&mut self, other: Group) {
+        self.first = self.first.min(other.first);
+        self.last = self.last.max(other.last);
+        self.count += other.count;
+    }
+
+    /// The row of the group `keep` retains, if any.
+    fn kept(&self, keep: Keep) -> Option<u64> {
+        match keep {
+            Keep::First => Some(self.first),
+            Keep::Last => Some(self.last),
+            Keep::None => (self.count == 1).then_some(self.first),
+        }
+    }
+}
+
+/// Rough heap size of a key and its group in the counting map.
+fn footprint(key: &[u8]) -> usize {
+    key.len() + 64
+}
+
+/// The group of every key: in memory, or partitioned into bucket files once
+/// they outgrow the memory budget.
+struct Counts {
+    groups: HashMap<Key, Group>,
+    held: usize,
+    memory: usize,
+    temp_dir: PathBuf,
+    buckets: Vec<BufWriter<File>>,
+}
+
+impl Counts {
+    fn new(spec: &DedupSpec) -> Counts {
+        Counts {
+            groups: HashMap::new(),
+            held: 0,
+            memory: spec.memory,
+            temp_dir: spec.temp_dir.clone().unwrap_or_else(std::env::temp_dir),
+            buckets: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, key: Key, group: Group) -> Result<()> {
+        match self.groups.get_mut(&key) {
+            Some(known) => known.add(group),
+            None => {
+                self.held += footprint(&key);
+                self.groups.insert(key, group);
+                if self.held >= self.memory {
+                    self.spill()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the groups held in memory to their bucket files.
+    fn spill(&mut self) -> Result<()> {
+        if self.buckets.is_empty() {
+            for _ in 0..BUCKETS {
+                self.buckets
+                    .push(BufWriter::new(temp_file(&self.temp_dir)?));
+            }
+        }
+        for (key, group) in self.groups.drain() {
+            let bucket = &mut self.buckets[bucket_of(&key)];
+            for n in [group.first, group.last, group.count, key.len() as u64] {
+                bucket.write_all(&n.to_le_bytes())?;
+            }
+            bucket.write_all(&key)?;
+        }
+        self.held = 0;
+        Ok(())
+    }
+
+    /// The groups held in memory, or `None` if they were partitioned; then
+    /// call `f` with the groups of each bucket in turn.
+    fn finish(
+        mut self,
+        mut f: impl FnMut(HashMap<Key, Group>) -> Result<()>,
+    ) -> Result<Option<HashMap<Key, Group>>> {
+        if self.buckets.is_empty() {
+            return Ok(Some(self.groups));
+        }
+        self.spill()?;
+        for bucket in std::mem::take(&mut self.buckets) {
+            let mut file = bucket.into_inner().map_err(io::Error::from)?;
+            file.seek(SeekFrom::Start(0))?;
+            f(read_bucket(BufReader::new(file))?)?;
+        }
+        Ok(None)
+    }
+}
+
+/// Sum the groups written to a bucket file.
+fn read_bucket(mut reader: impl Read) -> Result<HashMap<Key, Group>> {
+    let mut groups: HashMap<Key, Group> = HashMap::new();
+    let mut header = [0u8; 32];
+    loop {
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(groups),
+            Err(e) => return Err(e.into()),
+        }
+        let n = |i: usize| u64::from_le_bytes(header[i * 8..i * 8 + 8].try_into().unwrap());
+        let group = Group {
+            first: n(0),
+            last: n(1),
+            count: n(2),
+        };
+        let mut key = vec![0; n(3) as usize];
+        reader.read_exact(&mut key)?;
+        match groups.get_mut(key.as_slice()) {
+            Some(known) => known.add(group),
+            None => {
+                groups.insert(key.into_boxed_slice(), group);
+            }
+        }
+    }
+}
+
+/// Count the records of `input` per key.
+fn count_keys(input: CsvInput, keys: &KeyMaker, spec: &DedupSpec) -> Result<Counts> {
+    let mut counts = Counts::new(spec);
+    for (row, record) in input.into_records().enumerate() {
+        let row = row as u64;
+        let group = Group {
+            first: row,
+            last: row,
+            count: 1,
+        };
+        counts.add(keys.key(&record?), group)?;
+    }
+    Ok(counts)
+}
+
+/// The deduplicated records, in input order.
+pub struct Dedup {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    records: IntoRecords,
+    keys: KeyMaker,
+    state: State,
+    row: u64,
+}
+
+enum State {
+    /// The group of each key
+    Memory(HashMap<Key, Group>, Keep),
+    /// The rows kept from each bucket, in ascending order
+    Buckets(Vec<KeptRows>),
+}
+
+/// A file of ascending row numbers, read one ahead.
+struct KeptRows {
+    reader: BufReader<File>,
+    next: Option<u64>,
+}
+
+impl KeptRows {
+    fn new(mut rows: Vec<u64>, dir: &Path) -> Result<KeptRows> {
+        rows.sort_unstable();
+        let mut file = temp_file(dir)?;
+        {
+            let mut writer = BufWriter::new(&mut file);
+            for row in rows {
+                writer.write_all(&row.to_le_bytes())?;
+            }
+            writer.flush()?;
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let mut kept = KeptRows {
+            reader: BufReader::new(file),
+            next: None,
+        };
+        kept.advance()?;
+        Ok(kept)
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        let mut row = [0u8; 8];
+        self.next = match self.reader.read_exact(&mut row) {
+            Ok(()) => Some(u64::from_le_bytes(row)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(())
+    }
+
+    /// Whether `row` is the next kept row, consuming it if so.
+    fn take(&mut self, row: u64) -> Result<bool> {
+        if self.next != Some(row) {
+            return Ok(false);
+        }
+        self.advance()?;
+        Ok(true)
+    }
+}
+
+/// Remove the duplicate rows of `path` (or stdin).
+pub fn dedup(path: Option<&str>, spec: &DedupSpec, opts: &CsvOptions) -> Result<Dedup> {
+    let (source, input) = Source::open(path, &counting_opts(opts)?)?;
+    let keys = KeyMaker::new(spec, &input.headers)?;
+    let delimiter = input.delimiter;
+    let counts = count_keys(input, &keys, spec)?;
+
+    let temp_dir = counts.temp_dir.clone();
+    let mut buckets = Vec::new();
+    let state = match counts.finish(|groups| {
+        let rows = groups.values().filter_map(|group| group.kept(spec.keep));
+        buckets.push(KeptRows::new(rows.collect(), &temp_dir)?);
+        Ok(())
+    })? {
+        Some(groups) => State::Memory(groups, spec.keep),
+        None => State::Buckets(buckets),
+    };
+
+    let input = source.reopen(delimiter, opts)?;
+    Ok(Dedup {
+        delimiter: input.delimiter,
+        headers: input.headers.clone(),
+        records: input.into_records(),
+        keys,
+        state,
+        row: 0,
+    })
+}
+
+impl Iterator for Dedup {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            let row = self.row;
+            self.row += 1;
+            let key = self.keys.key(&record);
+            let keep = match &mut self.state {
+                State::Memory(groups, keep) => groups[&key].kept(*keep) == Some(row),
+                State::Buckets(buckets) => match buckets[bucket_of(&key)].take(row) {
+                    Ok(keep) => keep,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            if keep {
+                return Some(Ok(record));
+            }
+        }
+    }
+}
+
+/// The keys found more than once, in order of first appearance.
+pub struct DuplicateGroups {
+    pub delimiter: char,
+    /// The key column names
+    pub headers: StringRecord,
+    /// Each duplicated key with its number of rows
+    pub groups: Vec<(StringRecord, usize)>,
+}
+
+/// Find the duplicate groups of `path` (or stdin).
+pub fn duplicate_groups(
+    path: Option<&str>,
+    spec: &DedupSpec,
+    opts: &CsvOptions,
+) -> Result<DuplicateGroups> {
+    let input = CsvInput::open(path, opts)?;
+    let keys = KeyMaker::new(spec, &input.headers)?;
+    let headers = keys.headers(&input.headers);
+    let delimiter = input.delimiter;
+
+    // Only the duplicated groups are kept, whether counted in memory or by bucket
+    let mut duplicated: Vec<(u64, Key, u64)> = Vec::new();
+    let mut collect = |groups: HashMap<Key, Group>| {
+        for (key, group) in groups {
+            if group.count > 1 {
+                duplicated.push((group.first, key, group.count));
+            }
+        }
+        Ok(())
+    };
+    if let Some(groups) = count_keys(input, &keys, spec)?.finish(&mut collect)? {
+        collect(groups)?;
+    }
+    duplicated.sort_unstable_by_key(|(first, _, _)| *first);
+    Ok(DuplicateGroups {
+        delimiter,
+        headers,
+        groups: duplicated
+            .into_iter()
+            .map(|(_, key, count)| (key_record(&key), count as usize))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(columns: Option<&str>) -> KeyMaker {
+        let spec = DedupSpec {
+            columns: columns.map(str::to_string),
+            ..DedupSpec::default()
+        };
+        KeyMaker::new(&spec, &StringRecord::from(vec!["a", "b", "c"])).unwrap()
+    }
+
+    #[test]
+    fn test_keys() {
+        let row = |fields: [&str; 3]| StringRecord::from(fields.to_vec());
+        let whole = keys(None);
+        assert_eq!(
+            whole.key(&row(["x", "y", "z"])),
+            whole.key(&row(["x", "y", "z"]))
+        );
+        assert_ne!(
+            whole.key(&row(["xy", "", "z"])),
+            whole.key(&row(["x", "y", "z"]))
+        );
+
+        let by_c = keys(Some("c,a"));
+        assert_eq!(
+            by_c.key(&row(["x", "1", "z"])),
+            by_c.key(&row(["x", "2", "z"]))
+        );
+        assert_ne!(
+            by_c.key(&row(["x", "1", "z"])),
+            by_c.key(&row(["z", "1", "x"]))
+        );
+        assert_eq!(
+            by_c.record(&row(["x", "y", "z"])),
+            StringRecord::from(vec!["z", "x"])
+        );
+        assert_eq!(
+            key_record(&by_c.key(&row(["x", "y", ""]))),
+            StringRecord::from(vec!["", "x"])
+        );
+    }
+
+    #[test]
+    fn test_spilled_counts_match_memory() {
+        let rows: Vec<u64> = (0..500).map(|i| i * 7 % 90).collect();
+        let count = |memory: usize| {
+            let spec = DedupSpec {
+                memory,
+                ..DedupSpec::default()
+            };
+            let mut counts = Counts::new(&spec);
+            for (row, value) in rows.iter().enumerate() {
+                let row = row as u64;
+                let group = Group {
+                    first: row,
+                    last: row,
+                    count: 1,
+                };
+                let key = value.to_le_bytes().to_vec().into_boxed_slice();
+                counts.add(key, group).unwrap();
+            }
+            let mut all = HashMap::new();
+            let mut buckets = 0;
+            if let Some(groups) = counts
+                .finish(|groups| {
+                    buckets += 1;
+                    all.extend(groups);
+                    Ok(())
+                })
+                .unwrap()
+            {
+                all.extend(groups);
+            }
+            (all, buckets)
+        };
+        let (in_memory, buckets) = count(usize::MAX);
+        assert_eq!((in_memory.len(), buckets), (90, 0));
+        let (spilled, buckets) = count(1000);
+        assert_eq!(buckets, BUCKETS);
+        assert_eq!(spilled, in_memory);
+    }
+}
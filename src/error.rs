@@ -0,0 +1,144 @@
+//! Error type shared by all commands, with one exit code per failure class.
+//!
+//! | Exit code | Meaning                                                   |
+//! |-----------|-----------------------------------------------------------|
+//! | 0         | Success (also when stdout is closed early, e.g. `\| head`) |
+//! | 1         | Any other error (I/O, mismatched inputs, ...)             |
+//! | 2         | Invalid usage: bad arguments or selector, no input given  |
+//! | 3         | Input file cannot be opened                               |
+//! | 4         | Column not found                                          |
+//! | 5         | Malformed CSV (parse error, invalid UTF-8, ragged row)    |
+//! | 6         | Empty input                                               |
+
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, CliError>;
+
+#[derive(Debug)]
+pub enum CliError {
+    /// Downstream reader closed the pipe; treated as a quiet success
+    BrokenPipe,
+    /// Invalid arguments, or no file given while stdin is a terminal
+    Usage(String),
+    /// The input file could not be opened
+    Open { path: String, source: io::Error },
+    /// A column reference did not match any header
+    ColumnNotFound(String),
+    /// The input is not valid CSV
+    Malformed(String),
+    /// The input holds no data
+    EmptyInput,
+    /// Any other I/O failure
+    Io(io::Error),
+    /// Any other failure
+    Other(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::BrokenPipe => 0,
+            CliError::Io(_) | CliError::Other(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Open { .. } => 3,
+            CliError::ColumnNotFound(_) => 4,
+            CliError::Malformed(_) => 5,
+            CliError::EmptyInput => 6,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::BrokenPipe => write!(f, "Broken pipe"),
+            CliError::Usage(msg)
+            | CliError::ColumnNotFound(msg)
+            | CliError::Malformed(msg)
+            | CliError::Other(msg) => write!(f, "{}", msg),
+            CliError::Open { path, source } => write!(f, "Cannot open file '{}': {}", path, source),
+            CliError::EmptyInput => write!(f, "File is empty or contains no data"),
+            CliError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            CliError::BrokenPipe
+        } else {
+            CliError::Io(e)
+        }
+    }
+}
+
+impl From<csv::Error> for CliError {
+    fn from(e: csv::Error) -> Self {
+        if e.is_io_error() {
+            match e.into_kind() {
+                csv::ErrorKind::Io(io) => CliError::from(io),
+                _ => unreachable!("is_io_error() implies ErrorKind::Io"),
+            }
+        } else {
+            CliError::Malformed(e.to_string())
+        }
+    }
+}
+
+impl From<String> for CliError {
+    fn from(msg: String) -> Self {
+        CliError::Other(msg)
+    }
+}
+
+impl From<&str> for CliError {
+    fn from(msg: &str) -> Self {
+        CliError::Other(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_pipe_is_quiet_success() {
+        let err = CliError::from(io::Error::new(io::ErrorKind::BrokenPipe, "pipe"));
+        assert!(matches!(err, CliError::BrokenPipe));
+        assert_eq!(err.exit_code(), 0);
+    }
+
+    #[test]
+    fn test_csv_parse_error_is_malformed() {
+        let mut csv = csv::ReaderBuilder::new().from_reader("a,b\n1,2,3\n".as_bytes());
+        let err = csv.records().next().unwrap().unwrap_err();
+        let err = CliError::from(err);
+        assert!(matches!(err, CliError::Malformed(_)));
+        assert_eq!(err.exit_code(), 5);
+    }
+
+    #[test]
+    fn test_distinct_exit_codes() {
+        let codes = [
+            CliError::Usage(String::new()).exit_code(),
+            CliError::Open {
+                path: String::new(),
+                source: io::Error::from(io::ErrorKind::NotFound),
+            }
+            .exit_code(),
+            CliError::ColumnNotFound(String::new()).exit_code(),
+            CliError::Malformed(String::new()).exit_code(),
+            CliError::EmptyInput.exit_code(),
+            CliError::Other(String::new()).exit_code(),
+        ];
+        for (i, a) in codes.iter().enumerate() {
+            for b in &codes[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,736 @@
+//! Row expressions for `filter --where`.
+//!
+//! ```text
+//! price > 100 && city in ('NYC', 'LA') && !is_empty(email)
+//! ```
+//!
+//! - Columns are referenced by name (`price`), or in backticks when the name
+//!   is not a plain identifier (`` `unit price` ``, `` `3` `` for the third column)
+//! - Literals: numbers (`42`, `-1.5e3`), strings in single or double quotes
+//!   (`\'` escapes a quote), `true`, `false` and `null`
+//! - Comparisons: `==` (or `=`), `!=` (or `<>`), `<`, `<=`, `>`, `>=`,
+//!   `x in (a, b, ...)` and `x not in (...)`
+//! - Boolean logic: `&&`/`and`, `||`/`or`, `!`/`not`, with parentheses
+//! - Functions: `contains(s, sub)`, `starts_with(s, prefix)`,
+//!   `ends_with(s, suffix)`, `lower(s)`, `upper(s)`, `trim(s)`, `len(s)`,
+//!   `is_null(x)` and `is_empty(x)`
+//!
+//! Empty fields, and fields missing from short rows, are `null`. Two values
+//! compare as numbers when both are numbers; a field or function result
+//! counts as a number when it parses as one, a quoted literal never does.
+//! Any other pair compares as strings, except that a number never equals a
+//! non-numeric string and `null` only equals `null`; ordering comparisons
+//! with either are false.
+
+use crate::error::{CliError, Result};
+use crate::selector::{find_index, find_name, not_found};
+use csv::StringRecord;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// A parsed expression, to be bound to headers with [`Expr::predicate`].
+#[derive(Debug, Clone)]
+pub struct Expr {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Literal),
+    /// Column reference, before binding
+    Column(String),
+    /// 0-based field index, after binding
+    Field(usize),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Compare(CmpOp, Box<Node>, Box<Node>),
+    In {
+        value: Box<Node>,
+        list: Vec<Node>,
+        negated: bool,
+    },
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Lower,
+    Upper,
+    Trim,
+    Len,
+    IsNull,
+    IsEmpty,
+}
+
+impl Func {
+    /// The function called `name`, with its number of arguments.
+    fn lookup(name: &str) -> Option<(Func, usize)> {
+        Some(match name {
+            "contains" => (Func::Contains, 2),
+            "starts_with" => (Func::StartsWith, 2),
+            "ends_with" => (Func::EndsWith, 2),
+            "lower" => (Func::Lower, 1),
+            "upper" => (Func::Upper, 1),
+            "trim" => (Func::Trim, 1),
+            "len" => (Func::Len, 1),
+            "is_null" => (Func::IsNull, 1),
+            "is_empty" => (Func::IsEmpty, 1),
+            _ => return None,
+        })
+    }
+}
+
+/// The value of a node for one record.
+#[derive(Debug)]
+enum Value<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    /// Quoted literal, never numeric
+    Str(&'a str),
+    /// Field or function result, numeric if it parses as a number
+    Text(Cow<'a, str>),
+}
+
+impl Value<'_> {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Text(s) => !s.is_empty(),
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value as a string for string functions; `None` for `null`.
+    fn text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Value::Null => None,
+            Value::Bool(b) => Some(Cow::Owned(b.to_string())),
+            Value::Number(n) => Some(Cow::Owned(n.to_string())),
+            Value::Str(s) => Some(Cow::Borrowed(s)),
+            Value::Text(s) => Some(Cow::Borrowed(s)),
+        }
+    }
+}
+
+/// Order two values, or `None` when they are not comparable.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => None,
+        (Value::Number(_), _) | (_, Value::Number(_)) => a.number()?.partial_cmp(&b.number()?),
+        (Value::Text(x), Value::Text(y)) => match (a.number(), b.number()) {
+            (Some(m), Some(n)) => m.partial_cmp(&n),
+            _ => Some(x.as_ref().cmp(y.as_ref())),
+        },
+        _ => Some(a.text()?.cmp(&b.text()?)),
+    }
+}
+
+impl CmpOp {
+    fn test(self, a: &Value, b: &Value) -> bool {
+        match compare(a, b) {
+            None => self == CmpOp::Ne,
+            Some(ordering) => match self {
+                CmpOp::Eq => ordering == Ordering::Equal,
+                CmpOp::Ne => ordering != Ordering::Equal,
+                CmpOp::Lt => ordering == Ordering::Less,
+                CmpOp::Le => ordering != Ordering::Greater,
+                CmpOp::Gt => ordering == Ordering::Greater,
+                CmpOp::Ge => ordering != Ordering::Less,
+            },
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            pos: 0,
+        };
+        let root = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error("expected an operator or the end of the expression"));
+        }
+        Ok(Expr { root })
+    }
+
+    /// Resolve the column references against `headers`, by name or else by
+    /// 1-based index, and return a predicate keeping the rows where the
+    /// expression is true (or a non-empty, non-zero value).
+    pub fn predicate(&self, headers: &StringRecord) -> Result<impl Fn(&StringRecord) -> bool> {
+        let root = bind(&self.root, headers)?;
+        Ok(move |record: &StringRecord| eval(&root, record).truthy())
+    }
+}
+
+fn bind(node: &Node, headers: &StringRecord) -> Result<Node> {
+    let bind_all =
+        |nodes: &[Node]| -> Result<Vec<Node>> { nodes.iter().map(|n| bind(n, headers)).collect() };
+    let boxed = |node: &Node| bind(node, headers).map(Box::new);
+    Ok(match node {
+        Node::Column(name) => Node::Field(
+            find_name(headers, name)
+                .or_else(|| find_index(headers, name))
+                .ok_or_else(|| not_found(headers, name))?,
+        ),
+        Node::Literal(_) | Node::Field(_) => node.clone(),
+        Node::Not(inner) => Node::Not(boxed(inner)?),
+        Node::And(a, b) => Node::And(boxed(a)?, boxed(b)?),
+        Node::Or(a, b) => Node::Or(boxed(a)?, boxed(b)?),
+        Node::Compare(op, a, b) => Node::Compare(*op, boxed(a)?, boxed(b)?),
+        Node::In {
+            value,
+            list,
+            negated,
+        } => Node::In {
+            value: boxed(value)?,
+            list: bind_all(list)?,
+            negated: *negated,
+        },
+        Node::Call(func, args) => Node::Call(*func, bind_all(args)?),
+    })
+}
+
+fn eval<'a>(node: &'a Node, record: &'a StringRecord) -> Value<'a> {
+    match node {
+        Node::Literal(Literal::Null) => Value::Null,
+        Node::Literal(Literal::Bool(b)) => Value::Bool(*b),
+        Node::Literal(Literal::Number(n)) => Value::Number(*n),
+        Node::Literal(Literal::Str(s)) => Value::Str(s),
+        Node::Field(idx) => match record.get(*idx) {
+            None | Some("") => Value::Null,
+            Some(field) => Value::Text(Cow::Borrowed(field)),
+        },
+        Node::Column(name) => unreachable!("column '{}' evaluated before binding", name),
+        Node::Not(inner) => Value::Bool(!eval(inner, record).truthy()),
+        Node::And(a, b) => Value::Bool(eval(a, record).truthy() && eval(b, record).truthy()),
+        Node::Or(a, b) => Value::Bool(eval(a, record).truthy() || eval(b, record).truthy()),
+        Node::Compare(op, a, b) => Value::Bool(op.test(&eval(a, record), &eval(b, record))),
+        Node::In {
+            value,
+            list,
+            negated,
+        } => {
+            let value = eval(value, record);
+            let found = list
+                .iter()
+                .any(|item| CmpOp::Eq.test(&value, &eval(item, record)));
+            Value::Bool(found != *negated)
+        }
+        Node::Call(func, args) => call(*func, args, record),
+    }
+}
+
+fn call<'a>(func: Func, args: &'a [Node], record: &'a StringRecord) -> Value<'a> {
+    let values: Vec<Value> = args.iter().map(|arg| eval(arg, record)).collect();
+    let texts: Vec<Option<Cow<str>>> = values.iter().map(Value::text).collect();
+    let both = |test: fn(&str, &str) -> bool| match (&texts[0], &texts[1]) {
+        (Some(s), Some(t)) => Value::Bool(test(s, t)),
+        _ => Value::Bool(false),
+    };
+    let map = |f: fn(&str) -> String| match &texts[0] {
+        Some(s) => Value::Text(Cow::Owned(f(s))),
+        None => Value::Null,
+    };
+    match func {
+        Func::Contains => both(|s, t| s.contains(t)),
+        Func::StartsWith => both(|s, t| s.starts_with(t)),
+        Func::EndsWith => both(|s, t| s.ends_with(t)),
+        Func::Lower => map(str::to_lowercase),
+        Func::Upper => map(str::to_uppercase),
+        Func::Trim => map(|s| s.trim().to_string()),
+        Func::Len => Value::Number(texts[0].as_ref().map_or(0, |s| s.chars().count()) as f64),
+        Func::IsNull => Value::Bool(matches!(values[0], Value::Null)),
+        Func::IsEmpty => Value::Bool(texts[0].as_ref().is_none_or(|s| s.trim().is_empty())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    /// Backtick-quoted column name
+    Quoted(String),
+    Op(&'static str),
+}
+
+/// A token with its span in characters.
+#[derive(Debug)]
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+const OPERATORS: [&str; 15] = [
+    "==", "!=", "<>", "<=", ">=", "&&", "||", "=", "<", ">", "!", "(", ")", ",", "-",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let tok = if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            match text.parse() {
+                Ok(n) => Tok::Number(n),
+                Err(_) => {
+                    return Err(syntax_error(
+                        source,
+                        start,
+                        i,
+                        &format!("invalid number '{}'", text),
+                    ))
+                }
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Tok::Ident(chars[start..i].iter().collect())
+        } else if c == '\'' || c == '"' || c == '`' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        let what = if c == '`' { "column name" } else { "string" };
+                        return Err(syntax_error(
+                            source,
+                            start,
+                            start + 1,
+                            &format!("unterminated {}", what),
+                        ));
+                    }
+                    Some(&q) if q == c => break,
+                    Some('\\') if c != '`' && i + 1 < chars.len() => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&other) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            if c == '`' {
+                Tok::Quoted(text)
+            } else {
+                Tok::Str(text)
+            }
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => {
+                    i += op.len();
+                    Tok::Op(op)
+                }
+                None => {
+                    return Err(syntax_error(
+                        source,
+                        start,
+                        start + 1,
+                        &format!("unexpected character '{}'", c),
+                    ))
+                }
+            }
+        };
+        tokens.push(Token { tok, start, end: i });
+    }
+    Ok(tokens)
+}
+
+/// A usage error quoting `source` with a caret under characters `start..end`.
+fn syntax_error(source: &str, start: usize, end: usize, message: &str) -> CliError {
+    CliError::Usage(format!(
+        "Invalid expression: {}\n  {}\n  {}{}",
+        message,
+        source,
+        " ".repeat(start),
+        "^".repeat((end - start).max(1))
+    ))
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn is_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Op(o)) if *o == op)
+    }
+
+    fn is_keyword(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(w)) if w == word)
+    }
+
+    /// Consume the operator `op`, or its keyword spelling `word`.
+    fn eat(&mut self, op: &str, word: &str) -> bool {
+        let found = self.is_op(op) || self.is_keyword(word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        if self.is_op(op) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", op)))
+        }
+    }
+
+    /// An error pointing at the current token, or at the end of the source.
+    fn error(&self, message: &str) -> CliError {
+        let chars = self.source.chars().count();
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                let text: String = self
+                    .source
+                    .chars()
+                    .skip(token.start)
+                    .take(token.end - token.start)
+                    .collect();
+                syntax_error(
+                    self.source,
+                    token.start,
+                    token.end,
+                    &format!("{}, found '{}'", message, text),
+                )
+            }
+            None => syntax_error(
+                self.source,
+                chars,
+                chars + 1,
+                &format!("{}, found the end of the expression", message),
+            ),
+        }
+    }
+
+    fn or(&mut self) -> Result<Node> {
+        let mut node = self.and()?;
+        while self.eat("||", "or") {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node> {
+        let mut node = self.not()?;
+        while self.eat("&&", "and") {
+            node = Node::And(Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node> {
+        if self.eat("!", "not") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node> {
+        let left = self.value()?;
+        let op = match self.peek() {
+            Some(Tok::Op("==")) | Some(Tok::Op("=")) => CmpOp::Eq,
+            Some(Tok::Op("!=")) | Some(Tok::Op("<>")) => CmpOp::Ne,
+            Some(Tok::Op("<")) => CmpOp::Lt,
+            Some(Tok::Op("<=")) => CmpOp::Le,
+            Some(Tok::Op(">")) => CmpOp::Gt,
+            Some(Tok::Op(">=")) => CmpOp::Ge,
+            Some(Tok::Ident(w)) if w == "in" || w == "not" => {
+                let negated = w == "not";
+                self.pos += 1;
+                if negated && !self.eat("", "in") {
+                    return Err(self.error("expected 'in' after 'not'"));
+                }
+                return Ok(Node::In {
+                    value: Box::new(left),
+                    list: self.list()?,
+                    negated,
+                });
+            }
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Node::Compare(op, Box::new(left), Box::new(self.value()?)))
+    }
+
+    /// A parenthesized, comma-separated list of values.
+    fn list(&mut self) -> Result<Vec<Node>> {
+        self.expect("(")?;
+        let mut items = vec![self.value()?];
+        while self.is_op(",") {
+            self.pos += 1;
+            items.push(self.value()?);
+        }
+        self.expect(")")?;
+        Ok(items)
+    }
+
+    fn value(&mut self) -> Result<Node> {
+        let node = match self.peek() {
+            Some(Tok::Number(n)) => Node::Literal(Literal::Number(*n)),
+            Some(Tok::Str(s)) => Node::Literal(Literal::Str(s.clone())),
+            Some(Tok::Quoted(name)) => Node::Column(name.clone()),
+            Some(Tok::Op("-")) => {
+                self.pos += 1;
+                return match self.peek() {
+                    Some(Tok::Number(n)) => {
+                        let n = -n;
+                        self.pos += 1;
+                        Ok(Node::Literal(Literal::Number(n)))
+                    }
+                    _ => Err(self.error("expected a number after '-'")),
+                };
+            }
+            Some(Tok::Op("(")) => {
+                self.pos += 1;
+                let node = self.or()?;
+                self.expect(")")?;
+                return Ok(node);
+            }
+            Some(Tok::Ident(word)) => match word.as_str() {
+                "true" => Node::Literal(Literal::Bool(true)),
+                "false" => Node::Literal(Literal::Bool(false)),
+                "null" => Node::Literal(Literal::Null),
+                "and" | "or" | "not" | "in" => return Err(self.error("expected a value")),
+                _ if self
+                    .tokens
+                    .get(self.pos + 1)
+                    .is_some_and(|t| t.tok == Tok::Op("(")) =>
+                {
+                    return self.call();
+                }
+                _ => Node::Column(word.clone()),
+            },
+            _ => return Err(self.error("expected a value")),
+        };
+        self.pos += 1;
+        Ok(node)
+    }
+
+    fn call(&mut self) -> Result<Node> {
+        let name = match self.peek() {
+            Some(Tok::Ident(name)) => name.clone(),
+            _ => unreachable!("call() is only entered on an identifier"),
+        };
+        let (func, arity) = Func::lookup(&name).ok_or_else(|| {
+            self.error(
+                "unknown function; expected contains, starts_with, ends_with, \
+                 lower, upper, trim, len, is_null or is_empty",
+            )
+        })?;
+        let name_pos = self.pos;
+        self.pos += 1;
+        let args = self.list()?;
+        if args.len() != arity {
+            self.pos = name_pos;
+            return Err(self.error(&format!(
+                "{}() takes {} argument{} but {} were given",
+                name,
+                arity,
+                if arity == 1 { "" } else { "s" },
+                args.len()
+            )));
+        }
+        Ok(Node::Call(func, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(expr: &str, headers: &[&str], rows: &[&[&str]]) -> Vec<usize> {
+        let headers = StringRecord::from(headers.to_vec());
+        let predicate = Expr::parse(expr).unwrap().predicate(&headers).unwrap();
+        rows.iter()
+            .enumerate()
+            .filter(|(_, row)| predicate(&StringRecord::from(row.to_vec())))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn parse_error(expr: &str) -> String {
+        Expr::parse(expr).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        let rows: &[&[&str]] = &[&["9", "b"], &["10", "a"], &["", "c"], &["x", "d"]];
+        assert_eq!(matching("n > 9", &["n", "s"], rows), vec![1]);
+        // A quoted literal compares as a string
+        assert_eq!(matching("n > '9'", &["n", "s"], rows), vec![3]);
+        assert_eq!(matching("n < 100", &["n", "s"], rows), vec![0, 1]);
+        assert_eq!(matching("n != 9", &["n", "s"], rows), vec![1, 2, 3]);
+        assert_eq!(matching("n == null", &["n", "s"], rows), vec![2]);
+        assert_eq!(matching("s >= 'b'", &["n", "s"], rows), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_fields_compare_as_numbers_when_both_are() {
+        let rows: &[&[&str]] = &[&["9", "10"], &["b", "a"], &["9", "a"]];
+        assert_eq!(matching("x < y", &["x", "y"], rows), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_boolean_logic_and_in() {
+        let headers = ["price", "city", "email"];
+        let rows: &[&[&str]] = &[
+            &["150", "NYC", "a@x.com"],
+            &["150", "LA", ""],
+            &["50", "LA", "b@x.com"],
+            &["200", "Boston", "c@x.com"],
+        ];
+        assert_eq!(
+            matching(
+                "price > 100 && city in ('NYC','LA') && !is_empty(email)",
+                &headers,
+                rows
+            ),
+            vec![0]
+        );
+        assert_eq!(
+            matching("city not in ('NYC', 'LA') or price < 100", &headers, rows),
+            vec![2, 3]
+        );
+        assert_eq!(
+            matching(
+                "(city = 'LA' || city = 'NYC') and not price > 100",
+                &headers,
+                rows
+            ),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_string_functions() {
+        let rows: &[&[&str]] = &[&["Alice Smith"], &["bob"], &[""]];
+        assert_eq!(
+            matching("contains(lower(name), 'smith')", &["name"], rows),
+            vec![0]
+        );
+        assert_eq!(matching("starts_with(name, 'b')", &["name"], rows), vec![1]);
+        assert_eq!(matching("upper(name) == 'BOB'", &["name"], rows), vec![1]);
+        assert_eq!(matching("len(name) > 3", &["name"], rows), vec![0]);
+        assert_eq!(matching("is_null(name)", &["name"], rows), vec![2]);
+    }
+
+    #[test]
+    fn test_quoted_and_indexed_columns() {
+        let rows: &[&[&str]] = &[&["1", "2"], &["3", "4"]];
+        assert_eq!(
+            matching("`unit price` > 2", &["unit price", "b"], rows),
+            vec![1]
+        );
+        assert_eq!(matching("`2` == 2", &["a", "b"], rows), vec![0]);
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let headers = StringRecord::from(vec!["a"]);
+        let err = Expr::parse("b > 1")
+            .unwrap()
+            .predicate(&headers)
+            .err()
+            .unwrap();
+        assert_eq!(err.exit_code(), 4);
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_token() {
+        assert_eq!(
+            parse_error("price >> 3"),
+            "Invalid expression: expected a value, found '>'\n  price >> 3\n         ^"
+        );
+        assert_eq!(
+            parse_error("a == 'x"),
+            "Invalid expression: unterminated string\n  a == 'x\n       ^"
+        );
+        assert!(parse_error("a in 'x'").contains("expected '(', found ''x''"));
+        assert!(parse_error("price > ").contains("found the end of the expression"));
+        assert!(parse_error("foo(a)").contains("unknown function"));
+        assert!(
+            parse_error("contains(a)").contains("contains() takes 2 arguments but 1 were given")
+        );
+        assert!(parse_error("a b").contains("^"));
+        assert!(Expr::parse("a == 1").is_ok());
+    }
+}
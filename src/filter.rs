@@ -1,75 +1,176 @@
-use crate::utils::{csv_writer, detect_delimiter, input_reader};
-use csv::ReaderBuilder;
+use crate::bloom::{BloomFilter, SortedValues};
+use crate::error::{CliError, Result};
+use crate::input::CsvInput;
+use crate::selector::Selector;
+use crate::utils::{input_reader, CsvOptions};
+use csv::StringRecord;
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
 use std::collections::HashSet;
-use std::error::Error;
-use std::io;
-
-pub fn filter_rows(
-    path: Option<&str>,
-    column: &str,
-    values: &str,
-    include_header: bool,
-) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-
-    let delimiter = detect_delimiter(&mut *reader)?;
-
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers()?.clone();
-
-    // Find the column index
-    let col_idx = headers.iter().position(|h| h == column).ok_or_else(|| {
-        let available: Vec<&str> = headers.iter().collect();
-        format!(
-            "\nColumn '{}' not found in CSV.\nAvailable columns: {}",
-            column,
-            available.join(", ")
-        )
-    })?;
-
-    // Parse the filter values into a HashSet for efficient lookup
-    let filter_values: HashSet<String> = values.split(',').map(|v| v.trim().to_string()).collect();
-
-    // Create CSV writer for proper quoting
-    let stdout = io::stdout();
-    let mut writer = csv_writer(stdout.lock(), delimiter);
-
-    // Print header if requested
-    if include_header {
-        writer.write_record(headers.iter().collect::<Vec<_>>())?;
+use std::io::BufRead;
+use std::rc::Rc;
+
+/// A boxed row predicate, for choosing between kinds of condition at runtime.
+pub type Predicate = Box<dyn Fn(&StringRecord) -> bool>;
+
+/// Parse a comma-separated `--value` list into the set of values to match.
+pub fn parse_values(values: &str) -> HashSet<String> {
+    values.split(',').map(|v| v.trim().to_string()).collect()
+}
+
+/// A predicate matching records whose field `col_idx` is one of `values`.
+pub fn value_in(col_idx: usize, values: HashSet<String>) -> impl Fn(&StringRecord) -> bool {
+    move |record| {
+        record
+            .get(col_idx)
+            .is_some_and(|cell_value| values.contains(cell_value))
+    }
+}
+
+/// Like [`value_in`], ignoring case.
+pub fn value_in_any_case(
+    col_idx: usize,
+    values: HashSet<String>,
+) -> impl Fn(&StringRecord) -> bool {
+    let values: HashSet<String> = values.iter().map(|v| v.to_lowercase()).collect();
+    move |record| {
+        record
+            .get(col_idx)
+            .is_some_and(|cell_value| values.contains(&cell_value.to_lowercase()))
     }
+}
 
-    // Filter and print matching rows
-    let mut match_count = 0;
-    for result in csv.records() {
-        let record = result?;
-
-        // Check if the value in the specified column matches any filter value
-        if let Some(cell_value) = record.get(col_idx) {
-            if filter_values.contains(cell_value) {
-                writer.write_record(record.iter().collect::<Vec<_>>())?;
-                match_count += 1;
-            }
+/// The values listed in `path` for `--values-file`: one per line, or the
+/// field `column` of each record when `path` is a CSV. Values are trimmed
+/// and empty ones skipped. Nothing is held in memory.
+pub fn read_values(
+    path: &str,
+    column: Option<&str>,
+    opts: &CsvOptions,
+) -> Result<Box<dyn Iterator<Item = Result<String>>>> {
+    let values: Box<dyn Iterator<Item = Result<String>>> = match column {
+        None => Box::new(
+            input_reader(Some(path), opts.encoding)?
+                .lines()
+                .map(|line| Ok(line?.trim().to_string())),
+        ),
+        Some(column) => {
+            let input = CsvInput::open(Some(path), opts)?;
+            let col_idx = Selector::parse(column)?.select_one(&input.headers)?;
+            Box::new(
+                input
+                    .into_records()
+                    .map(move |record| Ok(record?.get(col_idx).unwrap_or("").trim().to_string())),
+            )
         }
+    };
+    Ok(Box::new(values.filter(|value| {
+        value.as_ref().map_or(true, |v| !v.is_empty())
+    })))
+}
+
+/// A predicate matching records whose field `col_idx` is in `bloom`,
+/// lowercased first with `ignore_case`. The filter's "maybe" answers are
+/// confirmed against `values`, the same keys sorted on disk.
+pub fn value_in_bloom(
+    col_idx: usize,
+    bloom: BloomFilter,
+    values: Rc<SortedValues>,
+    ignore_case: bool,
+) -> impl Fn(&StringRecord) -> bool {
+    move |record| {
+        record.get(col_idx).is_some_and(|cell_value| {
+            let key = if ignore_case {
+                Cow::Owned(cell_value.to_lowercase())
+            } else {
+                Cow::Borrowed(cell_value)
+            };
+            bloom.contains(&key) && values.confirm(&key)
+        })
     }
+}
 
-    // Inform user if no matches were found
-    if match_count == 0 {
-        eprintln!("\nWarning: No rows matched the filter criteria.");
-        eprintln!(
-            "Column: '{}', Values: {}",
-            column,
-            filter_values
-                .iter()
-                .map(|s| format!("'{}'", s))
-                .collect::<Vec<_>>()
-                .join(", ")
+/// Compile a `--regex` pattern.
+pub fn parse_regex(pattern: &str, ignore_case: bool) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| CliError::Usage(format!("Invalid regex '{}': {}", pattern, e)))
+}
+
+/// A predicate matching records whose field `col_idx` contains a match of `re`.
+pub fn matches_regex(col_idx: usize, re: Regex) -> impl Fn(&StringRecord) -> bool {
+    move |record| {
+        record
+            .get(col_idx)
+            .is_some_and(|cell_value| re.is_match(cell_value))
+    }
+}
+
+/// A predicate matching records that match every one of `predicates`.
+pub fn all(predicates: Vec<Predicate>) -> impl Fn(&StringRecord) -> bool {
+    move |record| predicates.iter().all(|p| p(record))
+}
+
+/// A predicate matching records that match at least one of `predicates`.
+pub fn any(predicates: Vec<Predicate>) -> impl Fn(&StringRecord) -> bool {
+    move |record| predicates.iter().any(|p| p(record))
+}
+
+/// A predicate matching the records `predicate` rejects.
+pub fn not(predicate: Predicate) -> impl Fn(&StringRecord) -> bool {
+    move |record| !predicate(record)
+}
+
+/// Keep the records matching `predicate`. Errors are passed through.
+pub fn filter<I, P>(records: I, predicate: P) -> impl Iterator<Item = Result<StringRecord>>
+where
+    I: Iterator<Item = Result<StringRecord>>,
+    P: Fn(&StringRecord) -> bool,
+{
+    records.filter(move |result| result.as_ref().map_or(true, &predicate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_keeps_matches_and_errors() {
+        let records = vec![
+            Ok(StringRecord::from(vec!["1", "a"])),
+            Ok(StringRecord::from(vec!["2", "b"])),
+            Err("bad row".into()),
+            Ok(StringRecord::from(vec!["3", "c"])),
+        ];
+        let kept: Vec<_> = filter(records.into_iter(), value_in(1, parse_values("a, c")))
+            .map(|r| r.map(|rec| rec[0].to_string()).map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                Ok("1".to_string()),
+                Err("bad row".to_string()),
+                Ok("3".to_string())
+            ]
         );
     }
 
-    Ok(())
+    #[test]
+    fn test_combinators() {
+        let record = StringRecord::from(vec!["NYC", "Alice"]);
+        let city: Predicate = Box::new(value_in_any_case(0, parse_values("nyc")));
+        let name: Predicate = Box::new(matches_regex(1, parse_regex("^B", false).unwrap()));
+        assert!(city(&record));
+        assert!(!name(&record));
+        assert!(any(vec![city, name])(&record));
+
+        let city: Predicate = Box::new(value_in(0, parse_values("nyc")));
+        let name: Predicate = Box::new(matches_regex(1, parse_regex("^a", true).unwrap()));
+        // Exact matching is case-sensitive, so only the inverted city matches
+        let both = all(vec![Box::new(not(city)), name]);
+        assert!(both(&StringRecord::from(vec!["NYC", "Al"])));
+        assert!(!both(&StringRecord::from(vec!["NYC", "Bo"])));
+        assert!(parse_regex("(", false).is_err());
+    }
 }
@@ -1,42 +1,92 @@
-use crate::utils::{detect_delimiter, input_reader};
-use colored::Colorize;
-use csv::ReaderBuilder;
+use crate::error::Result;
+use crate::input::CsvInput;
+use crate::parallel::fold_chunks;
+use crate::selector::Selector;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::error::Error;
-
-pub fn freq(
-    path: Option<&str>,
-    column: &str,
-    plot: bool,
-    sort_index: bool,
-) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-    let delimiter = detect_delimiter(&mut *reader)?;
-
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers()?.clone();
-
-    // Find column index
-    let col_idx = headers.iter().position(|h| h == column).ok_or_else(|| {
-        let available: Vec<&str> = headers.iter().collect();
-        format!(
-            "Column '{}' not found in CSV.\nAvailable columns: {}",
-            column,
-            available.join(", ")
-        )
-    })?;
-
-    // Count frequencies with progress indicator
+
+/// Distinct values of one column with their number of occurrences.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreqTable {
+    pub column: String,
+    /// Sorted by count (high to low), ties by value, unless re-sorted by `sort_by_value`.
+    pub counts: Vec<(String, usize)>,
+}
+
+impl FreqTable {
+    /// Number of values counted.
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Sort by value instead: numerically if all values are numbers,
+    /// alphabetically otherwise.
+    pub fn sort_by_value(&mut self) {
+        let all_numeric = self
+            .counts
+            .iter()
+            .all(|(val, _)| val.trim().parse::<f64>().is_ok());
+
+        if all_numeric {
+            self.counts.sort_by(|a, b| {
+                let a_num = a.0.trim().parse::<f64>().unwrap();
+                let b_num = b.0.trim().parse::<f64>().unwrap();
+                a_num.partial_cmp(&b_num).unwrap()
+            });
+        } else {
+            self.counts.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+    }
+}
+
+/// Count the values of the column of `input` selected by `column`.
+pub fn freq(mut input: CsvInput, column: &str, opts: &CsvOptions) -> Result<FreqTable> {
+    // Find the column index
+    let col_idx = Selector::parse(column)?.select_one(&input.headers)?;
+    let num_fields = input.headers.len();
+
+    // Count frequencies, in parallel chunks when the input allows it
+    let count = |counts: &mut HashMap<String, usize>, record: &StringRecord| {
+        if let Some(val) = record.get(col_idx) {
+            *counts.entry(val.to_string()).or_insert(0) += 1;
+        }
+    };
+    let path = input.path.clone();
+    let freq_map = match fold_chunks(
+        path.as_deref(),
+        input.delimiter,
+        Some(num_fields),
+        opts,
+        HashMap::new,
+        count,
+    )? {
+        Some(chunks) => merge_counts(chunks),
+        None => count_rows(&mut input, count, opts)?,
+    };
+
+    // Sort by frequency (high to low), ties by value
+    let mut counts: Vec<(String, usize)> = freq_map.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(FreqTable {
+        column: input.headers.get(col_idx).unwrap_or("").to_string(),
+        counts,
+    })
+}
+
+/// Count values by reading every record of `csv` in turn.
+fn count_rows(
+    input: &mut CsvInput,
+    count: impl Fn(&mut HashMap<String, usize>, &StringRecord),
+    opts: &CsvOptions,
+) -> Result<HashMap<String, usize>> {
     let mut freq_map: HashMap<String, usize> = HashMap::new();
     let mut row_count = 0;
 
-    // Create progress spinner (only shows if stderr is a TTY)
-    let spinner = if atty::is(atty::Stream::Stderr) {
+    // Create progress spinner (only shows if requested, i.e. stderr is a TTY)
+    let spinner = if opts.progress {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -50,11 +100,9 @@ pub fn freq(
         None
     };
 
-    for result in csv.records() {
+    for result in input.records() {
         let record = result?;
-        if let Some(val) = record.get(col_idx) {
-            *freq_map.entry(val.to_string()).or_insert(0) += 1;
-        }
+        count(&mut freq_map, &record);
         row_count += 1;
 
         // Update spinner every 1000 rows
@@ -71,156 +119,44 @@ pub fn freq(
         pb.finish_and_clear();
     }
 
-    // Convert to vector for sorting
-    let mut freq_vec: Vec<(String, usize)> = freq_map.into_iter().collect();
-
-    // Sort: by index if requested, otherwise by frequency (default)
-    if sort_index {
-        // Try to sort numerically if all values are numeric, otherwise alphabetically
-        let all_numeric = freq_vec
-            .iter()
-            .all(|(val, _)| val.trim().parse::<f64>().is_ok());
+    Ok(freq_map)
+}
 
-        if all_numeric {
-            freq_vec.sort_by(|a, b| {
-                let a_num = a.0.trim().parse::<f64>().unwrap();
-                let b_num = b.0.trim().parse::<f64>().unwrap();
-                a_num.partial_cmp(&b_num).unwrap()
-            });
-        } else {
-            freq_vec.sort_by(|a, b| a.0.cmp(&b.0));
+/// Sum the counts of each chunk.
+fn merge_counts(chunks: Vec<HashMap<String, usize>>) -> HashMap<String, usize> {
+    let mut chunks = chunks.into_iter();
+    let mut total = chunks.next().unwrap_or_default();
+    for chunk in chunks {
+        for (val, n) in chunk {
+            *total.entry(val).or_insert(0) += n;
         }
-    } else {
-        // Default: sort by frequency (high to low)
-        freq_vec.sort_by(|a, b| b.1.cmp(&a.1));
-    }
-
-    // Display results
-    if plot {
-        print_with_plot(&freq_vec);
-    } else {
-        print_without_plot(&freq_vec);
     }
-
-    Ok(())
+    total
 }
 
-fn print_without_plot(freq_vec: &[(String, usize)]) {
-    if freq_vec.is_empty() {
-        return;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Find max value length for alignment
-    let max_val_len = freq_vec.iter().map(|(val, _)| val.len()).max().unwrap_or(0);
-
-    // Find max count length for alignment
-    let max_count_len = freq_vec
-        .iter()
-        .map(|(_, count)| count.to_string().len())
-        .max()
-        .unwrap_or(0);
-
-    // Calculate total for percentages
-    let total: usize = freq_vec.iter().map(|(_, count)| *count).sum();
-
-    // Print header
-    println!(
-        "{:<val_width$}  {:>count_width$}  {:>7}  {:>11}",
-        "Value".green().bold(),
-        "Count".green().bold(),
-        "Pct".green().bold(),
-        "CumPct".green().bold(),
-        val_width = max_val_len.max(5),
-        count_width = max_count_len.max(5)
-    );
-
-    // Track cumulative percentage
-    let mut cumulative = 0;
-
-    for (val, count) in freq_vec {
-        // Calculate percentages
-        let percentage = (*count as f64 / total as f64) * 100.0;
-        cumulative += count;
-        let cumulative_pct = (cumulative as f64 / total as f64) * 100.0;
-
-        println!(
-            "{:<val_width$}  {:>count_width$}  {:>7.2}%  {:>10.2}%",
-            val,
-            count,
-            percentage,
-            cumulative_pct,
-            val_width = max_val_len.max(5),
-            count_width = max_count_len.max(5)
-        );
+    fn table(counts: &[(&str, usize)]) -> FreqTable {
+        FreqTable {
+            column: "x".to_string(),
+            counts: counts.iter().map(|&(v, n)| (v.to_string(), n)).collect(),
+        }
     }
-}
 
-fn print_with_plot(freq_vec: &[(String, usize)]) {
-    if freq_vec.is_empty() {
-        return;
+    #[test]
+    fn test_sort_by_value_numeric() {
+        let mut freq = table(&[("10", 1), ("9", 3), ("-1", 2)]);
+        freq.sort_by_value();
+        assert_eq!(freq, table(&[("-1", 2), ("9", 3), ("10", 1)]));
+        assert_eq!(freq.total(), 6);
     }
 
-    // Find max count for scaling
-    let max_count = freq_vec.iter().map(|(_, count)| *count).max().unwrap_or(1);
-
-    // Find max value length for alignment
-    let max_val_len = freq_vec.iter().map(|(val, _)| val.len()).max().unwrap_or(0);
-
-    // Find max count length for alignment
-    let max_count_len = freq_vec
-        .iter()
-        .map(|(_, count)| count.to_string().len())
-        .max()
-        .unwrap_or(0);
-
-    // Calculate total for percentages
-    let total: usize = freq_vec.iter().map(|(_, count)| *count).sum();
-
-    // Bar width: scale to max 50 characters (not including the | borders)
-    let max_bar_width = 50;
-
-    // Print header
-    let bar_header = format!("|{:^50}|", "");
-    println!(
-        "{:<val_width$}  {}  {:>count_width$}  {:>9}  {:>15}",
-        "Value".green().bold(),
-        bar_header,
-        "Count".green().bold(),
-        "Pct".green().bold(),
-        "CumPct".green().bold(),
-        val_width = max_val_len,
-        count_width = max_count_len
-    );
-
-    // Track cumulative percentage
-    let mut cumulative = 0;
-
-    for (val, count) in freq_vec {
-        let bar_width = if max_count > 0 {
-            ((*count as f64 / max_count as f64) * max_bar_width as f64).round() as usize
-        } else {
-            0
-        };
-
-        // Create the bar with padding to ensure consistent width
-        let bar_chars = "▪".repeat(bar_width);
-        let padding = " ".repeat(max_bar_width - bar_width);
-        let bar = format!("|{}{}|", bar_chars, padding);
-
-        // Calculate percentages
-        let percentage = (*count as f64 / total as f64) * 100.0;
-        cumulative += count;
-        let cumulative_pct = (cumulative as f64 / total as f64) * 100.0;
-
-        println!(
-            "{:<val_width$}  {}  {:>count_width$}  {:>15.2}%  {:>12.2}%",
-            val,
-            bar,
-            count,
-            percentage,
-            cumulative_pct,
-            val_width = max_val_len,
-            count_width = max_count_len
-        );
+    #[test]
+    fn test_sort_by_value_alphabetical() {
+        let mut freq = table(&[("b", 1), ("10", 3), ("a", 2)]);
+        freq.sort_by_value();
+        assert_eq!(freq, table(&[("10", 3), ("a", 2), ("b", 1)]));
     }
 }
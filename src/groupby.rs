@@ -0,0 +1,521 @@
+//! Per-group aggregations, e.g. the sum of stock and mean price per category.
+//!
+//! By default groups live in a hash table until the input ends, and come out
+//! in order of first appearance. With sorted input, where each group's rows
+//! are contiguous, only the current group is held and each is written as
+//! soon as the next one starts; a key out of order is an error, since its
+//! group could otherwise be split.
+
+use crate::error::{CliError, Result};
+use crate::input::{CsvInput, IntoRecords};
+use crate::selector::Selector;
+use crate::sort::{SortKey, SortKind};
+use crate::stats::{parse_number, percentile};
+use csv::StringRecord;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// An aggregate function. Numeric functions read values the way `stats`
+/// does and ignore empty and non-numeric ones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggFunc {
+    /// Rows of the group, or non-empty values of a column
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    /// Population standard deviation, as in `stats`
+    Std,
+    Median,
+    /// Percentile between 0 and 100
+    Percentile(f64),
+    /// Distinct non-empty values
+    NUnique,
+    /// First non-empty value
+    First,
+    /// Last non-empty value
+    Last,
+}
+
+impl AggFunc {
+    /// Parse a function name such as "sum" or "p90".
+    pub fn parse(name: &str) -> std::result::Result<AggFunc, String> {
+        let unknown = || {
+            format!(
+                "unknown function '{}' (expected count, sum, mean, min, max, std, median, p0-p100, n_unique, first or last)",
+                name
+            )
+        };
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "count" => AggFunc::Count,
+            "sum" => AggFunc::Sum,
+            "mean" | "avg" => AggFunc::Mean,
+            "min" => AggFunc::Min,
+            "max" => AggFunc::Max,
+            "std" => AggFunc::Std,
+            "median" => AggFunc::Median,
+            "n_unique" => AggFunc::NUnique,
+            "first" => AggFunc::First,
+            "last" => AggFunc::Last,
+            lower => match lower.strip_prefix('p').map(str::parse::<f64>) {
+                Some(Ok(p)) if (0.0..=100.0).contains(&p) => AggFunc::Percentile(p),
+                _ => return Err(unknown()),
+            },
+        })
+    }
+
+    pub(crate) fn label(&self) -> String {
+        match self {
+            AggFunc::Count => "count".to_string(),
+            AggFunc::Sum => "sum".to_string(),
+            AggFunc::Mean => "mean".to_string(),
+            AggFunc::Min => "min".to_string(),
+            AggFunc::Max => "max".to_string(),
+            AggFunc::Std => "std".to_string(),
+            AggFunc::Median => "median".to_string(),
+            AggFunc::Percentile(p) => format!("p{}", p),
+            AggFunc::NUnique => "n_unique".to_string(),
+            AggFunc::First => "first".to_string(),
+            AggFunc::Last => "last".to_string(),
+        }
+    }
+}
+
+/// One output column: a function and the column it reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregation {
+    pub func: AggFunc,
+    /// `None` only for `count()`
+    pub column: Option<String>,
+}
+
+impl Aggregation {
+    /// Parse a comma-separated list like "sum(stock),mean(price),count()".
+    pub fn parse_list(spec: &str) -> Result<Vec<Aggregation>> {
+        let mut items = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in spec.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    items.push(&spec[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        items.push(&spec[start..]);
+        items
+            .into_iter()
+            .map(|item| Aggregation::parse(item.trim()))
+            .collect()
+    }
+
+    fn parse(item: &str) -> Result<Aggregation> {
+        let invalid =
+            |reason: &str| CliError::Usage(format!("Invalid aggregation '{}': {}", item, reason));
+        let (name, arg) = item
+            .strip_suffix(')')
+            .and_then(|call| call.split_once('('))
+            .ok_or_else(|| invalid("expected FUNCTION(COLUMN), e.g. sum(price) or count()"))?;
+        let func = AggFunc::parse(name.trim()).map_err(|e| invalid(&e))?;
+        let column = match arg.trim() {
+            "" if func == AggFunc::Count => None,
+            "" => return Err(invalid("a column is required")),
+            column => Some(column.to_string()),
+        };
+        Ok(Aggregation { func, column })
+    }
+
+    /// Output column name: "count" for `count()`, else e.g. "sum_stock".
+    pub fn name(&self) -> String {
+        match &self.column {
+            Some(column) => format!("{}_{}", self.func.label(), column),
+            None => self.func.label(),
+        }
+    }
+}
+
+/// Running state of one aggregation within one group.
+pub(crate) enum State {
+    Count(usize),
+    Sum(Option<f64>),
+    Mean {
+        sum: f64,
+        n: usize,
+    },
+    Min(Option<f64>),
+    Max(Option<f64>),
+    /// Welford's running mean and sum of squared deviations
+    Std {
+        n: usize,
+        mean: f64,
+        m2: f64,
+    },
+    Values(Vec<f64>),
+    Distinct(HashSet<String>),
+    Text(Option<String>),
+}
+
+impl State {
+    pub(crate) fn new(func: &AggFunc) -> State {
+        match func {
+            AggFunc::Count => State::Count(0),
+            AggFunc::Sum => State::Sum(None),
+            AggFunc::Mean => State::Mean { sum: 0.0, n: 0 },
+            AggFunc::Min => State::Min(None),
+            AggFunc::Max => State::Max(None),
+            AggFunc::Std => State::Std {
+                n: 0,
+                mean: 0.0,
+                m2: 0.0,
+            },
+            AggFunc::Median | AggFunc::Percentile(_) => State::Values(Vec::new()),
+            AggFunc::NUnique => State::Distinct(HashSet::new()),
+            AggFunc::First | AggFunc::Last => State::Text(None),
+        }
+    }
+
+    /// Add a row, whose value is `None` for `count()`.
+    pub(crate) fn add(&mut self, func: &AggFunc, value: Option<&str>) {
+        let Some(value) = value else {
+            if let State::Count(n) = self {
+                *n += 1;
+            }
+            return;
+        };
+        if value.trim().is_empty() {
+            return;
+        }
+        let number = parse_number(value);
+        match (self, number) {
+            (State::Count(n), _) => *n += 1,
+            (State::Sum(sum), Some(x)) => *sum = Some(sum.unwrap_or(0.0) + x),
+            (State::Mean { sum, n }, Some(x)) => {
+                *sum += x;
+                *n += 1;
+            }
+            (State::Min(min), Some(x)) => *min = Some(min.map_or(x, |m| m.min(x))),
+            (State::Max(max), Some(x)) => *max = Some(max.map_or(x, |m| m.max(x))),
+            (State::Std { n, mean, m2 }, Some(x)) => {
+                *n += 1;
+                let delta = x - *mean;
+                *mean += delta / *n as f64;
+                *m2 += delta * (x - *mean);
+            }
+            (State::Values(values), Some(x)) => values.push(x),
+            (State::Distinct(seen), _) if !seen.contains(value) => {
+                seen.insert(value.to_string());
+            }
+            (State::Text(text), _) if text.is_none() || *func == AggFunc::Last => {
+                *text = Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// The output field; empty when a numeric function saw no number.
+    pub(crate) fn finish(self, func: &AggFunc) -> String {
+        let number = match self {
+            State::Count(n) => return n.to_string(),
+            State::Sum(sum) => sum,
+            State::Mean { sum, n } => (n > 0).then(|| sum / n as f64),
+            State::Min(x) | State::Max(x) => x,
+            State::Std { n, m2, .. } => (n > 0).then(|| (m2 / n as f64).sqrt()),
+            State::Values(mut values) => {
+                values.sort_by(f64::total_cmp);
+                let p = match func {
+                    AggFunc::Percentile(p) => *p,
+                    _ => 50.0,
+                };
+                (!values.is_empty()).then(|| percentile(&values, p))
+            }
+            State::Distinct(seen) => return seen.len().to_string(),
+            State::Text(text) => return text.unwrap_or_default(),
+        };
+        number.map(|x| x.to_string()).unwrap_or_default()
+    }
+}
+
+/// An aggregation bound to a column index.
+struct Bound {
+    func: AggFunc,
+    column: Option<usize>,
+}
+
+struct Group {
+    key: Vec<String>,
+    states: Vec<State>,
+}
+
+impl Group {
+    fn new(key: Vec<String>, aggs: &[Bound]) -> Group {
+        Group {
+            key,
+            states: aggs.iter().map(|agg| State::new(&agg.func)).collect(),
+        }
+    }
+
+    fn add(&mut self, record: &StringRecord, aggs: &[Bound]) {
+        for (state, agg) in self.states.iter_mut().zip(aggs) {
+            let value = agg.column.map(|idx| record.get(idx).unwrap_or(""));
+            state.add(&agg.func, value);
+        }
+    }
+
+    fn finish(self, aggs: &[Bound]) -> StringRecord {
+        let mut row: StringRecord = self.key.iter().collect();
+        for (state, agg) in self.states.into_iter().zip(aggs) {
+            row.push_field(&state.finish(&agg.func));
+        }
+        row
+    }
+}
+
+/// An order sorted input may follow in one group column: one of `clw
+/// sort`'s, or plain byte order with empty values first.
+enum Order {
+    Sort(SortKey),
+    Bytes { descending: bool },
+}
+
+impl Order {
+    /// Every order a group column may be sorted in.
+    fn all(column: usize) -> Vec<Order> {
+        let mut orders = Vec::new();
+        for descending in [false, true] {
+            orders.push(Order::Bytes { descending });
+            for kind in [
+                SortKind::Lexical,
+                SortKind::Numeric,
+                SortKind::Natural,
+                SortKind::Date,
+            ] {
+                orders.push(Order::Sort(SortKey {
+                    column,
+                    kind,
+                    descending,
+                }));
+            }
+        }
+        orders
+    }
+
+    /// Whether `a` sorts strictly before `b`.
+    fn precedes(&self, a: &str, b: &str) -> bool {
+        let ordering = match self {
+            Order::Sort(key) => key.compare(a, b),
+            Order::Bytes { descending: false } => a.cmp(b),
+            Order::Bytes { descending: true } => b.cmp(a),
+        };
+        ordering == Ordering::Less
+    }
+}
+
+/// Check that the group `key` may follow `previous` in sorted input, keeping
+/// in `orders` those of each column that every key so far has followed.
+fn check_order(orders: &mut [Vec<Order>], previous: &[String], key: &[String]) -> Result<()> {
+    let Some(column) = (0..key.len()).find(|&i| previous[i] != key[i]) else {
+        return Ok(());
+    };
+    orders[column].retain(|order| order.precedes(&previous[column], &key[column]));
+    if orders[column].is_empty() {
+        return Err(CliError::Usage(format!(
+            "--sorted input is not sorted by the group columns: '{}' comes after '{}'",
+            key.join(","),
+            previous.join(",")
+        )));
+    }
+    Ok(())
+}
+
+/// One record per group: the group columns, then one column per aggregation.
+pub struct Groups {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    /// `None` once the input is exhausted
+    records: Option<IntoRecords>,
+    keys: Vec<usize>,
+    aggs: Vec<Bound>,
+    sorted: bool,
+    /// The orders each group column still follows, in sorted mode
+    orders: Vec<Vec<Order>>,
+    /// The group being read, in sorted mode
+    current: Option<Group>,
+    /// The finished groups, in hash mode
+    finished: std::vec::IntoIter<Group>,
+}
+
+/// Aggregate the rows of `input` grouped by the `group` columns. With
+/// `sorted`, the input must be sorted by them, ascending or descending in
+/// any order of `clw sort` or byte order; a key out of order is a usage error.
+pub fn groupby(
+    input: CsvInput,
+    group: &str,
+    aggregations: &[Aggregation],
+    sorted: bool,
+) -> Result<Groups> {
+    let keys = Selector::parse(group)?.select(&input.headers)?;
+    let aggs = aggregations
+        .iter()
+        .map(|agg| {
+            let column = match &agg.column {
+                Some(column) => Some(Selector::parse(column)?.select_one(&input.headers)?),
+                None => None,
+            };
+            Ok(Bound {
+                func: agg.func.clone(),
+                column,
+            })
+        })
+        .collect::<Result<Vec<Bound>>>()?;
+
+    let mut headers: StringRecord = keys.iter().map(|&idx| &input.headers[idx]).collect();
+    for agg in aggregations {
+        headers.push_field(&agg.name());
+    }
+
+    let orders = if sorted {
+        keys.iter().map(|&column| Order::all(column)).collect()
+    } else {
+        Vec::new()
+    };
+    Ok(Groups {
+        delimiter: input.delimiter,
+        headers,
+        records: Some(input.into_records()),
+        keys,
+        aggs,
+        sorted,
+        orders,
+        current: None,
+        finished: Vec::new().into_iter(),
+    })
+}
+
+impl Groups {
+    fn key_of(&self, record: &StringRecord) -> Vec<String> {
+        self.keys
+            .iter()
+            .map(|&idx| record.get(idx).unwrap_or("").to_string())
+            .collect()
+    }
+
+    /// Aggregate the whole input into a hash table.
+    fn aggregate_all(&mut self, records: IntoRecords) -> Result<()> {
+        let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut groups = Vec::new();
+        for record in records {
+            let record = record?;
+            let key = self.key_of(&record);
+            let idx = match index.get(&key) {
+                Some(&idx) => idx,
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push(Group::new(key, &self.aggs));
+                    groups.len() - 1
+                }
+            };
+            groups[idx].add(&record, &self.aggs);
+        }
+        self.finished = groups.into_iter();
+        Ok(())
+    }
+
+    /// Read up to the end of the current group of sorted input.
+    fn next_sorted(&mut self) -> Option<Result<StringRecord>> {
+        loop {
+            let Some(result) = self.records.as_mut()?.next() else {
+                self.records = None;
+                return self
+                    .current
+                    .take()
+                    .map(|group| Ok(group.finish(&self.aggs)));
+            };
+            let record = match result {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            let key = self.key_of(&record);
+            if let Some(group) = self.current.as_mut().filter(|group| group.key == key) {
+                group.add(&record, &self.aggs);
+                continue;
+            }
+            if let Some(previous) = &self.current {
+                if let Err(e) = check_order(&mut self.orders, &previous.key, &key) {
+                    return Some(Err(e));
+                }
+            }
+            let mut group = Group::new(key, &self.aggs);
+            group.add(&record, &self.aggs);
+            if let Some(done) = self.current.replace(group) {
+                return Some(Ok(done.finish(&self.aggs)));
+            }
+        }
+    }
+}
+
+impl Iterator for Groups {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sorted {
+            return self.next_sorted();
+        }
+        if let Some(records) = self.records.take() {
+            if let Err(e) = self.aggregate_all(records) {
+                return Some(Err(e));
+            }
+        }
+        self.finished
+            .next()
+            .map(|group| Ok(group.finish(&self.aggs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let aggs = Aggregation::parse_list("sum(stock), count(), p90(latency),AVG(price)").unwrap();
+        let names: Vec<String> = aggs.iter().map(Aggregation::name).collect();
+        assert_eq!(names, ["sum_stock", "count", "p90_latency", "mean_price"]);
+        assert_eq!(aggs[2].func, AggFunc::Percentile(90.0));
+
+        for bad in ["sum()", "total(x)", "p101(x)", "sum(x", "sum"] {
+            assert!(Aggregation::parse_list(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_states() {
+        let run = |func: AggFunc, values: &[&str]| {
+            let mut state = State::new(&func);
+            for value in values {
+                state.add(&func, Some(value));
+            }
+            state.finish(&func)
+        };
+        let values = ["4", "", "2", "n/a", " 6 ", "2"];
+        assert_eq!(run(AggFunc::Count, &values), "5");
+        assert_eq!(run(AggFunc::Sum, &values), "14");
+        assert_eq!(run(AggFunc::Mean, &values), "3.5");
+        assert_eq!(run(AggFunc::Min, &values), "2");
+        assert_eq!(run(AggFunc::Max, &values), "6");
+        assert_eq!(run(AggFunc::Median, &values), "3");
+        assert_eq!(
+            run(AggFunc::Std, &["2", "4", "4", "4", "5", "5", "7", "9"]),
+            "2"
+        );
+        assert_eq!(run(AggFunc::NUnique, &values), "4");
+        assert_eq!(run(AggFunc::First, &["", "a", "b", ""]), "a");
+        assert_eq!(run(AggFunc::Last, &["", "a", "b", ""]), "b");
+        assert_eq!(run(AggFunc::Mean, &["x", ""]), "");
+        assert_eq!(run(AggFunc::Sum, &["x", ""]), "");
+    }
+}
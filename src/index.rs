@@ -0,0 +1,294 @@
+//! Row-offset index (`clw index`), stored next to a CSV file as `<file>.idx`.
+//!
+//! The index holds the byte offset of every data record, so commands can
+//! seek straight to a row and know the row count without parsing the file.
+//! It is only used while the CSV's size, mtime and dialect still match and
+//! the index file is as long as its header says.
+//!
+//! Layout, all integers little-endian:
+//!
+//! | Bytes     | Content                                                  |
+//! |-----------|----------------------------------------------------------|
+//! | 8         | magic `CLWIDX01`                                         |
+//! | 8 + 8 + 4 | CSV size, mtime seconds and nanoseconds                  |
+//! | 8         | dialect: delimiter, quote, escape, double quote, no header |
+//! | 8 + 8     | fields in the header (first) row, number of data rows    |
+//! | 8 × rows  | byte offset of each data row                             |
+//! | 8 + 16 × n | number of rows with a different field count, then (row, fields) pairs |
+
+use crate::error::{CliError, Result};
+use crate::output::{persist, temp_file_beside};
+use crate::utils::{Compression, CsvOptions};
+use encoding_rs::{Encoding, UTF_8};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 8] = b"CLWIDX01";
+const HEADER_LEN: u64 = 52;
+const ROWS_POS: u64 = 44;
+
+/// Indexes ignored during a run because they no longer match their file.
+/// Clones share the list, so a command reading a file several times
+/// reports its index once.
+#[derive(Debug, Clone, Default)]
+pub struct StaleIndexes(Arc<Mutex<Vec<(String, String)>>>);
+
+impl StaleIndexes {
+    fn record(&self, idx_path: String, csv_path: &str) {
+        let mut stale = self.0.lock().unwrap();
+        if !stale.iter().any(|(p, _)| *p == idx_path) {
+            stale.push((idx_path, csv_path.to_string()));
+        }
+    }
+
+    /// One warning per stale index, to show once the command is done.
+    pub fn warnings(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(idx_path, csv_path)| {
+                format!(
+                    "Ignoring index '{}', which is incomplete or does not match the file or dialect; run `clw index {}` to rebuild it",
+                    idx_path, csv_path
+                )
+            })
+            .collect()
+    }
+}
+
+/// Path of the index belonging to `csv_path`.
+pub fn index_path(csv_path: &str) -> String {
+    format!("{}.idx", csv_path)
+}
+
+/// Size and mtime identifying the current contents of `path`.
+fn fingerprint(path: &str) -> io::Result<(u64, u64, u32)> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok((meta.len(), mtime.as_secs(), mtime.subsec_nanos()))
+}
+
+fn dialect(delimiter: char, opts: &CsvOptions) -> [u8; 8] {
+    [
+        delimiter as u8,
+        opts.quote.unwrap_or('"') as u8,
+        opts.escape.map_or(0, |c| c as u8),
+        !opts.no_double_quote as u8,
+        opts.no_header as u8,
+        0,
+        0,
+        0,
+    ]
+}
+
+/// Whether `encoding` leaves byte offsets in the file unchanged.
+pub fn is_utf8(encoding: Option<&'static Encoding>) -> bool {
+    match encoding {
+        None => true,
+        Some(e) => e == UTF_8,
+    }
+}
+
+fn fingerprint_bytes((size, secs, nanos): (u64, u64, u32)) -> [u8; 20] {
+    let mut bytes = [0u8; 20];
+    bytes[..8].copy_from_slice(&size.to_le_bytes());
+    bytes[8..16].copy_from_slice(&secs.to_le_bytes());
+    bytes[16..].copy_from_slice(&nanos.to_le_bytes());
+    bytes
+}
+
+/// Scan `path` and write its index. Returns the number of data rows.
+pub fn build_index(path: &str, opts: &CsvOptions) -> Result<u64> {
+    let fingerprint = fingerprint(path).map_err(|source| CliError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    let file = File::open(path).map_err(|source| CliError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    let mut reader = BufReader::new(file);
+
+    if Compression::from_path(path).is_some()
+        || Compression::from_magic(reader.fill_buf()?).is_some()
+    {
+        return Err(CliError::Usage(format!(
+            "Cannot index compressed file '{}'; decompress it first",
+            path
+        )));
+    }
+    // Offsets are taken from the raw file, so it must be read without transcoding
+    let bom_len = match Encoding::for_bom(reader.fill_buf()?) {
+        Some((e, len)) if e == UTF_8 => len,
+        None if is_utf8(opts.encoding) => 0,
+        _ => {
+            return Err(CliError::Usage(format!(
+                "Cannot index '{}': only UTF-8 files can be indexed",
+                path
+            )))
+        }
+    };
+    reader.consume(bom_len);
+
+    let delimiter = opts.resolve_delimiter(&mut reader)?;
+    let mut csv = opts
+        .reader_builder(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+
+    // Written under a temporary name, so an interrupted run cannot leave a
+    // partial index behind
+    let idx_path = index_path(path);
+    let (file, temp) = temp_file_beside(Path::new(&idx_path))?.into_parts();
+    let mut out = BufWriter::new(file);
+    out.write_all(MAGIC)?;
+    out.write_all(&fingerprint_bytes(fingerprint))?;
+    out.write_all(&dialect(delimiter, opts))?;
+
+    let mut record = csv::ByteRecord::new();
+    if !csv.read_byte_record(&mut record)? {
+        return Err(CliError::EmptyInput);
+    }
+    let header_fields = record.len() as u64;
+    out.write_all(&header_fields.to_le_bytes())?;
+    out.write_all(&0u64.to_le_bytes())?; // row count, patched below
+
+    let mut rows = 0u64;
+    let mut inconsistent = Vec::new();
+    if opts.no_header {
+        // The first row is data
+        let offset = record.position().map_or(0, |p| p.byte()) + bom_len as u64;
+        out.write_all(&offset.to_le_bytes())?;
+        rows += 1;
+    }
+    while csv.read_byte_record(&mut record)? {
+        let offset = record.position().map_or(0, |p| p.byte()) + bom_len as u64;
+        out.write_all(&offset.to_le_bytes())?;
+        if record.len() as u64 != header_fields {
+            inconsistent.push((rows, record.len() as u64));
+        }
+        rows += 1;
+    }
+
+    out.write_all(&(inconsistent.len() as u64).to_le_bytes())?;
+    for (row, fields) in inconsistent {
+        out.write_all(&row.to_le_bytes())?;
+        out.write_all(&fields.to_le_bytes())?;
+    }
+    out.seek(SeekFrom::Start(ROWS_POS))?;
+    out.write_all(&rows.to_le_bytes())?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    persist(temp, Path::new(&idx_path))?;
+    Ok(rows)
+}
+
+/// An open, up-to-date index for one CSV file.
+pub struct RowIndex {
+    file: BufReader<File>,
+    rows: u64,
+}
+
+impl RowIndex {
+    /// Open the index of `path` if there is one and it still matches the file
+    /// and the dialect in use. Stdin, compressed and transcoded input never
+    /// use an index; a stale one is ignored and added to `opts.stale_indexes`.
+    pub fn open_fresh(
+        path: Option<&str>,
+        delimiter: char,
+        opts: &CsvOptions,
+    ) -> Result<Option<RowIndex>> {
+        let path = match path {
+            Some(p) if Compression::from_path(p).is_none() && is_utf8(opts.encoding) => p,
+            _ => return Ok(None),
+        };
+        let idx_path = index_path(path);
+        let mut file = match File::open(&idx_path) {
+            Ok(f) => BufReader::new(f),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        let mut fresh = file.read_exact(&mut header).is_ok()
+            && &header[..8] == MAGIC
+            && header[8..28] == fingerprint_bytes(fingerprint(path)?)
+            && header[28..36] == dialect(delimiter, opts);
+        let rows = u64::from_le_bytes(header[44..52].try_into().unwrap());
+        let mut index = RowIndex { file, rows };
+        fresh = fresh && index.complete()?;
+        if !fresh {
+            opts.stale_indexes.record(idx_path, path);
+            return Ok(None);
+        }
+        Ok(Some(index))
+    }
+
+    /// Whether the file holds exactly the offsets and inconsistent rows
+    /// its header announces.
+    fn complete(&mut self) -> Result<bool> {
+        let len = self.file.get_ref().metadata()?.len();
+        let offsets_end = self
+            .rows
+            .checked_mul(8)
+            .and_then(|n| n.checked_add(HEADER_LEN));
+        let offsets_end = match offsets_end {
+            Some(end) if len.saturating_sub(end) >= 8 => end,
+            _ => return Ok(false),
+        };
+        self.file.seek(SeekFrom::Start(offsets_end))?;
+        let count = self.read_u64()?;
+        Ok(count
+            .checked_mul(16)
+            .and_then(|n| n.checked_add(offsets_end + 8))
+            == Some(len))
+    }
+
+    /// Number of data rows in the file.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Byte offset of data row `row` (0-based) in the CSV file.
+    pub fn offset(&mut self, row: u64) -> Result<u64> {
+        self.file.seek(SeekFrom::Start(HEADER_LEN + 8 * row))?;
+        self.read_u64()
+    }
+
+    /// Data rows (0-based) whose field count differs from the header's, with that count.
+    pub fn inconsistent_rows(&mut self) -> Result<Vec<(u64, usize)>> {
+        self.file
+            .seek(SeekFrom::Start(HEADER_LEN + 8 * self.rows))?;
+        let count = self.read_u64()?;
+        let mut rows = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let row = self.read_u64()?;
+            let fields = self.read_u64()?;
+            rows.push((row, fields as usize));
+        }
+        Ok(rows)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        self.file.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+/// A reader over `path` positioned at byte `offset`, for parsing from a data row.
+pub fn reader_at(path: &str, offset: u64) -> Result<Box<dyn BufRead>> {
+    let mut file = File::open(path).map_err(|source| CliError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(Box::new(BufReader::new(file)))
+}
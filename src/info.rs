@@ -1,28 +1,103 @@
-use crate::utils::{detect_delimiter, input_reader};
-use colored::Colorize;
-use csv::ReaderBuilder;
+use crate::error::Result;
+use crate::index::RowIndex;
+use crate::input::CsvInput;
+use crate::parallel::fold_chunks;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::error::Error;
 
-pub fn get_info(path: Option<&str>) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
+/// Shape of a dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetInfo {
+    /// Number of data rows.
+    pub rows: usize,
+    /// Number of fields in the header (or first) row.
+    pub columns: usize,
+    /// (line, field count) of each row whose length differs from the header's.
+    pub inconsistent_rows: Vec<(usize, usize)>,
+}
 
-    let delimiter = detect_delimiter(&mut *reader)?;
+/// Count the rows and columns of `input`, which should be opened with
+/// `CsvInput::open_flexible` so that ragged rows are reported rather than rejected.
+pub fn get_info(mut input: CsvInput, opts: &CsvOptions) -> Result<DatasetInfo> {
+    let num_columns = input.headers.len();
+    let path = input.path.clone();
+
+    // Line number of the first data row: 0-indexed + header row, if any
+    let first_row = if opts.no_header { 1 } else { 2 };
+
+    // A fresh index already knows the row count and the inconsistent rows
+    let (num_rows, inconsistent_rows) =
+        match RowIndex::open_fresh(path.as_deref(), input.delimiter, opts)? {
+            Some(mut index) => {
+                let inconsistent_rows = index
+                    .inconsistent_rows()?
+                    .into_iter()
+                    .map(|(row, fields)| (row as usize + first_row, fields))
+                    .collect();
+                (index.rows() as usize, inconsistent_rows)
+            }
+            None => {
+                // Otherwise count in parallel chunks when the input allows it
+                let scan = |(rows, inconsistent): &mut Scan, record: &StringRecord| {
+                    if record.len() != num_columns {
+                        inconsistent.push((*rows + first_row, record.len()));
+                    }
+                    *rows += 1;
+                };
+                match fold_chunks(
+                    path.as_deref(),
+                    input.delimiter,
+                    None,
+                    opts,
+                    Scan::default,
+                    scan,
+                )? {
+                    Some(chunks) => merge_scans(chunks),
+                    None => scan_rows(&mut input, num_columns, first_row, opts)?,
+                }
+            }
+        };
+
+    Ok(DatasetInfo {
+        rows: num_rows,
+        columns: num_columns,
+        inconsistent_rows,
+    })
+}
 
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .flexible(true) // Allow rows with different number of fields
-        .from_reader(reader);
+/// Number of data rows, and (line, field count) of rows whose length differs
+/// from the header's.
+type Scan = (usize, Vec<(usize, usize)>);
 
-    let headers = csv.headers()?;
-    let num_columns = headers.len();
+/// Join the scans of consecutive chunks, whose line numbers each start at the chunk.
+fn merge_scans(chunks: Vec<Scan>) -> Scan {
+    let mut num_rows = 0;
+    let mut inconsistent_rows = Vec::new();
+    for (rows, inconsistent) in chunks {
+        inconsistent_rows.extend(
+            inconsistent
+                .into_iter()
+                .map(|(line, n)| (line + num_rows, n)),
+        );
+        num_rows += rows;
+    }
+    (num_rows, inconsistent_rows)
+}
 
+/// Count the data rows, collecting (line, field count) for rows whose length
+/// differs from the header's.
+fn scan_rows(
+    input: &mut CsvInput,
+    num_columns: usize,
+    first_row: usize,
+    opts: &CsvOptions,
+) -> Result<Scan> {
     let mut num_rows = 0;
     let mut inconsistent_rows = Vec::new();
 
-    // Create progress spinner (only shows if stderr is a TTY)
-    let spinner = if atty::is(atty::Stream::Stderr) {
+    // Create progress spinner (only shows if requested, i.e. stderr is a TTY)
+    let spinner = if opts.progress {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -36,13 +111,13 @@ pub fn get_info(path: Option<&str>) -> Result<(), Box<dyn Error>> {
         None
     };
 
-    for (idx, result) in csv.records().enumerate() {
+    for (idx, result) in input.records().enumerate() {
         let record = result?;
         num_rows += 1;
 
         // Check if this row has a different number of fields
         if record.len() != num_columns {
-            inconsistent_rows.push((idx + 2, record.len())); // +2 because: 0-indexed + header row
+            inconsistent_rows.push((idx + first_row, record.len()));
         }
 
         // Update spinner every 1000 rows
@@ -59,40 +134,5 @@ pub fn get_info(path: Option<&str>) -> Result<(), Box<dyn Error>> {
         pb.finish_and_clear();
     }
 
-    // Print summary
-    println!("\n{}", "Dataset Info:".green().bold());
-    println!("  Rows:    {}", num_rows);
-    println!("  Columns: {}", num_columns);
-
-    // Warn about inconsistent rows
-    if !inconsistent_rows.is_empty() {
-        println!(
-            "\n{}",
-            "⚠ WARNING: Inconsistent row lengths detected!"
-                .yellow()
-                .bold()
-        );
-        println!("  Expected {} fields per row, but found:", num_columns);
-
-        // Show first few inconsistent rows
-        let display_limit = 5;
-        for (row_num, field_count) in inconsistent_rows.iter().take(display_limit) {
-            println!("    Row {}: {} fields", row_num, field_count);
-        }
-
-        if inconsistent_rows.len() > display_limit {
-            println!(
-                "    ... and {} more inconsistent rows",
-                inconsistent_rows.len() - display_limit
-            );
-        }
-
-        println!(
-            "  Total inconsistent rows: {}/{}",
-            inconsistent_rows.len(),
-            num_rows
-        );
-    }
-
-    Ok(())
+    Ok((num_rows, inconsistent_rows))
 }
@@ -0,0 +1,262 @@
+//! An opened CSV input: the resolved delimiter, the header row and a stream
+//! of data records with the `--on-error` policy applied.
+//!
+//! Every operation starts from a [`CsvInput`], so library users get the same
+//! decompression, transcoding, delimiter detection and header handling as
+//! the `clw` binary.
+
+use crate::bad_rows::{next_record, BadRows, OnError, Records};
+use crate::error::{CliError, Result};
+use crate::index::{reader_at, RowIndex};
+use crate::utils::{input_reader, CsvOptions};
+use csv::{ByteRecord, StringRecord};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// A CSV source positioned after its header row.
+pub struct CsvInput {
+    /// The file this input was opened from, or `None` for stdin or a reader.
+    pub path: Option<String>,
+    pub delimiter: char,
+    /// The header row; synthetic "1".."N" names with `--no-header`, and
+    /// empty for inputs opened with [`CsvInput::open_raw`].
+    pub headers: StringRecord,
+    csv: csv::Reader<Box<dyn BufRead>>,
+    expected: Option<usize>,
+    bad_rows: BadRows,
+}
+
+impl CsvInput {
+    /// Open `path` (or stdin). Every data row must have as many fields as the header.
+    pub fn open(path: Option<&str>, opts: &CsvOptions) -> Result<CsvInput> {
+        let mut input = CsvInput::from_reader(input_reader(path, opts.encoding)?, opts)?;
+        input.path = path.map(str::to_string);
+        input.report_as(path);
+        Ok(input)
+    }
+
+    /// Open `path` (or stdin), accepting rows of any length.
+    pub fn open_flexible(path: Option<&str>, opts: &CsvOptions) -> Result<CsvInput> {
+        let mut reader = input_reader(path, opts.encoding)?;
+        let delimiter = opts.resolve_delimiter(&mut *reader)?;
+        let mut csv = opts
+            .reader_builder(delimiter)
+            .flexible(true)
+            .from_reader(reader);
+        let headers = opts.headers(&mut csv)?;
+        Ok(CsvInput {
+            path: path.map(str::to_string),
+            delimiter,
+            headers,
+            csv,
+            expected: None,
+            bad_rows: opts.bad_rows.reading(path),
+        })
+    }
+
+    /// Open `path` (or stdin) with no header handling: every row, the first
+    /// included, is a data record of any length.
+    pub fn open_raw(path: Option<&str>, opts: &CsvOptions) -> Result<CsvInput> {
+        let mut reader = input_reader(path, opts.encoding)?;
+        let delimiter = opts.resolve_delimiter(&mut *reader)?;
+        let csv = opts
+            .reader_builder(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        Ok(CsvInput {
+            path: path.map(str::to_string),
+            delimiter,
+            headers: StringRecord::new(),
+            csv,
+            expected: None,
+            bad_rows: opts.bad_rows.reading(path),
+        })
+    }
+
+    /// Read CSV from an already decoded `reader`, detecting its delimiter.
+    pub fn from_reader(mut reader: Box<dyn BufRead>, opts: &CsvOptions) -> Result<CsvInput> {
+        let delimiter = opts.resolve_delimiter(&mut *reader)?;
+        CsvInput::with_delimiter(reader, delimiter, opts)
+    }
+
+    /// Read CSV from an already decoded `reader` with a known delimiter.
+    pub fn with_delimiter(
+        reader: Box<dyn BufRead>,
+        delimiter: char,
+        opts: &CsvOptions,
+    ) -> Result<CsvInput> {
+        let mut csv = opts.reader_builder(delimiter).from_reader(reader);
+        let headers = opts.headers(&mut csv)?;
+        Ok(CsvInput {
+            path: None,
+            delimiter,
+            expected: Some(headers.len()),
+            headers,
+            csv,
+            bad_rows: opts.bad_rows.clone(),
+        })
+    }
+
+    /// Name `path` (or stdin) as the file of the rows dropped by `--on-error report`.
+    pub(crate) fn report_as(&mut self, path: Option<&str>) {
+        self.bad_rows = self.bad_rows.reading(path);
+    }
+
+    /// Continue reading data records at byte `offset` of `path`, e.g. after
+    /// seeking to a row through the index. Bad rows are still reported at
+    /// their line and byte in the whole file.
+    pub fn resume_at(&mut self, path: &str, offset: u64, opts: &CsvOptions) -> Result<()> {
+        let lines = if self.bad_rows.reports() {
+            count_lines(path, offset)?
+        } else {
+            0
+        };
+        self.bad_rows.resumed_at(lines, offset);
+        self.read_from(reader_at(path, offset)?, opts);
+        Ok(())
+    }
+
+    /// Continue reading data records from `reader` instead.
+    fn read_from(&mut self, reader: Box<dyn BufRead>, opts: &CsvOptions) {
+        let mut builder = opts.reader_builder(self.delimiter);
+        builder.has_headers(false);
+        if self.expected.is_none() {
+            builder.flexible(true);
+        }
+        self.csv = builder.from_reader(reader);
+    }
+
+    /// Skip the next `n` data records. With a fresh index the reader seeks
+    /// straight past them instead of parsing them.
+    pub fn skip_records(&mut self, n: usize, opts: &CsvOptions) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        let path = self.path.clone();
+        if let (Some(p), Some(mut index)) = (
+            path.as_deref(),
+            RowIndex::open_fresh(path.as_deref(), self.delimiter, opts)?,
+        ) {
+            if (n as u64) < index.rows() {
+                self.resume_at(p, index.offset(n as u64)?, opts)?;
+            } else {
+                self.read_from(Box::new(io::empty()), opts);
+            }
+            return Ok(());
+        }
+        for result in self.records().take(n) {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Iterate over the remaining data records.
+    pub fn records(&mut self) -> Records<'_, Box<dyn BufRead>> {
+        Records::new(&mut self.csv, self.expected, &self.bad_rows)
+    }
+
+    /// An owned iterator over the remaining data records.
+    pub fn into_records(self) -> IntoRecords {
+        IntoRecords {
+            csv: self.csv,
+            expected: self.expected,
+            bad_rows: self.bad_rows,
+            raw: ByteRecord::new(),
+        }
+    }
+}
+
+/// An input that can be read twice: a file, or stdin spooled to a temporary file.
+pub(crate) struct Source {
+    path: Option<String>,
+    spool: Option<File>,
+}
+
+impl Source {
+    /// Open the first pass over `path` (or stdin).
+    pub(crate) fn open(path: Option<&str>, opts: &CsvOptions) -> Result<(Source, CsvInput)> {
+        if let Some(path) = path {
+            let source = Source {
+                path: Some(path.to_string()),
+                spool: None,
+            };
+            return Ok((source, CsvInput::open(Some(path), opts)?));
+        }
+        // The spool holds decoded UTF-8, so it is read back without transcoding
+        let mut spool = tempfile::tempfile()?;
+        io::copy(&mut input_reader(None, opts.encoding)?, &mut spool)?;
+        let source = Source {
+            path: None,
+            spool: Some(spool),
+        };
+        let input = CsvInput::from_reader(source.rewind()?, opts)?;
+        Ok((source, input))
+    }
+
+    /// Open another pass, with the delimiter found by the first one.
+    pub(crate) fn reopen(&self, delimiter: char, opts: &CsvOptions) -> Result<CsvInput> {
+        let reader = match &self.path {
+            Some(path) => input_reader(Some(path), opts.encoding)?,
+            None => self.rewind()?,
+        };
+        let mut input = CsvInput::with_delimiter(reader, delimiter, opts)?;
+        input.report_as(self.path.as_deref());
+        Ok(input)
+    }
+
+    fn rewind(&self) -> Result<Box<dyn io::BufRead>> {
+        let mut spool = self
+            .spool
+            .as_ref()
+            .expect("only stdin sources are spooled")
+            .try_clone()?;
+        spool.seek(SeekFrom::Start(0))?;
+        Ok(Box::new(BufReader::new(spool)))
+    }
+}
+
+/// Options for a pass that only gathers counts or statistics: malformed rows
+/// are skipped silently, and the `--on-error` policy applies to the pass that
+/// produces the output.
+pub(crate) fn counting_opts(opts: &CsvOptions) -> Result<CsvOptions> {
+    Ok(CsvOptions {
+        bad_rows: BadRows::new(OnError::Skip, None)?,
+        ..opts.clone()
+    })
+}
+
+/// Number of line breaks in the first `len` bytes of `path`.
+fn count_lines(path: &str, len: u64) -> Result<u64> {
+    let file = File::open(path).map_err(|source| CliError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    let mut reader = BufReader::new(file.take(len));
+    let mut lines = 0;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(lines);
+        }
+        lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        let consumed = buf.len();
+        reader.consume(consumed);
+    }
+}
+
+/// Owned iterator over the data records of a [`CsvInput`].
+pub struct IntoRecords {
+    csv: csv::Reader<Box<dyn BufRead>>,
+    expected: Option<usize>,
+    bad_rows: BadRows,
+    raw: ByteRecord,
+}
+
+impl Iterator for IntoRecords {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_record(&mut self.csv, self.expected, &self.bad_rows, &mut self.raw)
+    }
+}
@@ -0,0 +1,227 @@
+//! Relational joins of two CSV files on key columns.
+//!
+//! The right file is loaded into a hash table keyed on its key columns; the
+//! left file is then streamed, so put the smaller file on the right. Output
+//! rows follow the left file's order, and with `right` and `full` joins the
+//! unmatched right rows come last, in their own order.
+
+use crate::error::{CliError, Result};
+use crate::input::{CsvInput, IntoRecords};
+use crate::selector::Selector;
+use crate::utils::{synthetic_headers, CsvOptions};
+use clap::ValueEnum;
+use csv::StringRecord;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum JoinKind {
+    /// Rows whose key is in both files
+    #[default]
+    Inner,
+    /// Every left row, with empty right columns when unmatched
+    Left,
+    /// Every right row, with empty left columns when unmatched
+    Right,
+    /// Every row of both files
+    Full,
+    /// Left rows whose key is in the right file, left columns only
+    Semi,
+    /// Left rows whose key is not in the right file, left columns only
+    Anti,
+}
+
+impl JoinKind {
+    /// Whether output rows only have the left file's columns.
+    fn left_only(self) -> bool {
+        matches!(self, JoinKind::Semi | JoinKind::Anti)
+    }
+}
+
+/// How to join: the key selectors of each file, and the prefixes added to
+/// the names of non-key columns present in both files.
+#[derive(Debug, Clone, Default)]
+pub struct JoinSpec {
+    pub kind: JoinKind,
+    pub left_key: String,
+    /// Defaults to `left_key`
+    pub right_key: Option<String>,
+    pub left_prefix: String,
+    pub right_prefix: String,
+}
+
+/// The joined records. Each one has the left file's columns followed by the
+/// right file's non-key columns; unmatched right rows of `right` and `full`
+/// joins carry their key in the left key columns.
+pub struct Join {
+    /// The left file's delimiter
+    pub delimiter: char,
+    pub headers: StringRecord,
+    kind: JoinKind,
+    left: IntoRecords,
+    left_key: Vec<usize>,
+    left_width: usize,
+    right_rows: Vec<StringRecord>,
+    right_index: HashMap<Vec<String>, Vec<usize>>,
+    right_key: Vec<usize>,
+    right_rest: Vec<usize>,
+    matched: Vec<bool>,
+    pending: VecDeque<StringRecord>,
+    /// Next right row to consider once the left file is done
+    right_cursor: usize,
+}
+
+/// Join `left_path` with `right_path`, '-' standing for stdin in either.
+pub fn join(left_path: &str, right_path: &str, spec: &JoinSpec, opts: &CsvOptions) -> Result<Join> {
+    if left_path == "-" && right_path == "-" {
+        return Err(CliError::Usage(
+            "Cannot use '-' for both inputs; at most one piped stream is supported".to_string(),
+        ));
+    }
+    let open = |path: &str| CsvInput::open((path != "-").then_some(path), opts);
+    let left = open(left_path)?;
+    let mut right = open(right_path)?;
+
+    let left_key = Selector::parse(&spec.left_key)?.select(&left.headers)?;
+    let right_key = Selector::parse(spec.right_key.as_deref().unwrap_or(&spec.left_key))?
+        .select(&right.headers)?;
+    if left_key.len() != right_key.len() {
+        return Err(CliError::Usage(format!(
+            "The left key has {} columns but the right key has {}",
+            left_key.len(),
+            right_key.len()
+        )));
+    }
+    let right_rest: Vec<usize> = (0..right.headers.len())
+        .filter(|idx| !right_key.contains(idx))
+        .collect();
+
+    let headers = if spec.kind.left_only() {
+        left.headers.clone()
+    } else if opts.no_header {
+        synthetic_headers(left.headers.len() + right_rest.len())
+    } else {
+        let right_names: Vec<&str> = right_rest.iter().map(|&i| &right.headers[i]).collect();
+        let left_names = left.headers.iter().enumerate().map(|(i, name)| {
+            if !left_key.contains(&i) && right_names.contains(&name) {
+                format!("{}{}", spec.left_prefix, name)
+            } else {
+                name.to_string()
+            }
+        });
+        let right_names = right_names.iter().map(|&name| {
+            if left.headers.iter().any(|h| h == name) {
+                format!("{}{}", spec.right_prefix, name)
+            } else {
+                name.to_string()
+            }
+        });
+        left_names.chain(right_names).collect()
+    };
+
+    let mut right_rows = Vec::new();
+    let mut right_index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for record in right.records() {
+        let record = record?;
+        right_index
+            .entry(key_of(&record, &right_key))
+            .or_default()
+            .push(right_rows.len());
+        right_rows.push(record);
+    }
+
+    Ok(Join {
+        delimiter: left.delimiter,
+        headers,
+        kind: spec.kind,
+        left_width: left.headers.len(),
+        left: left.into_records(),
+        left_key,
+        matched: vec![false; right_rows.len()],
+        right_rows,
+        right_index,
+        right_key,
+        right_rest,
+        pending: VecDeque::new(),
+        right_cursor: 0,
+    })
+}
+
+fn key_of(record: &StringRecord, key: &[usize]) -> Vec<String> {
+    key.iter()
+        .map(|&idx| record.get(idx).unwrap_or("").to_string())
+        .collect()
+}
+
+impl Join {
+    /// The output row for `left` joined with `right`, either of which may be missing.
+    fn combine(&self, left: Option<&StringRecord>, right: Option<&StringRecord>) -> StringRecord {
+        let mut row: Vec<&str> = (0..self.left_width)
+            .map(|idx| left.and_then(|r| r.get(idx)).unwrap_or(""))
+            .collect();
+        if let (None, Some(right)) = (left, right) {
+            for (&l, &r) in self.left_key.iter().zip(&self.right_key) {
+                row[l] = right.get(r).unwrap_or("");
+            }
+        }
+        row.extend(
+            self.right_rest
+                .iter()
+                .map(|&idx| right.and_then(|r| r.get(idx)).unwrap_or("")),
+        );
+        StringRecord::from(row)
+    }
+
+    /// Queue the output rows of one left record.
+    fn join_left(&mut self, record: StringRecord) {
+        let matches = self
+            .right_index
+            .get(&key_of(&record, &self.left_key))
+            .cloned()
+            .unwrap_or_default();
+        match self.kind {
+            JoinKind::Semi if !matches.is_empty() => self.pending.push_back(record),
+            JoinKind::Anti if matches.is_empty() => self.pending.push_back(record),
+            JoinKind::Semi | JoinKind::Anti => {}
+            JoinKind::Left | JoinKind::Full if matches.is_empty() => {
+                let row = self.combine(Some(&record), None);
+                self.pending.push_back(row);
+            }
+            JoinKind::Inner | JoinKind::Left | JoinKind::Right | JoinKind::Full => {
+                for idx in matches {
+                    self.matched[idx] = true;
+                    let row = self.combine(Some(&record), Some(&self.right_rows[idx]));
+                    self.pending.push_back(row);
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Join {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.pending.pop_front() {
+                return Some(Ok(row));
+            }
+            match self.left.next() {
+                Some(Ok(record)) => self.join_left(record),
+                Some(Err(e)) => return Some(Err(e)),
+                None => break,
+            }
+        }
+
+        if !matches!(self.kind, JoinKind::Right | JoinKind::Full) {
+            return None;
+        }
+        while self.right_cursor < self.right_rows.len() {
+            let idx = self.right_cursor;
+            self.right_cursor += 1;
+            if !self.matched[idx] {
+                return Some(Ok(self.combine(None, Some(&self.right_rows[idx]))));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,63 @@
+//! CLW - CSV Light Wizard, as a library.
+//!
+//! The operations behind the `clw` commands, returning structured results
+//! instead of printing them. Inputs are opened as a [`CsvInput`], which
+//! handles compression, encodings, delimiter detection, `--no-header` and
+//! the `--on-error` policy exactly as the command line does:
+//!
+//! ```no_run
+//! use clw::{column_stats, freq, CsvInput, CsvOptions};
+//!
+//! let opts = CsvOptions::default();
+//! let table = freq(CsvInput::open(Some("sales.csv"), &opts)?, "region", &opts)?;
+//! for (value, count) in &table.counts {
+//!     println!("{value}: {count}");
+//! }
+//! for stats in column_stats(CsvInput::open(Some("sales.csv"), &opts)?, "price", &opts)? {
+//!     println!("{}: {} values, {} empty", stats.column, stats.count, stats.null);
+//! }
+//! # Ok::<(), clw::CliError>(())
+//! ```
+//!
+//! Streaming operations (`filter`, `select`, `stack`, `paste`) are iterators
+//! over `Result<StringRecord>`, so they compose and never hold the whole file.
+
+pub mod bad_rows;
+pub mod bloom;
+pub mod dedup;
+pub mod error;
+pub mod expr;
+pub mod filter;
+pub mod freq;
+pub mod groupby;
+pub mod index;
+pub mod info;
+pub mod input;
+pub mod join;
+pub mod melt;
+pub mod output;
+pub mod parallel;
+pub mod paste;
+pub mod peek;
+pub mod pivot;
+pub mod sample_rows;
+pub mod select_cols;
+pub mod selector;
+pub mod show_header;
+pub mod slice;
+pub mod sort;
+pub mod split;
+pub mod stack;
+pub mod stats;
+pub mod transpose;
+pub mod utils;
+
+pub use bad_rows::{BadRows, OnError};
+pub use error::{CliError, Result};
+pub use expr::Expr;
+pub use freq::{freq, FreqTable};
+pub use info::{get_info, DatasetInfo};
+pub use input::CsvInput;
+pub use selector::Selector;
+pub use stats::{column_stats, ColumnStats};
+pub use utils::CsvOptions;
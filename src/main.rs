@@ -1,33 +1,93 @@
 use clap::{Parser, Subcommand};
-use std::error::Error;
-
-mod filter;
-mod freq;
-mod info;
-mod paste;
-mod peek;
-mod sample_rows;
-mod select_cols;
-mod show_header;
-mod stack;
-mod stats;
-mod transpose;
-mod utils;
-use filter::filter_rows;
-use freq::freq;
-use info::get_info;
-use paste::paste;
-use peek::peek;
-use sample_rows::sample_rows;
-use select_cols::select_cols;
-use show_header::show_header;
-use stack::stack;
-use stats::column_stats;
-use transpose::transpose;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod render;
+mod report;
+use clw::bad_rows::{BadRows, OnError};
+use clw::dedup::{DedupSpec, Keep};
+use clw::error::{CliError, Result};
+use clw::groupby::{AggFunc, Aggregation};
+use clw::index::StaleIndexes;
+use clw::join::{JoinKind, JoinSpec};
+use clw::melt::MeltSpec;
+use clw::pivot::{ColumnOrder, PivotSpec};
+use clw::slice;
+use clw::split::{SplitMode, SplitSpec};
+use clw::utils::{self, CsvOptions, QuoteStyle};
+use report::ReportFormat;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Field delimiter, overriding auto-detection (a character, or tab/comma/pipe/space/semicolon)
+    #[arg(short, long, value_parser = utils::parse_dialect_char)]
+    delimiter: Option<char>,
+
+    /// Delimiters to consider during auto-detection, e.g. ";,|" (default: tab, | , ; ^ 0x1F space)
+    #[arg(long, value_parser = utils::parse_delimiter_candidates)]
+    delimiter_candidates: Option<Vec<char>>,
+
+    /// Quote character (default: ")
+    #[arg(long, value_parser = utils::parse_dialect_char)]
+    quote: Option<char>,
+
+    /// Escape character for quotes inside quoted fields (e.g. "\\")
+    #[arg(long, value_parser = utils::parse_dialect_char)]
+    escape: Option<char>,
+
+    /// Do not treat doubled quotes ("") inside quoted fields as an escaped quote
+    #[arg(long)]
+    no_double_quote: bool,
+
+    /// Input has no header row: the first row is data and columns are referenced by 1-based index
+    #[arg(long)]
+    no_header: bool,
+
+    /// With --no-header, still write a 1..N header row from writer commands
+    #[arg(long, requires = "no_header")]
+    synthetic_header: bool,
+
+    /// Input encoding when there is no byte order mark, e.g. utf-16le, latin1, windows-1252 (default: utf-8)
+    #[arg(long, value_parser = utils::parse_encoding)]
+    encoding: Option<&'static encoding_rs::Encoding>,
+
+    /// What to do with malformed rows (ragged or invalid UTF-8)
+    #[arg(long, value_enum, default_value_t = OnError::Fail)]
+    on_error: OnError,
+
+    /// File receiving malformed rows with their line and byte offset (with --on-error report)
+    #[arg(long, value_name = "FILE")]
+    error_report: Option<String>,
+
+    /// Threads used by freq, stats and info to parse regular files in chunks (default: number of CPUs)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Write the CSV output of writer commands to FILE, replaced only once complete (.gz/.zst/.bz2/.xz are compressed)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Delimiter of CSV written by writer commands (default: the input's delimiter)
+    #[arg(long, value_parser = utils::parse_dialect_char)]
+    out_delimiter: Option<char>,
+
+    /// Which fields of CSV written by writer commands are quoted
+    #[arg(long, value_enum, default_value_t = QuoteStyle::Necessary)]
+    quote_style: QuoteStyle,
+
+    /// End records written by writer commands with CRLF instead of LF
+    #[arg(long)]
+    crlf: bool,
+
+    /// Start CSV written by writer commands with a UTF-8 byte order mark (for Excel)
+    #[arg(long)]
+    bom: bool,
+
+    /// Report format for header, info, stats and freq
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -38,9 +98,9 @@ enum Commands {
     Header { file: Option<String> },
     /// Shows dataset info: number of rows, columns, and warns about inconsistent rows
     Info { file: Option<String> },
-    /// Selects/cuts columns by names
+    /// Selects/cuts columns by names, indices, ranges or patterns
     Select {
-        /// Comma-separated list of column names to select (e.g., "name,age,city")
+        /// Column selector (e.g., "name,age", "3-7", "id-price", "/^score_/", "!comment", "\"last, first\"")
         #[arg(short, long)]
         columns: String,
 
@@ -56,21 +116,62 @@ enum Commands {
         #[arg(long, default_value = "67")]
         seed: u64,
 
-        /// Exclude header row from output
+        /// Exclude header row from output (unlike the global --no-header, the input still has one)
         #[arg(long)]
-        no_header: bool,
+        omit_header: bool,
 
         file: Option<String>,
     },
-    /// Filters rows based on column values
+    /// Filters rows based on column values or an expression
     Filter {
-        /// Column name to filter on
-        #[arg(short, long)]
-        column: String,
+        /// Column to filter on (name or 1-based index); repeat with --value for several conditions
+        #[arg(short, long, requires = "values", required_unless_present = "expr")]
+        column: Vec<String>,
 
-        /// Comma-separated list of values to match (e.g., "100,200,300")
-        #[arg(short, long)]
-        value: String,
+        /// Comma-separated list of values to match (e.g., "100,200,300"), one per --column
+        #[arg(short, long, requires = "column", group = "values")]
+        value: Vec<String>,
+
+        /// File listing the values to match, one per line (or a CSV with --values-column)
+        #[arg(long, value_name = "FILE", requires = "column", group = "values")]
+        values_file: Option<String>,
+
+        /// Take the values from this column of the --values-file CSV
+        #[arg(long, value_name = "COLUMN", requires = "values_file")]
+        values_column: Option<String>,
+
+        /// Hold --values-file in a Bloom filter, confirming its hits against the values sorted on disk: bounded memory, exact matches
+        #[arg(long, requires = "values_file")]
+        bloom: bool,
+
+        /// False-positive rate of the --bloom filter (each false positive costs a lookup on disk)
+        #[arg(long, value_name = "RATE", default_value_t = 0.001, value_parser = parse_rate)]
+        bloom_fp_rate: f64,
+
+        /// Treat each --value as a regular expression searched for in the cell
+        #[arg(long, requires = "value")]
+        regex: bool,
+
+        /// Match --value case-insensitively
+        #[arg(short, long, requires = "column")]
+        ignore_case: bool,
+
+        /// Keep rows matching any --column/--value pair instead of all of them
+        #[arg(long, requires = "column")]
+        any: bool,
+
+        /// Keep the rows that do not match instead (with --values-file, an anti-join)
+        #[arg(long, visible_alias = "anti")]
+        invert: bool,
+
+        /// Keep rows where the expression is true (e.g., "price > 100 && city in ('NYC','LA')")
+        #[arg(
+            short = 'w',
+            long = "where",
+            value_name = "EXPR",
+            conflicts_with = "column"
+        )]
+        expr: Option<String>,
 
         /// Include header row in output
         #[arg(long)]
@@ -78,9 +179,9 @@ enum Commands {
 
         file: Option<String>,
     },
-    /// Shows statistics for a column
+    /// Shows statistics for one or more columns
     Stats {
-        /// Column name to analyze
+        /// Column selector to analyze (e.g., "price", "3-5", "/^score_/")
         #[arg(short, long)]
         column: String,
 
@@ -88,7 +189,7 @@ enum Commands {
     },
     /// Shows frequency counts for unique values in a column
     Freq {
-        /// Column name to analyze
+        /// Column to analyze (name or 1-based index)
         #[arg(short, long)]
         column: String,
 
@@ -102,12 +203,174 @@ enum Commands {
 
         file: Option<String>,
     },
-    /// Stack CSV files vertically (keeping one header). Use '-' to read from stdin.
-    Stack {
-        files: Vec<String>,
+    /// Aggregates columns per group of rows, one output row per group
+    Groupby {
+        /// Column(s) defining the groups (e.g., "category" or "category,region")
+        #[arg(short, long, value_name = "COLUMNS")]
+        group: String,
+
+        /// Aggregations, e.g. "sum(stock),mean(price),count(),n_unique(sku),p90(latency)"
+        #[arg(short, long, value_name = "LIST")]
+        aggregate: String,
+
+        /// Input is sorted by the group columns: hold one group at a time (out-of-order keys are an error)
+        #[arg(long)]
+        sorted: bool,
+
+        file: Option<String>,
+    },
+    /// Reshapes long to wide: one row per index value, one column per value of --columns
+    Pivot {
+        /// Column(s) identifying an output row (e.g., "date")
+        #[arg(short, long, value_name = "COLUMNS")]
+        index: String,
+
+        /// Column whose distinct values become output columns (e.g., "metric")
+        #[arg(short, long, value_name = "COLUMN")]
+        columns: String,
+
+        /// Column aggregated into each cell (optional with --agg count)
+        #[arg(short, long, value_name = "COLUMN")]
+        values: Option<String>,
+
+        /// Aggregation of the values of a cell, as in groupby (sum, mean, first, count, p90, ...)
+        #[arg(short, long, default_value = "first", value_parser = AggFunc::parse)]
+        agg: AggFunc,
+
+        /// Content of cells with no input row
+        #[arg(long, default_value = "")]
+        fill: String,
+
+        /// Order of the new columns
+        #[arg(long, value_enum, default_value_t = ColumnOrder::Natural)]
+        column_order: ColumnOrder,
+
+        file: Option<String>,
     },
+    /// Reshapes wide to long: one row per value column of each input row
+    Melt {
+        /// Column(s) repeated on every output row (e.g., "id,name")
+        #[arg(long, value_name = "COLUMNS")]
+        id: Option<String>,
+
+        /// Columns turned into rows, e.g. "q1-q4" or "/^score_/" (default: every non-id column)
+        #[arg(long, value_name = "COLUMNS")]
+        value_cols: Option<String>,
+
+        /// Name of the output column holding the value column's name
+        #[arg(long, default_value = "variable")]
+        var_name: String,
+
+        /// Name of the output column holding the value
+        #[arg(long, default_value = "value")]
+        value_name: String,
+
+        file: Option<String>,
+    },
+    /// Stack CSV files vertically (keeping one header). Use '-' to read from stdin.
+    Stack { files: Vec<String> },
     /// Paste two CSV files horizontally (side by side)
     Paste { file1: String, file2: String },
+    /// Sorts rows by one or more columns, spilling to disk beyond --memory
+    Sort {
+        /// Sort keys as COLUMN[:lexical|numeric|natural|date][:asc|desc] (e.g., "price:desc,name"); without a kind, numeric columns sort as numbers
+        #[arg(short, long)]
+        columns: String,
+
+        /// Memory for buffered rows before sorted runs spill to temporary files (e.g., 512M, 4G)
+        #[arg(short, long, default_value = "512M", value_parser = utils::parse_size)]
+        memory: usize,
+
+        /// Directory for the temporary runs (default: the system temporary directory)
+        #[arg(long, value_name = "DIR")]
+        temp_dir: Option<String>,
+
+        file: Option<String>,
+    },
+    /// Joins two CSV files on key columns (the right file is held in memory)
+    Join {
+        /// Key column(s) of the left file (e.g., "id" or "id,date")
+        #[arg(short = 'l', long, value_name = "COLUMNS")]
+        left_key: String,
+
+        /// Key column(s) of the right file, matched in order (default: --left-key)
+        #[arg(short = 'r', long, value_name = "COLUMNS")]
+        right_key: Option<String>,
+
+        /// Join type
+        #[arg(short = 't', long = "type", value_enum, default_value_t = JoinKind::Inner)]
+        kind: JoinKind,
+
+        /// Prefix for left non-key columns whose name is also a right column
+        #[arg(long, default_value = "")]
+        left_prefix: String,
+
+        /// Prefix for right non-key columns whose name is also a left column
+        #[arg(long, default_value = "right_")]
+        right_prefix: String,
+
+        /// Left file ('-' for stdin)
+        left: String,
+        /// Right file ('-' for stdin)
+        right: String,
+    },
+    /// Removes duplicate rows, comparing whole rows or key columns
+    Dedup {
+        /// Key column(s) (e.g., "email" or "name,date"; default: the whole row)
+        #[arg(short, long)]
+        columns: Option<String>,
+
+        /// Which row of each group of duplicates to keep
+        #[arg(short, long, value_enum, default_value_t = Keep::First)]
+        keep: Keep,
+
+        /// Write each duplicated key with its number of rows instead of the rows
+        #[arg(long, conflicts_with = "keep")]
+        report: bool,
+
+        /// Memory for distinct keys before they are partitioned into temporary bucket files (e.g., 512M, 4G)
+        #[arg(short, long, default_value = "512M", value_parser = utils::parse_size)]
+        memory: usize,
+
+        /// Directory for the bucket files (default: the system temporary directory)
+        #[arg(long, value_name = "DIR")]
+        temp_dir: Option<String>,
+
+        file: Option<String>,
+    },
+    /// Writes the input to several files: by row count, by size, or one per column value
+    #[command(group(clap::ArgGroup::new("mode").required(true)))]
+    Split {
+        /// Data rows per file
+        #[arg(long, group = "mode")]
+        rows: Option<usize>,
+
+        /// Approximate size of each file (e.g., 500M, 1G); rows are never split
+        #[arg(long, group = "mode", value_parser = utils::parse_size)]
+        bytes: Option<usize>,
+
+        /// Column whose distinct values each get a file
+        #[arg(long, group = "mode", value_name = "COLUMN")]
+        by: Option<String>,
+
+        /// Write --by files as DIR/COLUMN=VALUE/<prefix>0.csv (Hive-style partitions)
+        #[arg(long, requires = "by", conflicts_with_all = ["rows", "bytes"])]
+        hive: bool,
+
+        /// Start of each file name
+        #[arg(long, default_value = "part-")]
+        prefix: String,
+
+        /// Directory receiving the files (created if missing)
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+
+        /// Most files kept open at once with --by; others are closed and reopened as needed
+        #[arg(long, default_value = "256")]
+        max_open: usize,
+
+        file: Option<String>,
+    },
     /// Transpose rows and columns
     Transpose { file: Option<String> },
     /// Pretty-print CSV header and first N rows with rainbow colors
@@ -116,40 +379,339 @@ enum Commands {
         #[arg(short, long)]
         number_rows: Option<usize>,
 
+        /// Number of data rows to skip before the first one displayed (uses the index if present)
+        #[arg(short, long, default_value = "0")]
+        start: usize,
+
+        file: Option<String>,
+    },
+    /// Writes a range of data rows as CSV
+    Slice {
+        /// First data row, counting from 0 (uses the index if present)
+        #[arg(short, long, default_value = "0")]
+        start: usize,
+
+        /// Data row to stop before
+        #[arg(short, long, conflicts_with = "len")]
+        end: Option<usize>,
+
+        /// Number of data rows to write
+        #[arg(short, long)]
+        len: Option<usize>,
+
         file: Option<String>,
     },
+    /// Writes the last N data rows as CSV, reading files backwards from their end
+    Tail {
+        /// Number of rows to write
+        #[arg(short, long, default_value = "10")]
+        number_rows: usize,
+
+        file: Option<String>,
+    },
+    /// Rewrites CSV in the output dialect (--out-delimiter, --quote-style, --crlf, --bom)
+    Fmt { file: Option<String> },
+    /// Writes a row-offset index (<file>.idx) used by peek, sample and info for fast access
+    Index { file: String },
+}
+
+impl Commands {
+    /// Whether the command writes CSV, which `--output` can redirect.
+    fn writes_csv(&self) -> bool {
+        matches!(
+            self,
+            Commands::Select { .. }
+                | Commands::Sample { .. }
+                | Commands::Filter { .. }
+                | Commands::Stack { .. }
+                | Commands::Paste { .. }
+                | Commands::Join { .. }
+                | Commands::Sort { .. }
+                | Commands::Groupby { .. }
+                | Commands::Pivot { .. }
+                | Commands::Melt { .. }
+                | Commands::Dedup { .. }
+                | Commands::Transpose { .. }
+                | Commands::Slice { .. }
+                | Commands::Tail { .. }
+                | Commands::Fmt { .. }
+        )
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// Parse a probability strictly between 0 and 1.
+fn parse_rate(s: &str) -> std::result::Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate < 1.0 => Ok(rate),
+        _ => Err(format!("expected a rate between 0 and 1, got '{}'", s)),
+    }
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match run(cli) {
+        Ok(()) | Err(CliError::BrokenPipe) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let opts = CsvOptions {
+        delimiter: cli.delimiter,
+        quote: cli.quote,
+        escape: cli.escape,
+        no_double_quote: cli.no_double_quote,
+        delimiter_candidates: cli.delimiter_candidates,
+        no_header: cli.no_header,
+        synthetic_header: cli.synthetic_header,
+        encoding: cli.encoding,
+        bad_rows: BadRows::new(cli.on_error, cli.error_report.as_deref())?,
+        stale_indexes: StaleIndexes::default(),
+        jobs: cli
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+        progress: atty::is(atty::Stream::Stderr),
+        output: cli.output,
+        out_delimiter: cli.out_delimiter,
+        quote_style: cli.quote_style,
+        crlf: cli.crlf,
+        bom: cli.bom,
+    };
+
+    if opts.output.is_some() && !cli.command.writes_csv() {
+        return Err(CliError::Usage(
+            "--output only applies to filter, select, sample, sort, dedup, groupby, pivot, melt, stack, paste, join, transpose, slice, tail and fmt"
+                .to_string(),
+        ));
+    }
+
     match cli.command {
-        Commands::Header { file } => show_header(file.as_deref())?,
-        Commands::Info { file } => get_info(file.as_deref())?,
-        Commands::Select { columns, file } => select_cols(file.as_deref(), &columns)?,
+        Commands::Header { file } => render::header(file.as_deref(), cli.format, &opts)?,
+        Commands::Info { file } => render::info(file.as_deref(), cli.format, &opts)?,
+        Commands::Select { columns, file } => {
+            render::select_cols(file.as_deref(), &columns, &opts)?
+        }
         Commands::Sample {
             rows,
             seed,
-            no_header,
+            omit_header,
             file,
-        } => sample_rows(file.as_deref(), rows, seed, !no_header)?,
+        } => render::sample(file.as_deref(), rows, seed, !omit_header, &opts)?,
         Commands::Filter {
             column,
             value,
+            values_file,
+            values_column,
+            bloom,
+            bloom_fp_rate,
+            regex,
+            ignore_case,
+            any,
+            invert,
+            expr,
             keep_header,
             file,
-        } => filter_rows(file.as_deref(), &column, &value, keep_header)?,
-        Commands::Stats { column, file } => column_stats(file.as_deref(), &column)?,
+        } => {
+            let condition = match (expr, values_file) {
+                (Some(expr), _) => render::Condition::Where(expr),
+                (None, Some(path)) => {
+                    let [column] = <[String; 1]>::try_from(column).map_err(|_| {
+                        CliError::Usage("--values-file takes a single --column".to_string())
+                    })?;
+                    render::Condition::ValuesFile {
+                        column,
+                        path,
+                        values_column,
+                        ignore_case,
+                        bloom: bloom.then_some(bloom_fp_rate),
+                    }
+                }
+                (None, None) => {
+                    if column.len() != value.len() {
+                        return Err(CliError::Usage(format!(
+                            "Each --column needs its own --value (got {} --column, {} --value)",
+                            column.len(),
+                            value.len()
+                        )));
+                    }
+                    render::Condition::Values {
+                        pairs: column.into_iter().zip(value).collect(),
+                        regex,
+                        ignore_case,
+                        any,
+                    }
+                }
+            };
+            render::filter_rows(file.as_deref(), &condition, invert, keep_header, &opts)?
+        }
+        Commands::Stats { column, file } => {
+            render::stats(file.as_deref(), &column, cli.format, &opts)?
+        }
+        Commands::Groupby {
+            group,
+            aggregate,
+            sorted,
+            file,
+        } => {
+            let aggregations = Aggregation::parse_list(&aggregate)?;
+            render::group_rows(file.as_deref(), &group, &aggregations, sorted, &opts)?
+        }
+        Commands::Pivot {
+            index,
+            columns,
+            values,
+            agg,
+            fill,
+            column_order,
+            file,
+        } => {
+            let spec = PivotSpec {
+                index,
+                columns,
+                values,
+                agg,
+                fill,
+                column_order,
+            };
+            render::pivot_rows(file.as_deref(), &spec, &opts)?
+        }
+        Commands::Melt {
+            id,
+            value_cols,
+            var_name,
+            value_name,
+            file,
+        } => {
+            let spec = MeltSpec {
+                id,
+                value_cols,
+                var_name,
+                value_name,
+            };
+            render::melt_rows(file.as_deref(), &spec, &opts)?
+        }
         Commands::Freq {
             column,
             plot,
             sort_index,
             file,
-        } => freq(file.as_deref(), &column, plot, sort_index)?,
-        Commands::Stack { files } => stack(&files)?,
-        Commands::Paste { file1, file2 } => paste(&file1, &file2)?,
-        Commands::Transpose { file } => transpose(file.as_deref())?,
-        Commands::Peek { number_rows, file } => peek(file.as_deref(), number_rows)?,
+        } => render::frequencies(
+            file.as_deref(),
+            &column,
+            plot,
+            sort_index,
+            cli.format,
+            &opts,
+        )?,
+        Commands::Stack { files } => render::stack_files(&files, &opts)?,
+        Commands::Paste { file1, file2 } => render::paste_files(&file1, &file2, &opts)?,
+        Commands::Sort {
+            columns,
+            memory,
+            temp_dir,
+            file,
+        } => render::sort_rows(
+            file.as_deref(),
+            &columns,
+            memory,
+            temp_dir.as_deref(),
+            &opts,
+        )?,
+        Commands::Join {
+            left_key,
+            right_key,
+            kind,
+            left_prefix,
+            right_prefix,
+            left,
+            right,
+        } => {
+            let spec = JoinSpec {
+                kind,
+                left_key,
+                right_key,
+                left_prefix,
+                right_prefix,
+            };
+            render::join_files(&left, &right, &spec, &opts)?
+        }
+        Commands::Dedup {
+            columns,
+            keep,
+            report,
+            memory,
+            temp_dir,
+            file,
+        } => {
+            let spec = DedupSpec {
+                columns,
+                keep,
+                memory,
+                temp_dir: temp_dir.map(PathBuf::from),
+            };
+            render::dedup_rows(file.as_deref(), &spec, report, &opts)?
+        }
+        Commands::Split {
+            rows,
+            bytes,
+            by,
+            hive,
+            prefix,
+            dir,
+            max_open,
+            file,
+        } => {
+            let mode = match (rows, bytes, by) {
+                (Some(0), _, _) => {
+                    return Err(CliError::Usage("--rows must be at least 1".to_string()))
+                }
+                (Some(rows), _, _) => SplitMode::Rows(rows),
+                (_, Some(bytes), _) => SplitMode::Bytes(bytes),
+                (_, _, Some(column)) => SplitMode::By { column, hive },
+                (None, None, None) => unreachable!("clap requires one of --rows, --bytes, --by"),
+            };
+            let spec = SplitSpec {
+                mode,
+                dir,
+                prefix,
+                max_open,
+            };
+            render::split_rows(file.as_deref(), &spec, &opts)?
+        }
+        Commands::Transpose { file } => render::transpose_rows(file.as_deref(), &opts)?,
+        Commands::Peek {
+            number_rows,
+            start,
+            file,
+        } => render::peek_rows(file.as_deref(), number_rows, start, &opts)?,
+        Commands::Slice {
+            start,
+            end,
+            len,
+            file,
+        } => {
+            let len = match end {
+                Some(end) => Some(slice::range_len(start, end)?),
+                None => len,
+            };
+            render::slice_rows(file.as_deref(), start, len, &opts)?
+        }
+        Commands::Tail { number_rows, file } => {
+            render::tail_rows(file.as_deref(), number_rows, &opts)?
+        }
+        Commands::Fmt { file } => render::fmt(file.as_deref(), &opts)?,
+        Commands::Index { file } => render::index(&file, &opts)?,
+    }
+
+    for warning in opts.stale_indexes.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(warning) = opts.bad_rows.finish()? {
+        eprintln!("Warning: {}", warning);
     }
 
     Ok(())
@@ -0,0 +1,128 @@
+//! Wide to long reshaping, the inverse of `pivot`: each input row becomes
+//! one output row per value column, holding the id columns, the value
+//! column's name and its value. Rows are streamed one at a time.
+
+use crate::error::{CliError, Result};
+use crate::input::{CsvInput, IntoRecords};
+use crate::selector::Selector;
+use csv::StringRecord;
+
+#[derive(Debug, Clone)]
+pub struct MeltSpec {
+    /// Columns repeated on every output row
+    pub id: Option<String>,
+    /// Columns unpivoted into rows; every non-id column when `None`
+    pub value_cols: Option<String>,
+    pub var_name: String,
+    pub value_name: String,
+}
+
+/// The long records.
+pub struct Melt {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    records: IntoRecords,
+    ids: Vec<usize>,
+    /// Value columns with their names
+    values: Vec<(usize, String)>,
+    current: Option<StringRecord>,
+    next_value: usize,
+}
+
+/// Unpivot the value columns of `input` into rows.
+pub fn melt(input: CsvInput, spec: &MeltSpec) -> Result<Melt> {
+    let ids = match &spec.id {
+        Some(id) => Selector::parse(id)?.select(&input.headers)?,
+        None => Vec::new(),
+    };
+    let values: Vec<usize> = match &spec.value_cols {
+        Some(cols) => Selector::parse(cols)?.select(&input.headers)?,
+        None => (0..input.headers.len())
+            .filter(|idx| !ids.contains(idx))
+            .collect(),
+    };
+    if values.is_empty() {
+        return Err(CliError::Usage(
+            "No value columns to melt: every column is an --id".to_string(),
+        ));
+    }
+
+    let mut headers: StringRecord = ids.iter().map(|&idx| &input.headers[idx]).collect();
+    headers.push_field(&spec.var_name);
+    headers.push_field(&spec.value_name);
+    let values = values
+        .into_iter()
+        .map(|idx| (idx, input.headers[idx].to_string()))
+        .collect();
+
+    Ok(Melt {
+        delimiter: input.delimiter,
+        headers,
+        records: input.into_records(),
+        ids,
+        values,
+        current: None,
+        next_value: 0,
+    })
+}
+
+impl Iterator for Melt {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_value == self.values.len() {
+            self.current = None;
+        }
+        let record = match &self.current {
+            Some(record) => record,
+            None => {
+                match self.records.next()? {
+                    Ok(record) => self.current = Some(record),
+                    Err(e) => return Some(Err(e)),
+                }
+                self.next_value = 0;
+                self.current.as_ref()?
+            }
+        };
+
+        let (idx, name) = &self.values[self.next_value];
+        self.next_value += 1;
+        let mut row: StringRecord = self
+            .ids
+            .iter()
+            .map(|&id| record.get(id).unwrap_or(""))
+            .collect();
+        row.push_field(name);
+        row.push_field(record.get(*idx).unwrap_or(""));
+        Some(Ok(row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CsvOptions;
+    use std::io;
+
+    #[test]
+    fn test_melt_streams_rows_in_order() {
+        let opts = CsvOptions::default();
+        let csv = "id,q1,q2,note\n1,10,20,x\n2,30,,y\n";
+        let input = CsvInput::from_reader(Box::new(io::Cursor::new(csv.to_string())), &opts);
+        let spec = MeltSpec {
+            id: Some("id".to_string()),
+            value_cols: Some("/^q/".to_string()),
+            var_name: "quarter".to_string(),
+            value_name: "revenue".to_string(),
+        };
+        let melted = melt(input.unwrap(), &spec).unwrap();
+        assert_eq!(
+            melted.headers,
+            StringRecord::from(vec!["id", "quarter", "revenue"])
+        );
+        let rows: Vec<String> = melted
+            .map(|row| row.unwrap().iter().collect::<Vec<_>>().join(","))
+            .collect();
+        assert_eq!(rows, ["1,q1,10", "1,q2,20", "2,q1,30", "2,q2,"]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Destination of writer commands (`--output`): stdout, or a file that is
+//! written under a temporary name in the same directory and renamed into
+//! place by [`Output::finish`]. The destination is never half-written: a run
+//! that fails or is interrupted leaves any existing file untouched. A run
+//! killed by a signal can leave its temporary file behind, though.
+//!
+//! Files ending in `.gz`, `.zst`, `.bz2` or `.xz` are compressed accordingly.
+
+use crate::error::{CliError, Result};
+use crate::utils::Compression;
+use bzip2::write::BzEncoder;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, StdoutLock, Write};
+use std::path::{Path, PathBuf};
+use tempfile::{NamedTempFile, TempPath};
+use xz2::write::XzEncoder;
+
+/// Where writer commands send their CSV.
+pub enum Output {
+    Stdout(StdoutLock<'static>),
+    File {
+        writer: Box<Encoder<BufWriter<File>>>,
+        temp: TempPath,
+        path: PathBuf,
+    },
+}
+
+/// A writer compressing with one of the `Compression` formats, or not at all.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Bzip2(BzEncoder<W>),
+    Xz(XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W, compression: Option<Compression>) -> io::Result<Encoder<W>> {
+        Ok(match compression {
+            None => Encoder::Plain(writer),
+            Some(Compression::Gzip) => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(writer, 0)?),
+            Some(Compression::Bzip2) => {
+                Encoder::Bzip2(BzEncoder::new(writer, bzip2::Compression::default()))
+            }
+            Some(Compression::Xz) => Encoder::Xz(XzEncoder::new(writer, 6)),
+        })
+    }
+
+    /// Write the end of the compressed stream and return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(w) => Ok(w),
+            Encoder::Gzip(e) => e.finish(),
+            Encoder::Zstd(e) => e.finish(),
+            Encoder::Bzip2(e) => e.finish(),
+            Encoder::Xz(e) => e.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            Encoder::Gzip(e) => e.write(buf),
+            Encoder::Zstd(e) => e.write(buf),
+            Encoder::Bzip2(e) => e.write(buf),
+            Encoder::Xz(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            Encoder::Gzip(e) => e.flush(),
+            Encoder::Zstd(e) => e.flush(),
+            Encoder::Bzip2(e) => e.flush(),
+            Encoder::Xz(e) => e.flush(),
+        }
+    }
+}
+
+impl Output {
+    /// Write to `path`, or to stdout when `None`.
+    pub fn create(path: Option<&str>) -> Result<Output> {
+        let path = match path {
+            Some(p) => Path::new(p),
+            None => return Ok(Output::Stdout(io::stdout().lock())),
+        };
+        let temp = temp_file_beside(path)?;
+        let (file, temp) = temp.into_parts();
+
+        let path_str = path.to_string_lossy();
+        let writer = Encoder::new(BufWriter::new(file), Compression::from_path(&path_str))?;
+        Ok(Output::File {
+            writer: Box::new(writer),
+            temp,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Flush everything and, for a file, move it into place. Dropping an
+    /// unfinished `Output` deletes its temporary file instead.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Output::Stdout(mut out) => Ok(out.flush()?),
+            Output::File { writer, temp, path } => {
+                let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+                file.sync_all()?;
+                persist(temp, &path)
+            }
+        }
+    }
+}
+
+/// A temporary file in the directory of `path`, to be moved over it by [`persist`].
+pub(crate) fn temp_file_beside(path: &Path) -> Result<NamedTempFile> {
+    // The temporary file must be on the same filesystem for the rename to be atomic
+    let dir = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        ".{}.",
+        path.file_name()
+            .map_or("clw".into(), |n| n.to_string_lossy())
+    );
+    tempfile::Builder::new()
+        .prefix(&prefix)
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(|source| CliError::Open {
+            path: path.display().to_string(),
+            source,
+        })
+}
+
+/// Move the complete temporary file `temp` to `path`.
+pub(crate) fn persist(temp: TempPath, path: &Path) -> Result<()> {
+    // Keep the permissions of the file being replaced; temporary
+    // files are created readable by the owner only
+    let permissions = match fs::metadata(path) {
+        Ok(meta) => Some(meta.permissions()),
+        Err(_) => default_permissions(),
+    };
+    if let Some(permissions) = permissions {
+        fs::set_permissions(&temp, permissions)?;
+    }
+    temp.persist(path).map_err(|e| CliError::Open {
+        path: path.display().to_string(),
+        source: e.error,
+    })?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn default_permissions() -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(fs::Permissions::from_mode(0o644))
+}
+
+#[cfg(not(unix))]
+fn default_permissions() -> Option<fs::Permissions> {
+    None
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::File { writer, .. } => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::File { writer, .. } => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_unfinished_output_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let mut out = Output::create(path.to_str()).unwrap();
+        out.write_all(b"a,b\n").unwrap();
+        drop(out);
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_finish_replaces_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        fs::write(&path, "old\n").unwrap();
+        let mut out = Output::create(path.to_str()).unwrap();
+        out.write_all(b"new\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+        out.finish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn test_gzip_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv.gz");
+        let mut out = Output::create(path.to_str()).unwrap();
+        out.write_all(b"a,b\n1,2\n").unwrap();
+        out.finish().unwrap();
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "a,b\n1,2\n");
+    }
+}
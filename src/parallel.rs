@@ -0,0 +1,256 @@
+//! Parallel chunked parsing (`--jobs`) for commands that fold over all records.
+//!
+//! A regular file is cut into byte ranges, each parsed on its own thread, and
+//! the per-chunk results are returned in file order for the caller to merge.
+//!
+//! Chunk starts come from the row index when there is a fresh one. Otherwise
+//! the file is split at evenly spaced offsets, each moved to the next line end
+//! outside quotes, found from the parity of the quote characters before it.
+//! That can still be wrong (escaped quotes, stray quotes in unquoted fields),
+//! so each chunk parses one record past its end to find where the next chunk
+//! really starts. A chunk whose start does not match is parsed again from the
+//! true start, which keeps the result identical to a sequential scan.
+
+use crate::bad_rows::OnError;
+use crate::error::{CliError, Result};
+use crate::index::{is_utf8, reader_at, RowIndex};
+use crate::utils::{Compression, CsvOptions};
+use csv::{ByteRecord, StringRecord};
+use encoding_rs::{Encoding, UTF_8};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::thread;
+
+/// Chunks smaller than this are not worth a thread.
+const MIN_CHUNK: u64 = 1 << 20;
+
+/// Fold the data records of `path` in parallel, one accumulator per chunk.
+///
+/// Returns `None` when the input cannot be split (stdin, compressed or
+/// transcoded input, a non-default `--on-error` policy, a small file or
+/// `--jobs 1`) or when any chunk fails to parse; the caller then falls back
+/// to its sequential loop, which also reports errors with exact positions.
+/// `expected` is the required number of fields per row, as for `CsvOptions::records`.
+pub fn fold_chunks<T, I, F>(
+    path: Option<&str>,
+    delimiter: char,
+    expected: Option<usize>,
+    opts: &CsvOptions,
+    init: I,
+    step: F,
+) -> Result<Option<Vec<T>>>
+where
+    T: Send,
+    I: Fn() -> T + Sync,
+    F: Fn(&mut T, &StringRecord) + Sync,
+{
+    let path = match path {
+        Some(p)
+            if opts.jobs > 1
+                && opts.bad_rows.policy == OnError::Fail
+                && is_utf8(opts.encoding)
+                && Compression::from_path(p).is_none() =>
+        {
+            p
+        }
+        _ => return Ok(None),
+    };
+    let starts = match chunk_starts(path, delimiter, opts)? {
+        Some(starts) => starts,
+        None => return Ok(None),
+    };
+
+    let chunk = |start: u64, end: u64| {
+        parse_chunk(path, start, end, delimiter, expected, opts, &init, &step)
+    };
+    let ends: Vec<u64> = starts[1..].iter().copied().chain([u64::MAX]).collect();
+    let results: Vec<Result<(T, u64)>> = thread::scope(|s| {
+        let handles: Vec<_> = starts
+            .iter()
+            .zip(&ends)
+            .map(|(&start, &end)| s.spawn(move || chunk(start, end)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("chunk parser panicked"))
+            .collect()
+    });
+
+    // Walk the chunks in order from the true start of the data, re-parsing
+    // any chunk whose guessed start is not where the previous one ended
+    let mut accumulators = Vec::with_capacity(starts.len());
+    let mut position = starts[0];
+    for ((result, &start), &end) in results.into_iter().zip(&starts).zip(&ends) {
+        let result = if start == position {
+            result
+        } else {
+            chunk(position, end)
+        };
+        match result {
+            Ok((acc, next)) => {
+                accumulators.push(acc);
+                position = next;
+            }
+            Err(CliError::BrokenPipe) => return Err(CliError::BrokenPipe),
+            Err(_) => return Ok(None),
+        }
+    }
+    Ok(Some(accumulators))
+}
+
+/// Byte offsets where each chunk should start, the first being the start of
+/// the data. `None` when the file is too small or not plain UTF-8.
+fn chunk_starts(path: &str, delimiter: char, opts: &CsvOptions) -> Result<Option<Vec<u64>>> {
+    let len = fs::metadata(path)?.len();
+    let jobs = (opts.jobs as u64).min(len / MIN_CHUNK);
+    if jobs < 2 {
+        return Ok(None);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let head = reader.fill_buf()?;
+    if Compression::from_magic(head).is_some() {
+        return Ok(None);
+    }
+    let bom_len = match Encoding::for_bom(head) {
+        Some((e, len)) if e == UTF_8 => len as u64,
+        Some(_) => return Ok(None),
+        None => 0,
+    };
+
+    // Data starts after the BOM and, unless --no-header, the header record
+    let data_start = if opts.no_header {
+        bom_len
+    } else {
+        reader.seek(SeekFrom::Start(bom_len))?;
+        let mut csv = opts
+            .reader_builder(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        csv.read_byte_record(&mut ByteRecord::new())?;
+        bom_len + csv.position().byte()
+    };
+
+    if let Some(mut index) = RowIndex::open_fresh(Some(path), delimiter, opts)? {
+        let rows = index.rows();
+        let mut starts = vec![data_start];
+        for k in 1..jobs {
+            let row = rows * k / jobs;
+            if row < rows {
+                starts.push(index.offset(row)?);
+            }
+        }
+        starts.dedup();
+        return Ok(Some(starts));
+    }
+
+    // Split into even byte ranges, then move each split to the next line end
+    // outside quotes. Whether a split falls inside quotes follows from the
+    // parity of the quote characters before it, counted in parallel.
+    let span = len - data_start;
+    let splits: Vec<u64> = (0..=jobs).map(|k| data_start + span * k / jobs).collect();
+    let quote = opts.quote.unwrap_or('"') as u8;
+    let counts: Vec<u64> = thread::scope(|s| {
+        let handles: Vec<_> = splits
+            .windows(2)
+            .map(|w| s.spawn(move || count_byte(path, w[0], w[1], quote)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("quote counter panicked"))
+            .collect::<io::Result<_>>()
+    })?;
+
+    let mut starts = vec![data_start];
+    let mut quotes = 0;
+    for (&split, count) in splits[1..jobs as usize].iter().zip(counts) {
+        quotes += count;
+        let start = next_record_start(path, split, quotes % 2 == 1, quote)?;
+        if start < len && start > *starts.last().unwrap() {
+            starts.push(start);
+        }
+    }
+    Ok(Some(starts))
+}
+
+/// Number of `byte`s in `from..to` of `path`.
+fn count_byte(path: &str, from: u64, to: u64, byte: u8) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(from))?;
+    let mut range = file.take(to - from);
+    let mut buf = vec![0u8; 1 << 16];
+    let mut count = 0;
+    loop {
+        let n = range.read(&mut buf)?;
+        if n == 0 {
+            return Ok(count);
+        }
+        count += buf[..n].iter().filter(|&&b| b == byte).count() as u64;
+    }
+}
+
+/// Position just after the first newline at or after `from` that is outside
+/// quotes, given whether `from` itself is inside quotes.
+fn next_record_start(path: &str, from: u64, mut in_quotes: bool, quote: u8) -> io::Result<u64> {
+    let mut file = BufReader::new(File::open(path)?);
+    file.seek(SeekFrom::Start(from))?;
+    let mut pos = from;
+    loop {
+        let buf = file.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(pos);
+        }
+        for (i, &b) in buf.iter().enumerate() {
+            if b == quote {
+                in_quotes = !in_quotes;
+            } else if b == b'\n' && !in_quotes {
+                return Ok(pos + i as u64 + 1);
+            }
+        }
+        let n = buf.len();
+        file.consume(n);
+        pos += n as u64;
+    }
+}
+
+/// Parse the records starting in `start..end`, plus the first record after it
+/// to learn where the next chunk truly starts. Returns the accumulator and
+/// that position (the file length at end of input).
+#[allow(clippy::too_many_arguments)]
+fn parse_chunk<T>(
+    path: &str,
+    start: u64,
+    end: u64,
+    delimiter: char,
+    expected: Option<usize>,
+    opts: &CsvOptions,
+    init: &impl Fn() -> T,
+    step: &impl Fn(&mut T, &StringRecord),
+) -> Result<(T, u64)> {
+    let mut csv = opts
+        .reader_builder(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader_at(path, start)?);
+    let mut acc = init();
+    let mut raw = ByteRecord::new();
+
+    loop {
+        if !csv.read_byte_record(&mut raw)? {
+            return Ok((acc, start + csv.position().byte()));
+        }
+        // After a "\r\n" terminator the next record is positioned at its
+        // '\n', one byte before the line it starts on
+        let record_start = start + raw.position().map_or(0, |p| p.byte());
+        if record_start + 1 >= end {
+            return Ok((acc, record_start.max(end)));
+        }
+        if expected.is_some_and(|n| raw.len() != n) {
+            return Err(CliError::Malformed("ragged row".to_string()));
+        }
+        let record = StringRecord::from_byte_record(raw.clone())
+            .map_err(|e| CliError::Malformed(e.to_string()))?;
+        step(&mut acc, &record);
+    }
+}
@@ -1,76 +1,76 @@
-use crate::utils::{csv_writer, detect_delimiter, input_reader};
-use csv::ReaderBuilder;
-use std::error::Error;
-use std::io;
+use crate::error::Result;
+use crate::input::{CsvInput, IntoRecords};
+use crate::utils::{synthetic_headers, CsvOptions};
+use csv::StringRecord;
 
-pub fn paste(file1_path: &str, file2_path: &str) -> Result<(), Box<dyn Error>> {
-    // Read first file
-    let mut reader1 = input_reader(Some(file1_path));
-    let delimiter1 = detect_delimiter(&mut *reader1)?;
-
-    let mut csv1 = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter1 as u8)
-        .from_reader(reader1);
-
-    let headers1 = csv1.headers()?.clone();
-
-    // Read second file
-    let mut reader2 = input_reader(Some(file2_path));
-    let delimiter2 = detect_delimiter(&mut *reader2)?;
-
-    let mut csv2 = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter2 as u8)
-        .from_reader(reader2);
+/// The records of two inputs joined side by side, row by row.
+pub struct Paste {
+    pub delimiter: char,
+    /// Both header rows, renumbered 1..N when they are synthetic.
+    pub headers: StringRecord,
+    left: IntoRecords,
+    right: IntoRecords,
+    row_num: usize,
+    done: bool,
+}
 
-    let headers2 = csv2.headers()?.clone();
+/// Paste `file1` and `file2` horizontally. Iterating fails at the first row
+/// where one file has ended and the other has not.
+pub fn paste(file1_path: &str, file2_path: &str, opts: &CsvOptions) -> Result<Paste> {
+    let input1 = CsvInput::open(Some(file1_path), opts)?;
+    let input2 = CsvInput::open(Some(file2_path), opts)?;
 
     // Verify delimiters match
-    if delimiter1 != delimiter2 {
+    if input1.delimiter != input2.delimiter {
         return Err(format!(
             "Files have different delimiters: '{}' vs '{}'",
-            delimiter1, delimiter2
+            input1.delimiter, input2.delimiter
         )
         .into());
     }
 
-    // Use CSV writer for proper quoting
-    let stdout = io::stdout();
-    let mut writer = csv_writer(stdout.lock(), delimiter1);
+    let headers = if opts.no_header {
+        synthetic_headers(input1.headers.len() + input2.headers.len())
+    } else {
+        input1.headers.iter().chain(input2.headers.iter()).collect()
+    };
 
-    // Combine and write headers
-    let combined_header: Vec<&str> = headers1.iter().chain(headers2.iter()).collect();
-    writer.write_record(combined_header)?;
+    Ok(Paste {
+        delimiter: input1.delimiter,
+        headers,
+        left: input1.into_records(),
+        right: input2.into_records(),
+        row_num: 0,
+        done: false,
+    })
+}
 
-    // Stream records from both files simultaneously
-    let mut iter1 = csv1.records();
-    let mut iter2 = csv2.records();
-    let mut row_num = 0;
+impl Iterator for Paste {
+    type Item = Result<StringRecord>;
 
-    loop {
-        match (iter1.next(), iter2.next()) {
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match (self.left.next(), self.right.next()) {
             (Some(Ok(record1)), Some(Ok(record2))) => {
-                // Combine and write row
-                let combined_row: Vec<&str> = record1.iter().chain(record2.iter()).collect();
-                writer.write_record(combined_row)?;
-                row_num += 1;
-            }
-            (None, None) => break, // Both files ended at same time - good!
-            (Some(_), None) => {
-                return Err(
-                    format!("File 1 has more rows than File 2 (at row {})", row_num + 1).into(),
-                );
-            }
-            (None, Some(_)) => {
-                return Err(
-                    format!("File 2 has more rows than File 1 (at row {})", row_num + 1).into(),
-                );
+                self.row_num += 1;
+                return Some(Ok(record1.iter().chain(record2.iter()).collect()));
             }
-            (Some(Err(e)), _) | (_, Some(Err(e))) => return Err(e.into()),
-        }
+            (None, None) => return None, // Both files ended at same time - good!
+            (Some(_), None) => Err(format!(
+                "File 1 has more rows than File 2 (at row {})",
+                self.row_num + 1
+            )
+            .into()),
+            (None, Some(_)) => Err(format!(
+                "File 2 has more rows than File 1 (at row {})",
+                self.row_num + 1
+            )
+            .into()),
+            (Some(Err(e)), _) | (_, Some(Err(e))) => Err(e),
+        };
+        self.done = true;
+        Some(result)
     }
-
-    writer.flush()?;
-    Ok(())
 }
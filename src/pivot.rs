@@ -0,0 +1,170 @@
+//! Long to wide reshaping: one row per distinct index value and one column
+//! per distinct value of the column being spread, each cell aggregating the
+//! matching values with the functions of `groupby`.
+//!
+//! All cells are held in memory, since new output columns can appear up to
+//! the last input row.
+
+use crate::error::{CliError, Result};
+use crate::groupby::{AggFunc, State};
+use crate::input::CsvInput;
+use crate::selector::Selector;
+use crate::sort::natural_chunks;
+use clap::ValueEnum;
+use csv::StringRecord;
+use std::collections::HashMap;
+
+/// Order of the spread columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColumnOrder {
+    /// Digit runs compared as numbers: "q2" before "q10"
+    #[default]
+    Natural,
+    /// Byte-wise string order
+    Lexical,
+    /// Order of first appearance in the input
+    Appearance,
+}
+
+#[derive(Debug, Clone)]
+pub struct PivotSpec {
+    /// Columns identifying an output row
+    pub index: String,
+    /// Column whose values become output columns
+    pub columns: String,
+    /// Column aggregated into the cells; only `count` works without one
+    pub values: Option<String>,
+    pub agg: AggFunc,
+    /// Content of cells with no input row
+    pub fill: String,
+    pub column_order: ColumnOrder,
+}
+
+/// The wide table, rows in order of first appearance of their index.
+pub struct Pivot {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    pub rows: Vec<StringRecord>,
+}
+
+/// Spread the rows of `input` into a wide table.
+pub fn pivot(input: CsvInput, spec: &PivotSpec) -> Result<Pivot> {
+    let index = Selector::parse(&spec.index)?.select(&input.headers)?;
+    let column = Selector::parse(&spec.columns)?.select_one(&input.headers)?;
+    let value = match &spec.values {
+        Some(values) => Some(Selector::parse(values)?.select_one(&input.headers)?),
+        None if spec.agg == AggFunc::Count => None,
+        None => {
+            return Err(CliError::Usage(format!(
+                "--agg {} needs a --values column",
+                spec.agg.label()
+            )))
+        }
+    };
+
+    let mut keys: Vec<Vec<String>> = Vec::new();
+    let mut key_index: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut name_index: HashMap<String, usize> = HashMap::new();
+    let mut cells: HashMap<(usize, usize), State> = HashMap::new();
+
+    let delimiter = input.delimiter;
+    let mut headers: StringRecord = index.iter().map(|&idx| &input.headers[idx]).collect();
+    for record in input.into_records() {
+        let record = record?;
+        let key: Vec<String> = index
+            .iter()
+            .map(|&idx| record.get(idx).unwrap_or("").to_string())
+            .collect();
+        let row = *key_index.entry(key).or_insert_with_key(|key| {
+            keys.push(key.clone());
+            keys.len() - 1
+        });
+        let name = record.get(column).unwrap_or("");
+        let col = match name_index.get(name) {
+            Some(&col) => col,
+            None => {
+                names.push(name.to_string());
+                name_index.insert(name.to_string(), names.len() - 1);
+                names.len() - 1
+            }
+        };
+        cells
+            .entry((row, col))
+            .or_insert_with(|| State::new(&spec.agg))
+            .add(&spec.agg, value.map(|idx| record.get(idx).unwrap_or("")));
+    }
+
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    match spec.column_order {
+        ColumnOrder::Natural => order.sort_by_cached_key(|&col| natural_chunks(&names[col])),
+        ColumnOrder::Lexical => order.sort_by(|&a, &b| names[a].cmp(&names[b])),
+        ColumnOrder::Appearance => {}
+    }
+    for &col in &order {
+        headers.push_field(&names[col]);
+    }
+
+    let rows = keys
+        .into_iter()
+        .enumerate()
+        .map(|(row, key)| {
+            let mut record: StringRecord = key.iter().collect();
+            for &col in &order {
+                match cells.remove(&(row, col)) {
+                    Some(state) => record.push_field(&state.finish(&spec.agg)),
+                    None => record.push_field(&spec.fill),
+                }
+            }
+            record
+        })
+        .collect();
+
+    Ok(Pivot {
+        delimiter,
+        headers,
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CsvOptions;
+    use std::io;
+
+    fn pivoted(csv: &str, agg: AggFunc, column_order: ColumnOrder) -> Vec<String> {
+        let opts = CsvOptions::default();
+        let input = CsvInput::from_reader(Box::new(io::Cursor::new(csv.to_string())), &opts);
+        let spec = PivotSpec {
+            index: "date".to_string(),
+            columns: "metric".to_string(),
+            values: Some("value".to_string()),
+            agg,
+            fill: "-".to_string(),
+            column_order,
+        };
+        let result = pivot(input.unwrap(), &spec).unwrap();
+        std::iter::once(result.headers)
+            .chain(result.rows)
+            .map(|row| row.iter().collect::<Vec<_>>().join(","))
+            .collect()
+    }
+
+    #[test]
+    fn test_pivot() {
+        let csv = "date,metric,value\nd1,m10,1\nd1,m2,2\nd2,m10,3\nd1,m2,4\n";
+        assert_eq!(
+            pivoted(csv, AggFunc::Sum, ColumnOrder::Natural),
+            ["date,m2,m10", "d1,6,1", "d2,-,3"]
+        );
+        assert_eq!(
+            pivoted(csv, AggFunc::First, ColumnOrder::Lexical),
+            ["date,m10,m2", "d1,1,2", "d2,3,-"]
+        );
+        assert_eq!(
+            pivoted(csv, AggFunc::Count, ColumnOrder::Appearance),
+            ["date,m10,m2", "d1,1,2", "d2,1,-"]
+        );
+    }
+}
@@ -0,0 +1,947 @@
+//! Rendering of the library's results for the terminal: tables, colors and
+//! plots on stdout, CSV on `--output` or stdout, warnings on stderr.
+
+use crate::report::{self, ReportFormat};
+use clw::bloom::{BloomFilter, SortedValues};
+use clw::dedup::{dedup, duplicate_groups, DedupSpec};
+use clw::error::Result;
+use clw::filter::{
+    all, any, filter, matches_regex, not, parse_regex, parse_values, read_values, value_in,
+    value_in_any_case, value_in_bloom, Predicate,
+};
+use clw::groupby::{groupby, Aggregation};
+use clw::index::{build_index, index_path};
+use clw::join::{join, JoinSpec};
+use clw::melt::{melt, MeltSpec};
+use clw::paste::paste;
+use clw::peek::peek;
+use clw::pivot::{pivot, PivotSpec};
+use clw::sample_rows::sample_rows;
+use clw::select_cols::{duplicate_columns, project, select};
+use clw::selector::Selector;
+use clw::show_header::read_headers;
+use clw::slice::{slice, tail};
+use clw::sort::sort_columns;
+use clw::split::{split, SplitSpec};
+use clw::stack::stack;
+use clw::stats::{NumericSummary, StatsKind};
+use clw::transpose::transpose;
+use clw::utils::finish_output;
+use clw::{column_stats, freq, get_info, ColumnStats, CsvInput, CsvOptions, DatasetInfo, Expr};
+use colored::*;
+use csv::StringRecord;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+// Rainbow colors for columns (cycling through these)
+const COLORS: &[&str] = &[
+    "cyan",
+    "green",
+    "yellow",
+    "magenta",
+    "blue",
+    "red",
+    "bright_cyan",
+    "bright_green",
+];
+
+/// Write `records` as CSV to `--output` or stdout, after `headers` if given.
+fn write_csv(
+    delimiter: char,
+    headers: Option<&StringRecord>,
+    records: impl Iterator<Item = Result<StringRecord>>,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let mut writer = opts.output_writer(delimiter)?;
+    if let Some(headers) = headers {
+        writer.write_record(headers)?;
+    }
+    for record in records {
+        writer.write_record(&record?)?;
+    }
+    finish_output(writer)
+}
+
+pub fn header(path: Option<&str>, format: ReportFormat, opts: &CsvOptions) -> Result<()> {
+    let headers = read_headers(path, opts)?;
+    if format != ReportFormat::Table {
+        return report::header(format, &headers);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(
+        out,
+        "{:>8}: {:<8}",
+        "Index".green().bold(),
+        "ColName".green().bold()
+    )?;
+    for (i, h) in headers.iter().enumerate() {
+        writeln!(out, "{:>8}: {:<8}", i, h)?;
+    }
+    Ok(())
+}
+
+pub fn info(path: Option<&str>, format: ReportFormat, opts: &CsvOptions) -> Result<()> {
+    let info = get_info(CsvInput::open_flexible(path, opts)?, opts)?;
+    if format != ReportFormat::Table {
+        return report::info(format, &info);
+    }
+    print_info(&mut io::stdout().lock(), &info)?;
+    Ok(())
+}
+
+fn print_info(out: &mut impl Write, info: &DatasetInfo) -> io::Result<()> {
+    writeln!(out, "\n{}", "Dataset Info:".green().bold())?;
+    writeln!(out, "  Rows:    {}", info.rows)?;
+    writeln!(out, "  Columns: {}", info.columns)?;
+
+    // Warn about inconsistent rows
+    if !info.inconsistent_rows.is_empty() {
+        writeln!(
+            out,
+            "\n{}",
+            "⚠ WARNING: Inconsistent row lengths detected!"
+                .yellow()
+                .bold()
+        )?;
+        writeln!(
+            out,
+            "  Expected {} fields per row, but found:",
+            info.columns
+        )?;
+
+        // Show first few inconsistent rows
+        let display_limit = 5;
+        for (row_num, field_count) in info.inconsistent_rows.iter().take(display_limit) {
+            writeln!(out, "    Row {}: {} fields", row_num, field_count)?;
+        }
+
+        if info.inconsistent_rows.len() > display_limit {
+            writeln!(
+                out,
+                "    ... and {} more inconsistent rows",
+                info.inconsistent_rows.len() - display_limit
+            )?;
+        }
+
+        writeln!(
+            out,
+            "  Total inconsistent rows: {}/{}",
+            info.inconsistent_rows.len(),
+            info.rows
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn select_cols(path: Option<&str>, columns: &str, opts: &CsvOptions) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+
+    // Resolve the requested columns to their indices
+    let col_indices = Selector::parse(columns)?.select(&input.headers)?;
+
+    // If duplicates found, ask for confirmation
+    let duplicates = duplicate_columns(&input.headers, &col_indices);
+    if !duplicates.is_empty() && !confirm_duplicates(&duplicates)? {
+        return Ok(());
+    }
+
+    let headers = project(&input.headers, &col_indices);
+    let delimiter = input.delimiter;
+    write_csv(
+        delimiter,
+        opts.emit_header().then_some(&headers),
+        select(input.into_records(), col_indices),
+        opts,
+    )
+}
+
+/// Ask whether to go on with duplicated columns. `false` means cancelled.
+fn confirm_duplicates(duplicates: &[String]) -> Result<bool> {
+    eprintln!(
+        "{}",
+        "⚠ Warning: Duplicate columns detected!".yellow().bold()
+    );
+    eprintln!(
+        "  Duplicated columns: {}",
+        duplicates
+            .iter()
+            .map(|s| format!("'{}'", s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    eprint!("  Do you want to proceed? [y/N]: ");
+    io::stderr().flush()?;
+
+    // Read from /dev/tty to get user input from terminal, not from stdin
+    // This ensures we don't conflict with piped CSV data
+    use atty::{is, Stream};
+    use std::fs::File;
+    use std::io::BufRead;
+
+    let response = if let Ok(tty) = File::open("/dev/tty") {
+        let mut tty_reader = io::BufReader::new(tty);
+        let mut response = String::new();
+        tty_reader.read_line(&mut response)?;
+        response.trim().to_lowercase()
+    } else if is(Stream::Stdin) {
+        // Stdin is a TTY (terminal), safe to read from it
+        let mut response = String::new();
+        io::stdin().read_line(&mut response)?;
+        response.trim().to_lowercase()
+    } else {
+        // In non-interactive mode (tests, pipes), default to "no"
+        eprintln!("  (Non-interactive mode: defaulting to 'no')");
+        "n".to_string()
+    };
+
+    if response != "y" && response != "yes" {
+        eprintln!("Operation cancelled.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+pub fn sample(
+    path: Option<&str>,
+    n: usize,
+    seed: u64,
+    include_header: bool,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+    let mut writer = opts.output_writer(input.delimiter)?;
+
+    // Print headers if requested
+    if include_header && opts.emit_header() {
+        writer.write_record(&input.headers)?;
+    }
+
+    let sample = sample_rows(input, n, seed, opts)?;
+    for record in &sample.rows {
+        writer.write_record(record)?;
+    }
+    finish_output(writer)?;
+
+    if sample.total < n && sample.total > 0 {
+        eprintln!(
+            "Warning: Requested {} rows but CSV only has {} rows. Returned all rows.",
+            n, sample.total
+        );
+    }
+    Ok(())
+}
+
+/// Which rows `filter` keeps.
+pub enum Condition {
+    /// Each `--column` matches its `--value`: one of a comma-separated list,
+    /// or a pattern with `--regex`
+    Values {
+        pairs: Vec<(String, String)>,
+        regex: bool,
+        ignore_case: bool,
+        /// Any pair may match, instead of all of them
+        any: bool,
+    },
+    /// `--column` is one of the values listed in `--values-file`, held in a
+    /// Bloom filter with this false-positive rate if `bloom` is set
+    ValuesFile {
+        column: String,
+        path: String,
+        values_column: Option<String>,
+        ignore_case: bool,
+        bloom: Option<f64>,
+    },
+    /// `--where` expression
+    Where(String),
+}
+
+pub fn filter_rows(
+    path: Option<&str>,
+    condition: &Condition,
+    invert: bool,
+    include_header: bool,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+
+    // Build the predicate, and how to describe it if nothing matches
+    let mut confirmed: Option<Rc<SortedValues>> = None;
+    let (predicate, mut criteria): (Predicate, Vec<String>) = match condition {
+        Condition::Values {
+            pairs,
+            regex,
+            ignore_case,
+            any: any_pair,
+        } => {
+            let mut predicates: Vec<Predicate> = Vec::new();
+            let mut criteria = Vec::new();
+            for (column, value) in pairs {
+                let col_idx = Selector::parse(column)?.select_one(&input.headers)?;
+                let (predicate, described): (Predicate, String) = if *regex {
+                    (
+                        Box::new(matches_regex(col_idx, parse_regex(value, *ignore_case)?)),
+                        format!("Pattern: /{}/", value),
+                    )
+                } else {
+                    // Parse the filter values into a HashSet for efficient lookup
+                    let filter_values = parse_values(value);
+                    let listed = filter_values
+                        .iter()
+                        .map(|s| format!("'{}'", s))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let predicate: Predicate = if *ignore_case {
+                        Box::new(value_in_any_case(col_idx, filter_values))
+                    } else {
+                        Box::new(value_in(col_idx, filter_values))
+                    };
+                    (predicate, format!("Values: {}", listed))
+                };
+                predicates.push(predicate);
+                let joiner = match (criteria.is_empty(), any_pair) {
+                    (true, _) => "",
+                    (false, true) => "OR ",
+                    (false, false) => "AND ",
+                };
+                criteria.push(format!("{}Column: '{}', {}", joiner, column, described));
+            }
+            if *ignore_case {
+                criteria.push("(ignoring case)".to_string());
+            }
+            let predicate: Predicate = match predicates.len() {
+                1 => predicates.remove(0),
+                _ if *any_pair => Box::new(any(predicates)),
+                _ => Box::new(all(predicates)),
+            };
+            (predicate, criteria)
+        }
+        Condition::ValuesFile {
+            column,
+            path: values_path,
+            values_column,
+            ignore_case,
+            bloom,
+        } => {
+            let col_idx = Selector::parse(column)?.select_one(&input.headers)?;
+            let values = || read_values(values_path, values_column.as_deref(), opts);
+            let (predicate, described): (Predicate, String) = match bloom {
+                Some(fp_rate) => {
+                    // Size the filter with a first pass, so only its bits are ever held;
+                    // the second fills it and sorts the values to disk to confirm hits
+                    let count = values()?.try_fold(0usize, |n, value| value.map(|_| n + 1))?;
+                    let mut bloom = BloomFilter::with_rate(count, *fp_rate);
+                    let keys = values()?.map(|value| {
+                        let key = if *ignore_case {
+                            value?.to_lowercase()
+                        } else {
+                            value?
+                        };
+                        bloom.insert(&key);
+                        Ok(key)
+                    });
+                    let sorted = Rc::new(SortedValues::new(keys)?);
+                    confirmed = Some(Rc::clone(&sorted));
+                    let described = format!(
+                        "{} values from '{}' (Bloom filter of {:.1} MB)",
+                        count,
+                        values_path,
+                        bloom.size_bytes() as f64 / 1_048_576.0
+                    );
+                    (
+                        Box::new(value_in_bloom(col_idx, bloom, sorted, *ignore_case)),
+                        described,
+                    )
+                }
+                None => {
+                    let filter_values: HashSet<String> = values()?.collect::<Result<_>>()?;
+                    let described =
+                        format!("{} values from '{}'", filter_values.len(), values_path);
+                    let predicate: Predicate = if *ignore_case {
+                        Box::new(value_in_any_case(col_idx, filter_values))
+                    } else {
+                        Box::new(value_in(col_idx, filter_values))
+                    };
+                    (predicate, described)
+                }
+            };
+            let mut criteria = vec![format!("Column: '{}', {}", column, described)];
+            if *ignore_case {
+                criteria.push("(ignoring case)".to_string());
+            }
+            (predicate, criteria)
+        }
+        Condition::Where(expr) => (
+            Box::new(Expr::parse(expr)?.predicate(&input.headers)?),
+            vec![format!("Expression: {}", expr)],
+        ),
+    };
+    let predicate: Predicate = if invert {
+        criteria.push("(inverted: keeping rows that do not match)".to_string());
+        Box::new(not(predicate))
+    } else {
+        predicate
+    };
+
+    let delimiter = input.delimiter;
+    let headers = input.headers.clone();
+
+    // Filter and print matching rows, with the header if requested
+    let mut match_count = 0;
+    let matches = filter(input.into_records(), predicate).inspect(|result| {
+        if result.is_ok() {
+            match_count += 1;
+        }
+    });
+    write_csv(
+        delimiter,
+        (include_header && opts.emit_header()).then_some(&headers),
+        matches,
+        opts,
+    )?;
+    if let Some(sorted) = confirmed {
+        sorted.finish()?;
+    }
+
+    // Inform user if no matches were found
+    if match_count == 0 {
+        eprintln!("\nWarning: No rows matched the filter criteria.");
+        for line in &criteria {
+            eprintln!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn stats(
+    path: Option<&str>,
+    column: &str,
+    format: ReportFormat,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let all_stats = column_stats(CsvInput::open(path, opts)?, column, opts)?;
+    if format != ReportFormat::Table {
+        return report::stats(format, &all_stats);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for stats in &all_stats {
+        print_column_stats(&mut out, stats)?;
+    }
+    Ok(())
+}
+
+/// Print numeric or categorical statistics for one column.
+fn print_column_stats(out: &mut impl Write, stats: &ColumnStats) -> io::Result<()> {
+    match &stats.kind {
+        StatsKind::Numeric { invalid, summary } => {
+            print_numeric_stats(out, stats, *invalid, summary.as_ref())
+        }
+        StatsKind::Categorical { unique, top } => print_categorical_stats(out, stats, *unique, top),
+    }
+}
+
+fn print_numeric_stats(
+    out: &mut impl Write,
+    stats: &ColumnStats,
+    invalid_count: usize,
+    summary: Option<&NumericSummary>,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "\n{}",
+        format!("Column '{}' Statistics (Numeric)", stats.column)
+            .green()
+            .bold()
+    )?;
+    writeln!(out)?;
+    writeln!(out, "{:<13} {}", "Count:".green(), stats.count)?;
+    writeln!(out, "{:<13} {}", "Null/Empty:".green(), stats.null)?;
+    if invalid_count > 0 {
+        writeln!(
+            out,
+            "{:<13} {} (could not convert to number)",
+            "Invalid:".green(),
+            invalid_count
+        )?;
+    }
+
+    let summary = match summary {
+        Some(summary) => summary,
+        None => return writeln!(out, "\nNo numeric values to analyze."),
+    };
+
+    writeln!(out)?;
+    writeln!(out, "{:<13} {:.2}", "Mean:".green(), summary.mean)?;
+    writeln!(out, "{:<13} {:.2}", "Std Dev:".green(), summary.std_dev)?;
+    writeln!(out, "{:<13} {:.2}", "Min:".green(), summary.min)?;
+    writeln!(out, "{:<13} {:.2}", "Max:".green(), summary.max)?;
+
+    // Percentiles
+    writeln!(out)?;
+    writeln!(out, "{}:", "Percentiles".green())?;
+    for (p, value) in &summary.percentiles {
+        writeln!(
+            out,
+            "  {}{:.2}",
+            format!("{:<12}", format!("{}%:", p)).green(),
+            value
+        )?;
+    }
+    writeln!(out)
+}
+
+fn print_categorical_stats(
+    out: &mut impl Write,
+    stats: &ColumnStats,
+    unique_count: usize,
+    top: &[(String, usize)],
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "\n{}",
+        format!("Column '{}' Statistics (Categorical)", stats.column)
+            .green()
+            .bold()
+    )?;
+    writeln!(out)?;
+    writeln!(out, "{:<13} {}", "Total Count:".green(), stats.count)?;
+    writeln!(out, "{:<13} {}", "Null/Empty:".green(), stats.null)?;
+    writeln!(out, "{:<13} {}", "Unique:".green(), unique_count)?;
+
+    if unique_count == 0 {
+        return writeln!(out, "\nNo non-empty values to analyze.");
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{}:", "Top 3 Most Frequent".green())?;
+    let non_null_total = (stats.count - stats.null) as f64;
+    for (i, (val, count)) in top.iter().enumerate() {
+        let percentage = (*count as f64 / non_null_total) * 100.0;
+        writeln!(
+            out,
+            "  {}. {:<20} {:>6}  ({:.1}%)",
+            i + 1,
+            val,
+            count,
+            percentage
+        )?;
+    }
+    writeln!(out)
+}
+
+pub fn group_rows(
+    path: Option<&str>,
+    group: &str,
+    aggregations: &[Aggregation],
+    sorted: bool,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let groups = groupby(CsvInput::open(path, opts)?, group, aggregations, sorted)?;
+    let headers = groups.headers.clone();
+    write_csv(
+        groups.delimiter,
+        opts.emit_header().then_some(&headers),
+        groups,
+        opts,
+    )
+}
+
+pub fn pivot_rows(path: Option<&str>, spec: &PivotSpec, opts: &CsvOptions) -> Result<()> {
+    let wide = pivot(CsvInput::open(path, opts)?, spec)?;
+    write_csv(
+        wide.delimiter,
+        opts.emit_header().then_some(&wide.headers),
+        wide.rows.into_iter().map(Ok),
+        opts,
+    )
+}
+
+pub fn melt_rows(path: Option<&str>, spec: &MeltSpec, opts: &CsvOptions) -> Result<()> {
+    let long = melt(CsvInput::open(path, opts)?, spec)?;
+    let headers = long.headers.clone();
+    write_csv(
+        long.delimiter,
+        opts.emit_header().then_some(&headers),
+        long,
+        opts,
+    )
+}
+
+pub fn frequencies(
+    path: Option<&str>,
+    column: &str,
+    plot: bool,
+    sort_index: bool,
+    format: ReportFormat,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let mut table = freq(CsvInput::open(path, opts)?, column, opts)?;
+
+    // Sort: by index if requested, otherwise by frequency (default)
+    if sort_index {
+        table.sort_by_value();
+    }
+
+    // The plot is only drawn in tables
+    if format != ReportFormat::Table {
+        return report::freq(format, &table);
+    }
+
+    // Display results
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if plot {
+        print_with_plot(&mut out, &table.counts)?;
+    } else {
+        print_without_plot(&mut out, &table.counts)?;
+    }
+
+    Ok(())
+}
+
+fn print_without_plot(out: &mut impl Write, freq_vec: &[(String, usize)]) -> io::Result<()> {
+    if freq_vec.is_empty() {
+        return Ok(());
+    }
+
+    // Find max value length for alignment
+    let max_val_len = freq_vec.iter().map(|(val, _)| val.len()).max().unwrap_or(0);
+
+    // Find max count length for alignment
+    let max_count_len = freq_vec
+        .iter()
+        .map(|(_, count)| count.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    // Calculate total for percentages
+    let total: usize = freq_vec.iter().map(|(_, count)| *count).sum();
+
+    // Print header
+    writeln!(
+        out,
+        "{:<val_width$}  {:>count_width$}  {:>7}  {:>11}",
+        "Value".green().bold(),
+        "Count".green().bold(),
+        "Pct".green().bold(),
+        "CumPct".green().bold(),
+        val_width = max_val_len.max(5),
+        count_width = max_count_len.max(5)
+    )?;
+
+    // Track cumulative percentage
+    let mut cumulative = 0;
+
+    for (val, count) in freq_vec {
+        // Calculate percentages
+        let percentage = (*count as f64 / total as f64) * 100.0;
+        cumulative += count;
+        let cumulative_pct = (cumulative as f64 / total as f64) * 100.0;
+
+        writeln!(
+            out,
+            "{:<val_width$}  {:>count_width$}  {:>7.2}%  {:>10.2}%",
+            val,
+            count,
+            percentage,
+            cumulative_pct,
+            val_width = max_val_len.max(5),
+            count_width = max_count_len.max(5)
+        )?;
+    }
+
+    Ok(())
+}
+
+fn print_with_plot(out: &mut impl Write, freq_vec: &[(String, usize)]) -> io::Result<()> {
+    if freq_vec.is_empty() {
+        return Ok(());
+    }
+
+    // Find max count for scaling
+    let max_count = freq_vec.iter().map(|(_, count)| *count).max().unwrap_or(1);
+
+    // Find max value length for alignment
+    let max_val_len = freq_vec.iter().map(|(val, _)| val.len()).max().unwrap_or(0);
+
+    // Find max count length for alignment
+    let max_count_len = freq_vec
+        .iter()
+        .map(|(_, count)| count.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    // Calculate total for percentages
+    let total: usize = freq_vec.iter().map(|(_, count)| *count).sum();
+
+    // Bar width: scale to max 50 characters (not including the | borders)
+    let max_bar_width = 50;
+
+    // Print header
+    let bar_header = format!("|{:^50}|", "");
+    writeln!(
+        out,
+        "{:<val_width$}  {}  {:>count_width$}  {:>9}  {:>15}",
+        "Value".green().bold(),
+        bar_header,
+        "Count".green().bold(),
+        "Pct".green().bold(),
+        "CumPct".green().bold(),
+        val_width = max_val_len,
+        count_width = max_count_len
+    )?;
+
+    // Track cumulative percentage
+    let mut cumulative = 0;
+
+    for (val, count) in freq_vec {
+        let bar_width = if max_count > 0 {
+            ((*count as f64 / max_count as f64) * max_bar_width as f64).round() as usize
+        } else {
+            0
+        };
+
+        // Create the bar with padding to ensure consistent width
+        let bar_chars = "▪".repeat(bar_width);
+        let padding = " ".repeat(max_bar_width - bar_width);
+        let bar = format!("|{}{}|", bar_chars, padding);
+
+        // Calculate percentages
+        let percentage = (*count as f64 / total as f64) * 100.0;
+        cumulative += count;
+        let cumulative_pct = (cumulative as f64 / total as f64) * 100.0;
+
+        writeln!(
+            out,
+            "{:<val_width$}  {}  {:>count_width$}  {:>15.2}%  {:>12.2}%",
+            val,
+            bar,
+            count,
+            percentage,
+            cumulative_pct,
+            val_width = max_val_len,
+            count_width = max_count_len
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn stack_files(files: &[String], opts: &CsvOptions) -> Result<()> {
+    let stacked = stack(files, opts)?;
+    let headers = stacked.headers.clone();
+    write_csv(
+        stacked.delimiter,
+        opts.emit_header().then_some(&headers),
+        stacked,
+        opts,
+    )
+}
+
+pub fn paste_files(file1: &str, file2: &str, opts: &CsvOptions) -> Result<()> {
+    let pasted = paste(file1, file2, opts)?;
+    let headers = pasted.headers.clone();
+    write_csv(
+        pasted.delimiter,
+        opts.emit_header().then_some(&headers),
+        pasted,
+        opts,
+    )
+}
+
+pub fn sort_rows(
+    path: Option<&str>,
+    columns: &str,
+    memory: usize,
+    temp_dir: Option<&str>,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let sorted = sort_columns(path, columns, memory, temp_dir.map(Path::new), opts)?;
+    let headers = sorted.headers.clone();
+    write_csv(
+        sorted.delimiter,
+        opts.emit_header().then_some(&headers),
+        sorted,
+        opts,
+    )
+}
+
+pub fn join_files(left: &str, right: &str, spec: &JoinSpec, opts: &CsvOptions) -> Result<()> {
+    let joined = join(left, right, spec, opts)?;
+    let headers = joined.headers.clone();
+    write_csv(
+        joined.delimiter,
+        opts.emit_header().then_some(&headers),
+        joined,
+        opts,
+    )
+}
+
+pub fn dedup_rows(
+    path: Option<&str>,
+    spec: &DedupSpec,
+    report: bool,
+    opts: &CsvOptions,
+) -> Result<()> {
+    if report {
+        let found = duplicate_groups(path, spec, opts)?;
+        let mut headers = found.headers;
+        headers.push_field("count");
+        let rows = found.groups.into_iter().map(|(mut key, count)| {
+            key.push_field(&count.to_string());
+            Ok(key)
+        });
+        return write_csv(
+            found.delimiter,
+            opts.emit_header().then_some(&headers),
+            rows,
+            opts,
+        );
+    }
+    let deduped = dedup(path, spec, opts)?;
+    let headers = deduped.headers.clone();
+    write_csv(
+        deduped.delimiter,
+        opts.emit_header().then_some(&headers),
+        deduped,
+        opts,
+    )
+}
+
+pub fn split_rows(path: Option<&str>, spec: &SplitSpec, opts: &CsvOptions) -> Result<()> {
+    split(CsvInput::open(path, opts)?, spec, opts)?;
+    Ok(())
+}
+
+pub fn slice_rows(
+    path: Option<&str>,
+    start: usize,
+    len: Option<usize>,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+    let (delimiter, headers) = (input.delimiter, input.headers.clone());
+    let rows = slice(input, start, len, opts)?;
+    write_csv(
+        delimiter,
+        opts.emit_header().then_some(&headers),
+        rows,
+        opts,
+    )
+}
+
+pub fn tail_rows(path: Option<&str>, number_rows: usize, opts: &CsvOptions) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+    let (delimiter, headers) = (input.delimiter, input.headers.clone());
+    let rows = tail(input, number_rows, opts)?;
+    write_csv(
+        delimiter,
+        opts.emit_header().then_some(&headers),
+        rows.into_iter().map(Ok),
+        opts,
+    )
+}
+
+pub fn transpose_rows(path: Option<&str>, opts: &CsvOptions) -> Result<()> {
+    let input = CsvInput::open_raw(path, opts)?;
+    let delimiter = input.delimiter;
+    let rows = transpose(input.into_records())?;
+    write_csv(delimiter, None, rows.into_iter().map(Ok), opts)
+}
+
+pub fn fmt(path: Option<&str>, opts: &CsvOptions) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+    let headers = input.headers.clone();
+    write_csv(
+        input.delimiter,
+        opts.emit_header().then_some(&headers),
+        input.into_records(),
+        opts,
+    )
+}
+
+pub fn peek_rows(
+    path: Option<&str>,
+    number_rows: Option<usize>,
+    start: usize,
+    opts: &CsvOptions,
+) -> Result<()> {
+    let input = CsvInput::open(path, opts)?;
+    let headers = input.headers.clone();
+
+    // Default to 10 rows if not specified
+    let records = peek(input, number_rows.unwrap_or(10), start, opts)?;
+
+    // Calculate column widths based on displayed rows only
+    let num_cols = headers.len();
+    let mut col_widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+
+    for record in &records {
+        for (i, value) in record.iter().enumerate() {
+            if i < col_widths.len() {
+                col_widths[i] = col_widths[i].max(value.len());
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    // Print headers with colors
+    for (i, header) in headers.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let colored_header = apply_color(header, color).bold();
+        write!(out, "{:<width$}", colored_header, width = col_widths[i])?;
+        if i < headers.len() - 1 {
+            write!(out, "  ")?; // Two spaces between columns
+        }
+    }
+    writeln!(out)?;
+
+    // Print data rows
+    for record in &records {
+        for (i, value) in record.iter().enumerate() {
+            if i < num_cols {
+                let color = COLORS[i % COLORS.len()];
+                let colored_value = apply_color(value, color);
+                write!(out, "{:<width$}", colored_value, width = col_widths[i])?;
+                if i < num_cols - 1 {
+                    write!(out, "  ")?; // Two spaces between columns
+                }
+            }
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn apply_color(text: &str, color: &str) -> ColoredString {
+    match color {
+        "cyan" => text.cyan(),
+        "green" => text.green(),
+        "yellow" => text.yellow(),
+        "magenta" => text.magenta(),
+        "blue" => text.blue(),
+        "red" => text.red(),
+        "bright_cyan" => text.bright_cyan(),
+        "bright_green" => text.bright_green(),
+        _ => text.white(),
+    }
+}
+
+/// `clw index`: build the index and report where it was written.
+pub fn index(path: &str, opts: &CsvOptions) -> Result<()> {
+    let rows = build_index(path, opts)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "Indexed {} rows into {}", rows, index_path(path))?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Machine-readable reports (`--format json|csv|tsv`) for header, info,
+//! stats and freq. The schemas are documented in USAGE.md; fields are only
+//! ever added, never renamed or removed.
+
+use clap::ValueEnum;
+use clw::error::Result;
+use clw::stats::StatsKind;
+use clw::{ColumnStats, DatasetInfo, FreqTable};
+use csv::StringRecord;
+use serde_json::{json, Map, Value};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Colored tables for reading in a terminal
+    #[default]
+    Table,
+    /// One JSON document
+    Json,
+    /// Comma-separated tidy table
+    Csv,
+    /// Tab-separated tidy table
+    Tsv,
+}
+
+/// Columns of the `stats` table, one row per analyzed column.
+const STATS_COLUMNS: [&str; 15] = [
+    "column", "type", "count", "null", "invalid", "unique", "mean", "std_dev", "min", "max", "p1",
+    "p25", "p50", "p75", "p99",
+];
+
+/// Print `value` as pretty JSON on stdout.
+fn write_json(value: &Value) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    serde_json::to_writer_pretty(&mut out, value).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Write `header` then `rows` as CSV or TSV on stdout.
+fn write_table<R: IntoIterator<Item = Vec<String>>>(
+    format: ReportFormat,
+    header: &[&str],
+    rows: R,
+) -> Result<()> {
+    let delimiter = if format == ReportFormat::Tsv {
+        b'\t'
+    } else {
+        b','
+    };
+    let stdout = io::stdout();
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(stdout.lock());
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// `{"columns": [{"index", "name"}]}`, or an `index,name` table.
+pub fn header(format: ReportFormat, headers: &StringRecord) -> Result<()> {
+    if format == ReportFormat::Json {
+        let columns: Vec<Value> = headers
+            .iter()
+            .enumerate()
+            .map(|(index, name)| json!({ "index": index, "name": name }))
+            .collect();
+        return write_json(&json!({ "columns": columns }));
+    }
+    write_table(
+        format,
+        &["index", "name"],
+        headers
+            .iter()
+            .enumerate()
+            .map(|(index, name)| vec![index.to_string(), name.to_string()]),
+    )
+}
+
+/// `{"rows", "columns", "inconsistent_rows": [{"line", "fields"}]}`, or a
+/// one-row `rows,columns,inconsistent_rows` table with the count of inconsistent rows.
+pub fn info(format: ReportFormat, info: &DatasetInfo) -> Result<()> {
+    if format == ReportFormat::Json {
+        let inconsistent: Vec<Value> = info
+            .inconsistent_rows
+            .iter()
+            .map(|(line, fields)| json!({ "line": line, "fields": fields }))
+            .collect();
+        return write_json(&json!({
+            "rows": info.rows,
+            "columns": info.columns,
+            "inconsistent_rows": inconsistent,
+        }));
+    }
+    write_table(
+        format,
+        &["rows", "columns", "inconsistent_rows"],
+        [vec![
+            info.rows.to_string(),
+            info.columns.to_string(),
+            info.inconsistent_rows.len().to_string(),
+        ]],
+    )
+}
+
+/// A JSON array with one object per column, or a table with one row per
+/// column and the `STATS_COLUMNS`, left empty where they do not apply.
+pub fn stats(format: ReportFormat, all_stats: &[ColumnStats]) -> Result<()> {
+    if format == ReportFormat::Json {
+        return write_json(&Value::Array(all_stats.iter().map(stats_json).collect()));
+    }
+    write_table(format, &STATS_COLUMNS, all_stats.iter().map(stats_row))
+}
+
+fn stats_json(stats: &ColumnStats) -> Value {
+    let mut object = Map::new();
+    object.insert("column".into(), json!(stats.column));
+    object.insert("count".into(), json!(stats.count));
+    object.insert("null".into(), json!(stats.null));
+    match &stats.kind {
+        StatsKind::Numeric { invalid, summary } => {
+            object.insert("type".into(), json!("numeric"));
+            object.insert("invalid".into(), json!(invalid));
+            let summary = summary.as_ref();
+            object.insert("mean".into(), json!(summary.map(|s| s.mean)));
+            object.insert("std_dev".into(), json!(summary.map(|s| s.std_dev)));
+            object.insert("min".into(), json!(summary.map(|s| s.min)));
+            object.insert("max".into(), json!(summary.map(|s| s.max)));
+            let percentiles: Map<String, Value> = summary
+                .map(|s| s.percentiles.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(|(p, value)| (format!("p{}", p), json!(value)))
+                .collect();
+            object.insert("percentiles".into(), Value::Object(percentiles));
+        }
+        StatsKind::Categorical { unique, top } => {
+            object.insert("type".into(), json!("categorical"));
+            object.insert("unique".into(), json!(unique));
+            let top: Vec<Value> = top
+                .iter()
+                .map(|(value, count)| json!({ "value": value, "count": count }))
+                .collect();
+            object.insert("top".into(), json!(top));
+        }
+    }
+    Value::Object(object)
+}
+
+fn stats_row(stats: &ColumnStats) -> Vec<String> {
+    let mut row = vec![String::new(); STATS_COLUMNS.len()];
+    row[0] = stats.column.clone();
+    row[2] = stats.count.to_string();
+    row[3] = stats.null.to_string();
+    match &stats.kind {
+        StatsKind::Numeric { invalid, summary } => {
+            row[1] = "numeric".to_string();
+            row[4] = invalid.to_string();
+            if let Some(s) = summary {
+                let values = [s.mean, s.std_dev, s.min, s.max]
+                    .into_iter()
+                    .chain(s.percentiles.iter().map(|(_, value)| *value));
+                for (cell, value) in row[6..].iter_mut().zip(values) {
+                    *cell = value.to_string();
+                }
+            }
+        }
+        StatsKind::Categorical { unique, .. } => {
+            row[1] = "categorical".to_string();
+            row[5] = unique.to_string();
+        }
+    }
+    row
+}
+
+/// `{"column", "total", "values": [{"value", "count", "pct", "cumpct"}]}`,
+/// or a `value,count,pct,cumpct` table, in the order of `table`.
+pub fn freq(format: ReportFormat, table: &FreqTable) -> Result<()> {
+    let total = table.total();
+    let mut cumulative = 0;
+    let rows: Vec<(&str, usize, f64, f64)> = table
+        .counts
+        .iter()
+        .map(|(value, count)| {
+            cumulative += count;
+            (
+                value.as_str(),
+                *count,
+                *count as f64 / total as f64 * 100.0,
+                cumulative as f64 / total as f64 * 100.0,
+            )
+        })
+        .collect();
+
+    if format == ReportFormat::Json {
+        let values: Vec<Value> = rows
+            .iter()
+            .map(|(value, count, pct, cumpct)| {
+                json!({ "value": value, "count": count, "pct": pct, "cumpct": cumpct })
+            })
+            .collect();
+        return write_json(&json!({
+            "column": table.column,
+            "total": total,
+            "values": values,
+        }));
+    }
+    write_table(
+        format,
+        &["value", "count", "pct", "cumpct"],
+        rows.iter().map(|(value, count, pct, cumpct)| {
+            vec![
+                value.to_string(),
+                count.to_string(),
+                pct.to_string(),
+                cumpct.to_string(),
+            ]
+        }),
+    )
+}
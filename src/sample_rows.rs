@@ -1,55 +1,50 @@
-use crate::utils::{csv_writer, detect_delimiter, input_reader};
-use csv::ReaderBuilder;
+use crate::bad_rows::OnError;
+use crate::error::Result;
+use crate::index::RowIndex;
+use crate::input::CsvInput;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
 use rand::rngs::StdRng;
 use rand::Rng;
 use rand::SeedableRng;
-use std::error::Error;
-use std::io;
 
-pub fn sample_rows(
-    path: Option<&str>,
-    n: usize,
-    seed: u64,
-    include_header: bool,
-) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-    let delimiter = detect_delimiter(&mut *reader)?;
-
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers()?.clone();
-
-    // Create CSV writer for proper quoting
-    let stdout = io::stdout();
-    let mut writer = csv_writer(stdout.lock(), delimiter);
+/// A random sample of data records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub rows: Vec<StringRecord>,
+    /// Number of data rows sampled from; fewer than requested means all rows were returned.
+    pub total: usize,
+}
 
-    // Print headers if requested
-    if include_header {
-        writer.write_record(headers.iter().collect::<Vec<_>>())?;
+/// Sample `n` data records of `input` uniformly, reproducibly for a given `seed`.
+pub fn sample_rows(mut input: CsvInput, n: usize, seed: u64, opts: &CsvOptions) -> Result<Sample> {
+    // With a fresh index, only the sampled rows need to be parsed, unless a
+    // ragged row outside the sample must still fail the command
+    let path = input.path.clone();
+    if let (Some(p), OnError::Fail) = (path.as_deref(), opts.bad_rows.policy) {
+        if let Some(mut index) = RowIndex::open_fresh(Some(p), input.delimiter, opts)? {
+            if index.inconsistent_rows()?.is_empty() {
+                return indexed_sample(p, &mut index, &mut input, n, seed, opts);
+            }
+        }
     }
 
     // Use reservoir sampling with seeded RNG
-    reservoir_sample(&mut csv, n, seed, &mut writer)?;
-
-    Ok(())
+    reservoir_sample(input.records(), n, seed)
 }
 
 // Reservoir sampling: guarantees exactly k samples with equal probability
 // Uses seeded RNG for reproducible results
-fn reservoir_sample<W: io::Write>(
-    csv: &mut csv::Reader<Box<dyn std::io::BufRead>>,
+fn reservoir_sample(
+    records: impl Iterator<Item = Result<StringRecord>>,
     k: usize,
     seed: u64,
-    writer: &mut csv::Writer<W>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<Sample> {
     let mut rng = StdRng::seed_from_u64(seed);
-    let mut reservoir: Vec<csv::StringRecord> = Vec::with_capacity(k);
+    let mut reservoir: Vec<StringRecord> = Vec::with_capacity(k);
 
     let mut i = 0;
-    for result in csv.records() {
+    for result in records {
         let record = result?;
 
         if i < k {
@@ -65,17 +60,41 @@ fn reservoir_sample<W: io::Write>(
         i += 1;
     }
 
-    // Write sampled rows
-    for record in reservoir {
-        writer.write_record(record.iter().collect::<Vec<_>>())?;
+    Ok(Sample {
+        rows: reservoir,
+        total: i,
+    })
+}
+
+// Same reservoir algorithm and RNG sequence as `reservoir_sample`, run over
+// row numbers from the index, so the output is identical to a full scan
+fn indexed_sample(
+    path: &str,
+    index: &mut RowIndex,
+    input: &mut CsvInput,
+    k: usize,
+    seed: u64,
+    opts: &CsvOptions,
+) -> Result<Sample> {
+    let total = index.rows() as usize;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut reservoir: Vec<usize> = (0..k.min(total)).collect();
+
+    for i in k..total {
+        let j = rng.gen_range(0..=i);
+        if j < k {
+            reservoir[j] = i;
+        }
     }
 
-    if i < k && i > 0 {
-        eprintln!(
-            "Warning: Requested {} rows but CSV only has {} rows. Returned all rows.",
-            k, i
-        );
+    // Seek to each sampled row and parse just that record
+    let mut rows = Vec::with_capacity(reservoir.len());
+    for row in reservoir {
+        input.resume_at(path, index.offset(row as u64)?, opts)?;
+        if let Some(record) = input.records().next() {
+            rows.push(record?);
+        }
     }
 
-    Ok(())
+    Ok(Sample { rows, total })
 }
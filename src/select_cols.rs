@@ -1,112 +1,54 @@
-use crate::utils::{csv_writer, detect_delimiter, input_reader};
-use colored::Colorize;
-use csv::ReaderBuilder;
+use crate::error::Result;
+use csv::StringRecord;
 use std::collections::HashSet;
-use std::error::Error;
-use std::io::{self, Write};
 
-pub fn select_cols(path: Option<&str>, columns: &str) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-
-    let delimiter = detect_delimiter(&mut *reader)?;
-
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers()?;
+/// Project each record onto the fields at `indices`, in that order. Errors
+/// are passed through.
+pub fn select<I>(records: I, indices: Vec<usize>) -> impl Iterator<Item = Result<StringRecord>>
+where
+    I: Iterator<Item = Result<StringRecord>>,
+{
+    records.map(move |result| result.map(|record| project(&record, &indices)))
+}
 
-    // Parse the requested column names
-    let requested_cols: Vec<&str> = columns.split(',').map(|s| s.trim()).collect();
+/// The fields of `record` at `indices`, empty where a field is missing.
+pub fn project(record: &StringRecord, indices: &[usize]) -> StringRecord {
+    indices
+        .iter()
+        .map(|&idx| record.get(idx).unwrap_or(""))
+        .collect()
+}
 
-    // Check for duplicates
+/// Names of the columns selected more than once, each listed once.
+pub fn duplicate_columns(headers: &StringRecord, indices: &[usize]) -> Vec<String> {
     let mut seen = HashSet::new();
     let mut duplicates = Vec::new();
-    for col_name in &requested_cols {
-        if !seen.insert(col_name) && !duplicates.contains(col_name) {
+    for &idx in indices {
+        let col_name = headers.get(idx).unwrap_or("").to_string();
+        if !seen.insert(idx) && !duplicates.contains(&col_name) {
             duplicates.push(col_name);
         }
     }
+    duplicates
+}
 
-    // If duplicates found, ask for confirmation
-    if !duplicates.is_empty() {
-        eprintln!(
-            "{}",
-            "⚠ Warning: Duplicate columns detected!".yellow().bold()
-        );
-        eprintln!(
-            "  Duplicated columns: {}",
-            duplicates
-                .iter()
-                .map(|s| format!("'{}'", s))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
-        eprint!("  Do you want to proceed? [y/N]: ");
-        io::stderr().flush()?;
-
-        // Read from /dev/tty to get user input from terminal, not from stdin
-        // This ensures we don't conflict with piped CSV data
-        use atty::{is, Stream};
-        use std::fs::File;
-        use std::io::BufRead;
-
-        let response = if let Ok(tty) = File::open("/dev/tty") {
-            let mut tty_reader = io::BufReader::new(tty);
-            let mut response = String::new();
-            tty_reader.read_line(&mut response)?;
-            response.trim().to_lowercase()
-        } else if is(Stream::Stdin) {
-            // Stdin is a TTY (terminal), safe to read from it
-            let mut response = String::new();
-            io::stdin().read_line(&mut response)?;
-            response.trim().to_lowercase()
-        } else {
-            // In non-interactive mode (tests, pipes), default to "no"
-            eprintln!("  (Non-interactive mode: defaulting to 'no')");
-            "n".to_string()
-        };
-
-        if response != "y" && response != "yes" {
-            eprintln!("Operation cancelled.");
-            return Ok(());
-        }
-    }
-
-    // Find the indices of the requested columns
-    let mut col_indices: Vec<usize> = Vec::new();
-    for col_name in &requested_cols {
-        match headers.iter().position(|h| h == *col_name) {
-            Some(idx) => col_indices.push(idx),
-            None => {
-                return Err(format!(
-                    "Column '{}' not found in CSV. Available columns: {}",
-                    col_name,
-                    headers.iter().collect::<Vec<_>>().join(", ")
-                )
-                .into());
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_reorders_fields() {
+        let records = vec![Ok(StringRecord::from(vec!["a", "b", "c"]))];
+        let selected: Vec<StringRecord> = select(records.into_iter(), vec![2, 0, 5])
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(selected, vec![vec!["c", "a", ""]]);
     }
 
-    // Use CSV writer for proper quoting
-    let stdout = io::stdout();
-    let mut writer = csv_writer(stdout.lock(), delimiter);
-
-    // Print selected headers
-    writer.write_record(requested_cols)?;
-
-    // Print selected columns for each row
-    for result in csv.records() {
-        let record = result?;
-        let selected_values: Vec<&str> = col_indices
-            .iter()
-            .map(|&idx| record.get(idx).unwrap_or(""))
-            .collect();
-        writer.write_record(selected_values)?;
+    #[test]
+    fn test_duplicate_columns() {
+        let headers = StringRecord::from(vec!["id", "name", "age"]);
+        assert_eq!(duplicate_columns(&headers, &[0, 1, 0, 0, 2]), vec!["id"]);
+        assert!(duplicate_columns(&headers, &[2, 1]).is_empty());
     }
-
-    writer.flush()?;
-    Ok(())
 }
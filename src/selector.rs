@@ -0,0 +1,472 @@
+//! Column selector language shared by every command that takes columns.
+//!
+//! A selector is a comma-separated list of items:
+//!
+//! - `name` — a column by header name (exact match wins over everything else)
+//! - `3` — a column by 1-based index
+//! - `3-7`, `id-price` — an inclusive range by index or name; either end may
+//!   be left open (`5-`, `-price`)
+//! - `/^score_/` — every column whose name matches the regex (`/.../i` for
+//!   case-insensitive)
+//! - `"last, first"` — a quoted name, which may contain commas or dashes
+//! - `!comment` — exclude the columns of any of the forms above
+//!
+//! Included columns are returned in the order they are listed. When a
+//! selector only has exclusions, it starts from all columns.
+
+use crate::error::{CliError, Result};
+use csv::StringRecord;
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug, Clone)]
+pub struct Selector {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    exclude: bool,
+    target: Target,
+}
+
+#[derive(Debug, Clone)]
+enum Target {
+    /// Unquoted text: a name, an index, or a range, decided against the headers
+    Bare(String),
+    /// Quoted name
+    Name(String),
+    /// Range with at least one quoted end
+    Range(Option<Endpoint>, Option<Endpoint>),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Endpoint {
+    Bare(String),
+    Name(String),
+}
+
+impl Selector {
+    pub fn parse(s: &str) -> Result<Selector> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut items = Vec::new();
+        let mut i = 0;
+
+        loop {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            let exclude = i < chars.len() && chars[i] == '!';
+            if exclude {
+                i += 1;
+            }
+
+            let target = if chars.get(i) == Some(&'/') {
+                parse_regex(&chars, &mut i)?
+            } else {
+                parse_term(&chars, &mut i)?
+            };
+            items.push(Item { exclude, target });
+
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            match chars.get(i) {
+                None => break,
+                Some(',') => i += 1,
+                Some(c) => {
+                    return Err(CliError::Usage(format!(
+                        "Invalid column selector '{}': unexpected '{}' at position {}",
+                        s,
+                        c,
+                        i + 1
+                    )))
+                }
+            }
+        }
+
+        Ok(Selector { items })
+    }
+
+    /// Resolve the selector to 0-based column indices against `headers`.
+    pub fn select(&self, headers: &StringRecord) -> Result<Vec<usize>> {
+        let mut selected: Vec<usize> = Vec::new();
+        let mut excluded: Vec<usize> = Vec::new();
+
+        for item in &self.items {
+            let cols = item.target.resolve(headers)?;
+            if item.exclude {
+                excluded.extend(cols);
+            } else {
+                selected.extend(cols);
+            }
+        }
+
+        if self.items.iter().all(|item| item.exclude) {
+            selected = (0..headers.len()).collect();
+        }
+        selected.retain(|idx| !excluded.contains(idx));
+        Ok(selected)
+    }
+
+    /// Resolve a selector that must name exactly one column.
+    pub fn select_one(&self, headers: &StringRecord) -> Result<usize> {
+        let cols = self.select(headers)?;
+        match cols.as_slice() {
+            [idx] => Ok(*idx),
+            _ => Err(CliError::Usage(format!(
+                "Expected exactly one column, but the selector matches {}: {}",
+                cols.len(),
+                cols.iter()
+                    .map(|&i| headers.get(i).unwrap_or(""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
+
+impl Target {
+    fn resolve(&self, headers: &StringRecord) -> Result<Vec<usize>> {
+        match self {
+            Target::Name(name) => find_name(headers, name)
+                .map(|idx| vec![idx])
+                .ok_or_else(|| not_found(headers, name)),
+            Target::Bare(text) => resolve_bare(headers, text),
+            Target::Range(start, end) => {
+                let start = match start {
+                    Some(e) => e.resolve(headers).ok_or_else(|| e.not_found(headers))?,
+                    None => 0,
+                };
+                let end = match end {
+                    Some(e) => e.resolve(headers).ok_or_else(|| e.not_found(headers))?,
+                    None => headers.len().saturating_sub(1),
+                };
+                Ok(range(start, end))
+            }
+            Target::Regex(re) => {
+                let cols: Vec<usize> = headers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, h)| re.is_match(h))
+                    .map(|(i, _)| i)
+                    .collect();
+                if cols.is_empty() {
+                    return Err(CliError::ColumnNotFound(format!(
+                        "No columns match /{}/. Available columns: {}",
+                        re.as_str(),
+                        available(headers)
+                    )));
+                }
+                Ok(cols)
+            }
+        }
+    }
+}
+
+impl Endpoint {
+    fn resolve(&self, headers: &StringRecord) -> Option<usize> {
+        match self {
+            Endpoint::Name(name) => find_name(headers, name),
+            Endpoint::Bare(text) => find_name(headers, text).or_else(|| find_index(headers, text)),
+        }
+    }
+
+    fn not_found(&self, headers: &StringRecord) -> CliError {
+        match self {
+            Endpoint::Name(text) | Endpoint::Bare(text) => not_found(headers, text),
+        }
+    }
+}
+
+/// Unquoted text is a name if a header matches it exactly, then an index,
+/// then a range split at the first `-` where both ends resolve.
+fn resolve_bare(headers: &StringRecord, text: &str) -> Result<Vec<usize>> {
+    if let Some(idx) = find_name(headers, text).or_else(|| find_index(headers, text)) {
+        return Ok(vec![idx]);
+    }
+
+    for (pos, _) in text.match_indices('-') {
+        let left = text[..pos].trim();
+        let right = text[pos + 1..].trim();
+        let start = if left.is_empty() {
+            Some(0)
+        } else {
+            find_name(headers, left).or_else(|| find_index(headers, left))
+        };
+        let end = if right.is_empty() {
+            Some(headers.len().saturating_sub(1))
+        } else {
+            find_name(headers, right).or_else(|| find_index(headers, right))
+        };
+        if let (Some(start), Some(end)) = (start, end) {
+            if !headers.is_empty() {
+                return Ok(range(start, end));
+            }
+        }
+    }
+
+    if let Ok(0) = text.parse::<usize>() {
+        return Err(CliError::Usage(
+            "Column indices are 1-based; 0 is not a valid column".to_string(),
+        ));
+    }
+    Err(not_found(headers, text))
+}
+
+fn range(start: usize, end: usize) -> Vec<usize> {
+    if start <= end {
+        (start..=end).collect()
+    } else {
+        (end..=start).rev().collect()
+    }
+}
+
+pub(crate) fn find_name(headers: &StringRecord, name: &str) -> Option<usize> {
+    headers.iter().position(|h| h == name)
+}
+
+pub(crate) fn find_index(headers: &StringRecord, text: &str) -> Option<usize> {
+    match text.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= headers.len() => Some(n - 1),
+        _ => None,
+    }
+}
+
+fn available(headers: &StringRecord) -> String {
+    headers.iter().collect::<Vec<_>>().join(", ")
+}
+
+pub(crate) fn not_found(headers: &StringRecord, name: &str) -> CliError {
+    CliError::ColumnNotFound(format!(
+        "Column '{}' not found in CSV. Available columns: {}",
+        name,
+        available(headers)
+    ))
+}
+
+fn parse_regex(chars: &[char], i: &mut usize) -> Result<Target> {
+    *i += 1; // opening '/'
+    let mut pattern = String::new();
+    loop {
+        match chars.get(*i) {
+            None => {
+                return Err(CliError::Usage(format!(
+                    "Unterminated regex selector '/{}'",
+                    pattern
+                )))
+            }
+            Some('\\') if chars.get(*i + 1) == Some(&'/') => {
+                pattern.push('/');
+                *i += 2;
+            }
+            Some('/') => {
+                *i += 1;
+                break;
+            }
+            Some(&c) => {
+                pattern.push(c);
+                *i += 1;
+            }
+        }
+    }
+
+    let case_insensitive = chars.get(*i) == Some(&'i');
+    if case_insensitive {
+        *i += 1;
+    }
+
+    let re = RegexBuilder::new(&pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| CliError::Usage(format!("Invalid regex selector '/{}/': {}", pattern, e)))?;
+    Ok(Target::Regex(re))
+}
+
+/// Parse a name, index or range up to the next top-level comma.
+fn parse_term(chars: &[char], i: &mut usize) -> Result<Target> {
+    let start = parse_endpoint(chars, i)?;
+    skip_spaces(chars, i);
+
+    match (start, chars.get(*i)) {
+        (None, Some(',') | None) => Err(CliError::Usage("Empty column selector".to_string())),
+        // Bare text running into a quote: `price-"last, first"`
+        (Some(Endpoint::Bare(text)), Some('"')) => match text.strip_suffix('-') {
+            Some(left) => {
+                let left = left.trim();
+                let start = (!left.is_empty()).then(|| Endpoint::Bare(left.to_string()));
+                let end = parse_endpoint(chars, i)?;
+                Ok(Target::Range(start, end))
+            }
+            None => Err(CliError::Usage(format!(
+                "Unexpected quote after '{}' in column selector",
+                text
+            ))),
+        },
+        (Some(Endpoint::Bare(text)), _) => Ok(Target::Bare(text)),
+        (Some(Endpoint::Name(name)), Some(',') | None) => Ok(Target::Name(name)),
+        (start, Some('-')) => {
+            *i += 1;
+            skip_spaces(chars, i);
+            let end = parse_endpoint(chars, i)?;
+            Ok(Target::Range(start, end))
+        }
+        (_, Some(c)) => Err(CliError::Usage(format!(
+            "Unexpected '{}' in column selector",
+            c
+        ))),
+    }
+}
+
+/// Parse one quoted or bare endpoint. Bare text stops at a comma, or at a
+/// `-` directly followed by a quote (the start of a quoted range end).
+fn parse_endpoint(chars: &[char], i: &mut usize) -> Result<Option<Endpoint>> {
+    skip_spaces(chars, i);
+    if chars.get(*i) == Some(&'"') {
+        *i += 1;
+        let mut name = String::new();
+        loop {
+            match chars.get(*i) {
+                None => {
+                    return Err(CliError::Usage(format!(
+                        "Unterminated quoted column name \"{}",
+                        name
+                    )))
+                }
+                Some('"') if chars.get(*i + 1) == Some(&'"') => {
+                    name.push('"');
+                    *i += 2;
+                }
+                Some('"') => {
+                    *i += 1;
+                    break;
+                }
+                Some(&c) => {
+                    name.push(c);
+                    *i += 1;
+                }
+            }
+        }
+        return Ok(Some(Endpoint::Name(name)));
+    }
+
+    let mut text = String::new();
+    while let Some(&c) = chars.get(*i) {
+        if c == ',' || c == '"' {
+            break;
+        }
+        text.push(c);
+        *i += 1;
+    }
+    let text = text.trim_end();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Endpoint::Bare(text.to_string())))
+}
+
+fn skip_spaces(chars: &[char], i: &mut usize) {
+    while *i < chars.len() && chars[*i].is_whitespace() {
+        *i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(names: &[&str]) -> StringRecord {
+        StringRecord::from(names.to_vec())
+    }
+
+    fn select(selector: &str, names: &[&str]) -> Result<Vec<usize>> {
+        Selector::parse(selector)?.select(&headers(names))
+    }
+
+    const COLS: &[&str] = &["id", "name", "price", "score_a", "score_b", "comment"];
+
+    #[test]
+    fn test_names_and_indices() {
+        assert_eq!(select("name, id", COLS).unwrap(), vec![1, 0]);
+        assert_eq!(select("3,1", COLS).unwrap(), vec![2, 0]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(select("2-4", COLS).unwrap(), vec![1, 2, 3]);
+        assert_eq!(select("id-price", COLS).unwrap(), vec![0, 1, 2]);
+        assert_eq!(select("price-id", COLS).unwrap(), vec![2, 1, 0]);
+        assert_eq!(select("5-", COLS).unwrap(), vec![4, 5]);
+        assert_eq!(select("-name", COLS).unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_regex() {
+        assert_eq!(select("/^score_/", COLS).unwrap(), vec![3, 4]);
+        assert_eq!(select("/^SCORE_/i,id", COLS).unwrap(), vec![3, 4, 0]);
+        assert!(select("/^nothing/", COLS).is_err());
+        assert!(Selector::parse("/[/").is_err());
+    }
+
+    #[test]
+    fn test_regex_may_contain_commas() {
+        let cols = &["a", "aa", "aaa", "aaaa"];
+        assert_eq!(select("/^a{2,3}$/", cols).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_exclusions() {
+        assert_eq!(select("!comment", COLS).unwrap(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(select("!/^score_/,!1", COLS).unwrap(), vec![1, 2, 5]);
+        assert_eq!(select("1-4,!name", COLS).unwrap(), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_quoted_names() {
+        let cols = &["id", "last, first", "a-b", "say \"hi\""];
+        assert_eq!(select("\"last, first\",id", cols).unwrap(), vec![1, 0]);
+        assert_eq!(select("\"say \"\"hi\"\"\"", cols).unwrap(), vec![3]);
+        assert_eq!(select("id-\"last, first\"", cols).unwrap(), vec![0, 1]);
+        assert_eq!(select("\"last, first\"-4", cols).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_names_with_dashes_prefer_exact_match() {
+        let cols = &["first-name", "last-name", "x"];
+        assert_eq!(select("last-name", cols).unwrap(), vec![1]);
+        assert_eq!(select("first-name-last-name", cols).unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_numeric_header_names_win_over_indices() {
+        let cols = &["2024", "1", "2"];
+        assert_eq!(select("1", cols).unwrap(), vec![1]);
+        assert_eq!(select("3", cols).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = select("missing", COLS).unwrap_err().to_string();
+        assert!(err.contains("Column 'missing' not found in CSV."));
+        assert!(err.contains("Available columns: id, name, price"));
+        assert!(select("0", COLS)
+            .unwrap_err()
+            .to_string()
+            .contains("1-based"));
+        assert!(select("7", COLS).is_err());
+        assert!(Selector::parse("id,").is_err());
+        assert!(Selector::parse("\"open").is_err());
+    }
+
+    #[test]
+    fn test_select_one() {
+        let h = headers(COLS);
+        assert_eq!(Selector::parse("price").unwrap().select_one(&h).unwrap(), 2);
+        assert!(Selector::parse("/^score/")
+            .unwrap()
+            .select_one(&h)
+            .unwrap_err()
+            .to_string()
+            .contains("score_a, score_b"));
+    }
+}
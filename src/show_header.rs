@@ -1,28 +1,11 @@
-use crate::utils::{detect_delimiter, input_reader};
-use colored::Colorize;
-use csv::ReaderBuilder;
-use std::error::Error;
-
-pub fn show_header(path: Option<&str>) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-
-    let delimiter = detect_delimiter(&mut *reader)?;
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers().expect("Cannot read headers");
-
-    println!(
-        "{:>8}: {:<8}",
-        "Index".green().bold(),
-        "ColName".green().bold()
-    );
-    for (i, h) in headers.iter().enumerate() {
-        println!("{:>8}: {:<8}", i, h);
-    }
-    Ok(())
+use crate::error::Result;
+use crate::input::CsvInput;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
+
+/// The header row of `path` (or stdin); "1".."N" with `--no-header`.
+pub fn read_headers(path: Option<&str>, opts: &CsvOptions) -> Result<StringRecord> {
+    Ok(CsvInput::open(path, opts)?.headers)
 }
 
 #[cfg(test)]
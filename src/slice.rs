@@ -0,0 +1,256 @@
+//! Row ranges (`slice`) and the last rows of an input (`tail`), as CSV.
+//!
+//! `tail` on a plain file reads backwards from its end to find where the
+//! last rows start, telling record boundaries from line breaks inside
+//! quoted fields by the parity of the quotes after them: with RFC 4180
+//! quoting, a line break is inside a field exactly when an odd number of
+//! quote characters follows it. A stray quote inside an unquoted field
+//! breaks that rule, so the rows after the guessed start are checked before
+//! they are used, and otherwise the whole input is read through, keeping
+//! the last rows in a ring buffer. Other inputs are always read through.
+
+use crate::error::{CliError, Result};
+use crate::index::{is_utf8, reader_at};
+use crate::input::{CsvInput, IntoRecords};
+use crate::utils::{Compression, CsvOptions};
+use csv::StringRecord;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::iter::Take;
+
+/// Size of the blocks read backwards by `tail`.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// The data records of `input` from `start` (0-based), at most `len` of
+/// them. A fresh index lets the reader seek to `start`.
+pub fn slice(
+    mut input: CsvInput,
+    start: usize,
+    len: Option<usize>,
+    opts: &CsvOptions,
+) -> Result<Take<IntoRecords>> {
+    input.skip_records(start, opts)?;
+    Ok(input.into_records().take(len.unwrap_or(usize::MAX)))
+}
+
+/// Turn an exclusive `end` into a length, checking it is not before `start`.
+pub fn range_len(start: usize, end: usize) -> Result<usize> {
+    end.checked_sub(start).ok_or_else(|| {
+        CliError::Usage(format!(
+            "--end ({}) must not be before --start ({})",
+            end, start
+        ))
+    })
+}
+
+/// The last `n` data records of `input`, in order.
+pub fn tail(mut input: CsvInput, n: usize, opts: &CsvOptions) -> Result<Vec<StringRecord>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    if let Some(path) = input.path.clone() {
+        if seekable(&path, opts)? {
+            let quote = opts.quote.unwrap_or('"') as u8;
+            if let Some(offset) = last_records_offset(&path, n, quote)? {
+                let (fields, delimiter) = (input.headers.len(), input.delimiter as u8);
+                if whole_records_from(&path, offset, n, fields, delimiter, quote)? {
+                    input.resume_at(&path, offset, opts)?;
+                    return last_records(input.records(), n);
+                }
+            }
+        }
+    }
+    last_records(input.records(), n)
+}
+
+/// The last `n` of `records`.
+fn last_records(
+    records: impl Iterator<Item = Result<StringRecord>>,
+    n: usize,
+) -> Result<Vec<StringRecord>> {
+    let mut last = VecDeque::with_capacity(n);
+    for record in records {
+        if last.len() == n {
+            last.pop_front();
+        }
+        last.push_back(record?);
+    }
+    Ok(last.into())
+}
+
+/// Whether `path` holds, from `offset` to its end, at least `n` records
+/// of `fields` fields each, quoted strictly as in RFC 4180. A guessed offset
+/// that is too early still passes, since only the last `n` records are
+/// kept; a stray quote, which could have misled `last_records_offset` into
+/// starting inside a record, fails.
+fn whole_records_from(
+    path: &str,
+    offset: u64,
+    n: usize,
+    fields: usize,
+    delimiter: u8,
+    quote: u8,
+) -> Result<bool> {
+    #[derive(PartialEq)]
+    enum State {
+        FieldStart,
+        Unquoted,
+        Quoted,
+        /// After a quote in a quoted field: its end, or the first of two
+        QuoteInQuoted,
+    }
+    let mut state = State::FieldStart;
+    let mut records = 0;
+    let mut record_fields = 1;
+    // Whether the current line has content; blank lines are not records
+    let mut in_record = false;
+    for byte in reader_at(path, offset)?.bytes() {
+        let byte = byte?;
+        state = match state {
+            State::Quoted if byte == quote => State::QuoteInQuoted,
+            State::Quoted => State::Quoted,
+            State::QuoteInQuoted if byte == quote => State::Quoted,
+            State::QuoteInQuoted if byte != delimiter && byte != b'\n' && byte != b'\r' => {
+                return Ok(false)
+            }
+            _ if byte == quote => {
+                if state == State::Unquoted {
+                    return Ok(false);
+                }
+                in_record = true;
+                State::Quoted
+            }
+            _ if byte == delimiter => {
+                in_record = true;
+                record_fields += 1;
+                State::FieldStart
+            }
+            _ if byte == b'\n' => {
+                if in_record {
+                    if record_fields != fields {
+                        return Ok(false);
+                    }
+                    records += 1;
+                    record_fields = 1;
+                    in_record = false;
+                }
+                State::FieldStart
+            }
+            _ if byte == b'\r' => State::FieldStart,
+            _ => {
+                in_record = true;
+                State::Unquoted
+            }
+        };
+    }
+    if state == State::Quoted || (in_record && record_fields != fields) {
+        return Ok(false);
+    }
+    Ok(records + in_record as usize >= n)
+}
+
+/// Whether byte offsets in `path` are offsets in the CSV as parsed, and its
+/// quoting lets `last_records_offset` find record boundaries.
+fn seekable(path: &str, opts: &CsvOptions) -> Result<bool> {
+    if Compression::from_path(path).is_some()
+        || !is_utf8(opts.encoding)
+        || opts.escape.is_some()
+        || opts.no_double_quote
+    {
+        return Ok(false);
+    }
+    let mut start = Vec::with_capacity(8);
+    File::open(path)
+        .map_err(|source| CliError::Open {
+            path: path.to_string(),
+            source,
+        })?
+        .take(8)
+        .read_to_end(&mut start)?;
+    let utf16 = start.starts_with(&[0xFF, 0xFE]) || start.starts_with(&[0xFE, 0xFF]);
+    Ok(Compression::from_magic(&start).is_none() && !utf16)
+}
+
+/// Byte offset where the last `n` records of `path` start, or `None` when
+/// there are no more than `n` records after the first line. Blank lines are
+/// not records, as for the CSV reader.
+fn last_records_offset(path: &str, n: usize, quote: u8) -> Result<Option<u64>> {
+    let mut file = File::open(path).map_err(|source| CliError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    let mut pos = file.metadata()?.len();
+    let mut buf = vec![0; BLOCK_SIZE];
+    // Whether an odd number of quotes follows
+    let mut quoted = false;
+    let mut found = 0;
+    // Whether a record follows the line break about to be found
+    let mut content = false;
+    while pos > 0 {
+        let len = pos.min(BLOCK_SIZE as u64) as usize;
+        pos -= len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buf[..len])?;
+        for i in (0..len).rev() {
+            match buf[i] {
+                b'\n' if !quoted => {
+                    if content {
+                        found += 1;
+                        if found == n {
+                            return Ok(Some(pos + i as u64 + 1));
+                        }
+                    }
+                    content = false;
+                }
+                b'\r' => {}
+                byte => {
+                    if byte == quote {
+                        quoted = !quoted;
+                    }
+                    content = true;
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_last_records_offset_skips_quoted_line_breaks() {
+        let csv = "id,note\n1,\"a\nb\"\n2,plain\n\n3,\"x\n\n\"\"y\"\"\"\r\n";
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(csv.as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let start_of = |needle: &str| Some(csv.find(needle).unwrap() as u64);
+        assert_eq!(last_records_offset(path, 1, b'"').unwrap(), start_of("3,"));
+        assert_eq!(last_records_offset(path, 2, b'"').unwrap(), start_of("2,"));
+        assert_eq!(last_records_offset(path, 3, b'"').unwrap(), start_of("1,"));
+        assert_eq!(last_records_offset(path, 4, b'"').unwrap(), None);
+    }
+
+    #[test]
+    fn test_whole_records_from_rejects_stray_quotes() {
+        let csv = "id,note\n1,\"a\nb,c\"\"\"\n2,plain\r\n3,5\" x\n4,\"\"\n";
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(csv.as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let check = |needle: &str, n: usize| {
+            let offset = csv.find(needle).unwrap() as u64;
+            whole_records_from(path, offset, n, 2, b',', b'"').unwrap()
+        };
+        assert!(check("4,", 1));
+        assert!(!check("4,", 2));
+        // From "2," on, the 5" in an unquoted field is a stray quote
+        assert!(!check("2,", 1));
+        // Inside the quoted field of record 1
+        assert!(!check("b,", 1));
+    }
+}
@@ -0,0 +1,657 @@
+//! Stable multi-key sorting with an external merge sort.
+//!
+//! Records are buffered until their estimated size reaches the memory
+//! budget; each full buffer is sorted and spilled to a temporary file as a
+//! run, and the runs are then merged k ways. Inputs that fit in the budget
+//! never touch the disk. Ties keep their input order, in memory and across
+//! runs alike.
+
+use crate::error::{CliError, Result};
+use crate::input::{counting_opts, CsvInput, Source};
+use crate::selector::Selector;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::path::Path;
+
+/// Most runs merged at once; more are first merged into longer runs, so
+/// that the number of open files stays bounded.
+const MERGE_FAN_IN: usize = 64;
+
+/// How the values of a sort key compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKind {
+    /// Numeric when every non-empty value of the column is a number, else
+    /// lexical; see [`SortKey::infer_kinds`]. Compared lexically until then.
+    Auto,
+    /// Byte-wise string order
+    Lexical,
+    /// As numbers
+    Numeric,
+    /// Digit runs as numbers, the rest as strings: "file2" < "file10"
+    Natural,
+    /// As dates or date-times: YYYY-MM-DD or YYYY/MM/DD, optionally followed
+    /// by HH:MM[:SS[.fff]] and a Z or ±HH:MM offset
+    Date,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    /// 0-based column index
+    pub column: usize,
+    pub kind: SortKind,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Parse a comma-separated list of `COLUMN[:KIND][:asc|desc]` keys, e.g.
+    /// "price:numeric:desc,name", resolving the columns against `headers`.
+    pub fn parse_list(spec: &str, headers: &StringRecord) -> Result<Vec<SortKey>> {
+        spec.split(',')
+            .map(|item| {
+                let (column, kind, descending) = parse_key(item)?;
+                Ok(SortKey {
+                    column: Selector::parse(column)?.select_one(headers)?,
+                    kind,
+                    descending,
+                })
+            })
+            .collect()
+    }
+
+    /// Resolve the `Auto` keys from the values of their columns in `records`:
+    /// numeric when every non-empty value is a number, lexical otherwise.
+    /// Reading stops once every such key turned out lexical.
+    pub fn infer_kinds(
+        keys: &mut [SortKey],
+        records: impl Iterator<Item = Result<StringRecord>>,
+    ) -> Result<()> {
+        let mut numeric: Vec<bool> = keys.iter().map(|key| key.kind == SortKind::Auto).collect();
+        for record in records {
+            if !numeric.contains(&true) {
+                break;
+            }
+            let record = record?;
+            for (key, numeric) in keys.iter().zip(numeric.iter_mut()) {
+                let field = record.get(key.column).unwrap_or("");
+                if *numeric && !field.is_empty() && field.trim().parse::<f64>().is_err() {
+                    *numeric = false;
+                }
+            }
+        }
+        for (key, numeric) in keys.iter_mut().zip(numeric) {
+            if key.kind == SortKind::Auto {
+                key.kind = if numeric {
+                    SortKind::Numeric
+                } else {
+                    SortKind::Lexical
+                };
+            }
+        }
+        Ok(())
+    }
+
+    fn extract(&self, record: &StringRecord) -> KeyPart {
+        self.part(record.get(self.column).unwrap_or(""))
+    }
+
+    /// How the values `a` and `b` compare under this key.
+    pub(crate) fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.part(a).cmp(&self.part(b))
+    }
+
+    fn part(&self, field: &str) -> KeyPart {
+        let value = if field.is_empty() {
+            None
+        } else {
+            match self.kind {
+                SortKind::Auto | SortKind::Lexical => Some(KeyValue::Text(field.to_string())),
+                SortKind::Numeric => field.trim().parse().ok().map(KeyValue::Number),
+                SortKind::Natural => Some(KeyValue::Natural(natural_chunks(field))),
+                SortKind::Date => parse_date(field.trim()).map(KeyValue::Date),
+            }
+        };
+        KeyPart {
+            value,
+            descending: self.descending,
+        }
+    }
+}
+
+/// Split a `COLUMN[:KIND][:asc|desc]` key into its column, kind and direction.
+fn parse_key(item: &str) -> Result<(&str, SortKind, bool)> {
+    let mut parts = item.trim().split(':');
+    let column = parts.next().unwrap_or("");
+    let mut kind = SortKind::Auto;
+    let mut descending = false;
+    for modifier in parts {
+        match modifier.trim().to_ascii_lowercase().as_str() {
+            "asc" => descending = false,
+            "desc" => descending = true,
+            "lexical" => kind = SortKind::Lexical,
+            "numeric" => kind = SortKind::Numeric,
+            "natural" => kind = SortKind::Natural,
+            "date" => kind = SortKind::Date,
+            other => {
+                return Err(CliError::Usage(format!(
+                    "Invalid sort key '{}': unknown modifier '{}' \
+                     (expected asc, desc, lexical, numeric, natural or date)",
+                    item.trim(),
+                    other
+                )))
+            }
+        }
+    }
+    Ok((column, kind, descending))
+}
+
+/// One key of one record. Empty values, and values that do not parse as
+/// the key's kind, sort last in both directions.
+#[derive(Debug)]
+struct KeyPart {
+    value: Option<KeyValue>,
+    descending: bool,
+}
+
+#[derive(Debug)]
+enum KeyValue {
+    Text(String),
+    Number(f64),
+    Natural(Vec<Chunk>),
+    /// Seconds and nanoseconds since the epoch
+    Date((i64, u32)),
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Chunk {
+    /// A digit run without leading zeros, ordered by length first
+    Digits(usize, String),
+    Text(String),
+}
+
+impl Ord for KeyPart {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.value, &other.value) {
+            (Some(a), Some(b)) => {
+                let ordering = match (a, b) {
+                    (KeyValue::Text(a), KeyValue::Text(b)) => a.cmp(b),
+                    (KeyValue::Number(a), KeyValue::Number(b)) => a.total_cmp(b),
+                    (KeyValue::Natural(a), KeyValue::Natural(b)) => a.cmp(b),
+                    (KeyValue::Date(a), KeyValue::Date(b)) => a.cmp(b),
+                    _ => Ordering::Equal,
+                };
+                if self.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for KeyPart {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for KeyPart {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for KeyPart {}
+
+/// Split `s` into digit and non-digit runs, which compare in natural order.
+pub(crate) fn natural_chunks(s: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let is_digit = c.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        if is_digit {
+            let digits = chunk.trim_start_matches('0');
+            chunks.push(Chunk::Digits(digits.len(), digits.to_string()));
+        } else {
+            chunks.push(Chunk::Text(chunk.to_string()));
+        }
+        rest = tail;
+    }
+    chunks
+}
+
+/// Parse a date or date-time to seconds and nanoseconds since the epoch.
+fn parse_date(s: &str) -> Option<(i64, u32)> {
+    let bytes = s.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let part = s.get(range)?;
+        if part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse().ok()
+        } else {
+            None
+        }
+    };
+    let separator = *bytes.get(4)?;
+    if !(separator == b'-' || separator == b'/') || bytes.get(7) != Some(&separator) {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+    let mut nanos = 0;
+
+    let mut rest = &s[10..];
+    if let Some(time) = rest.strip_prefix(['T', ' ']) {
+        let time_len = time
+            .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '.'))
+            .unwrap_or(time.len());
+        let (clock, zone) = time.split_at(time_len);
+        let mut fields = clock.split(':');
+        let hours: i64 = fields.next()?.parse().ok()?;
+        let minutes: i64 = fields.next()?.parse().ok()?;
+        let (secs, frac) = match fields.next() {
+            Some(sec) => match sec.split_once('.') {
+                Some((whole, frac)) => (whole.parse().ok()?, frac),
+                None => (sec.parse().ok()?, ""),
+            },
+            None => (0, ""),
+        };
+        if fields.next().is_some() || hours > 23 || minutes > 59 || secs > 60 {
+            return None;
+        }
+        seconds += hours * 3600 + minutes * 60 + secs;
+        if !frac.is_empty() {
+            let digits: String = frac.chars().chain("000000000".chars()).take(9).collect();
+            nanos = digits.parse().ok()?;
+        }
+        rest = zone;
+    }
+
+    match rest {
+        "" | "Z" => {}
+        zone => {
+            let sign = match zone.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = zone[1..].split_once(':').unwrap_or((&zone[1..], "0"));
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            seconds -= sign * offset;
+        }
+    }
+    Some((seconds, nanos))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn keys_of(keys: &[SortKey], record: &StringRecord) -> Vec<KeyPart> {
+    keys.iter().map(|key| key.extract(record)).collect()
+}
+
+/// Rough heap size of a buffered record and its keys.
+fn footprint(record: &StringRecord) -> usize {
+    2 * record.as_slice().len() + 24 * record.len() + 128
+}
+
+/// The sorted records of an input.
+pub struct Sorted {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    inner: SortedInner,
+    /// Number of runs spilled to disk, 0 when the input fit in memory
+    pub runs: usize,
+}
+
+enum SortedInner {
+    Memory(std::vec::IntoIter<(Vec<KeyPart>, StringRecord)>),
+    Merge(Merge),
+}
+
+impl Iterator for Sorted {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            SortedInner::Memory(records) => records.next().map(|(_, record)| Ok(record)),
+            SortedInner::Merge(merge) => merge.next(),
+        }
+    }
+}
+
+/// Sort the records of `path` (or stdin) by the `COLUMN[:KIND][:asc|desc]`
+/// keys in `spec`, as [`sort`] does. Keys without a kind take a first pass
+/// to infer it (stdin is spooled to a temporary file for that).
+pub fn sort_columns(
+    path: Option<&str>,
+    spec: &str,
+    memory: usize,
+    temp_dir: Option<&Path>,
+    opts: &CsvOptions,
+) -> Result<Sorted> {
+    let mut inferred = false;
+    for item in spec.split(',') {
+        inferred |= parse_key(item)?.1 == SortKind::Auto;
+    }
+    if !inferred {
+        let input = CsvInput::open(path, opts)?;
+        let keys = SortKey::parse_list(spec, &input.headers)?;
+        return sort(input, &keys, memory, temp_dir);
+    }
+
+    let (source, scan) = Source::open(path, &counting_opts(opts)?)?;
+    let mut keys = SortKey::parse_list(spec, &scan.headers)?;
+    let delimiter = scan.delimiter;
+    SortKey::infer_kinds(&mut keys, scan.into_records())?;
+    sort(source.reopen(delimiter, opts)?, &keys, memory, temp_dir)
+}
+
+/// Sort the records of `input` by `keys`, holding about `memory` bytes of
+/// records before spilling sorted runs to temporary files in `temp_dir`
+/// (the system's temporary directory if `None`).
+pub fn sort(
+    input: CsvInput,
+    keys: &[SortKey],
+    memory: usize,
+    temp_dir: Option<&Path>,
+) -> Result<Sorted> {
+    let temp_dir = temp_dir.map_or_else(std::env::temp_dir, Path::to_path_buf);
+    let delimiter = input.delimiter;
+    let headers = input.headers.clone();
+    let mut buffer: Vec<(Vec<KeyPart>, StringRecord)> = Vec::new();
+    let mut buffered = 0;
+    let mut runs = Vec::new();
+
+    for record in input.into_records() {
+        let record = record?;
+        buffered += footprint(&record);
+        buffer.push((keys_of(keys, &record), record));
+        if buffered >= memory {
+            runs.push(write_run(&mut buffer, &temp_dir)?);
+            buffered = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        buffer.sort_by(|a, b| a.0.cmp(&b.0));
+        return Ok(Sorted {
+            delimiter,
+            headers,
+            inner: SortedInner::Memory(buffer.into_iter()),
+            runs: 0,
+        });
+    }
+    if !buffer.is_empty() {
+        runs.push(write_run(&mut buffer, &temp_dir)?);
+    }
+    let spilled = runs.len();
+
+    // Merge groups of consecutive runs until one pass can merge them all
+    while runs.len() > MERGE_FAN_IN {
+        let mut merged = Vec::new();
+        let mut pending = runs.into_iter().peekable();
+        while pending.peek().is_some() {
+            let group: Vec<File> = pending.by_ref().take(MERGE_FAN_IN).collect();
+            let mut run = temp_file(&temp_dir)?;
+            {
+                let mut writer = csv::Writer::from_writer(BufWriter::new(&mut run));
+                for record in Merge::new(group, keys)? {
+                    writer.write_record(&record?)?;
+                }
+                writer.flush()?;
+            }
+            run.seek(SeekFrom::Start(0))?;
+            merged.push(run);
+        }
+        runs = merged;
+    }
+
+    Ok(Sorted {
+        delimiter,
+        headers,
+        inner: SortedInner::Merge(Merge::new(runs, keys)?),
+        runs: spilled,
+    })
+}
+
+pub(crate) fn temp_file(dir: &Path) -> Result<File> {
+    tempfile::tempfile_in(dir).map_err(|source| CliError::Open {
+        path: dir.display().to_string(),
+        source,
+    })
+}
+
+/// Sort `buffer` and write it to a new temporary file, rewound for reading.
+fn write_run(buffer: &mut Vec<(Vec<KeyPart>, StringRecord)>, dir: &Path) -> Result<File> {
+    buffer.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut run = temp_file(dir)?;
+    {
+        let mut writer = csv::Writer::from_writer(BufWriter::new(&mut run));
+        for (_, record) in buffer.drain(..) {
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+    }
+    run.seek(SeekFrom::Start(0))?;
+    Ok(run)
+}
+
+/// The smallest unread record of each run, by key then by run order.
+struct Head {
+    key: Vec<KeyPart>,
+    run: usize,
+    record: StringRecord,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.run.cmp(&other.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// A k-way merge of sorted runs.
+struct Merge {
+    keys: Vec<SortKey>,
+    readers: Vec<csv::Reader<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<Head>>,
+}
+
+impl Merge {
+    fn new(runs: Vec<File>, keys: &[SortKey]) -> Result<Merge> {
+        let mut merge = Merge {
+            keys: keys.to_vec(),
+            readers: runs
+                .into_iter()
+                .map(|run| {
+                    csv::ReaderBuilder::new()
+                        .has_headers(false)
+                        .flexible(true)
+                        .from_reader(BufReader::new(run))
+                })
+                .collect(),
+            heap: BinaryHeap::new(),
+        };
+        for run in 0..merge.readers.len() {
+            merge.advance(run)?;
+        }
+        Ok(merge)
+    }
+
+    /// Push the next record of `run`, if any, onto the heap.
+    fn advance(&mut self, run: usize) -> Result<()> {
+        let mut record = StringRecord::new();
+        if self.readers[run].read_record(&mut record)? {
+            self.heap.push(Reverse(Head {
+                key: keys_of(&self.keys, &record),
+                run,
+                record,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(head) = self.heap.pop()?;
+        if let Err(e) = self.advance(head.run) {
+            return Some(Err(e));
+        }
+        Some(Ok(head.record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CsvOptions;
+    use std::io;
+
+    fn sorted(csv: &str, spec: &str, memory: usize) -> (Vec<String>, usize) {
+        let opts = CsvOptions::default();
+        let input =
+            CsvInput::with_delimiter(Box::new(io::Cursor::new(csv.to_string())), ',', &opts)
+                .unwrap();
+        let keys = SortKey::parse_list(spec, &input.headers).unwrap();
+        let sorted = sort(input, &keys, memory, None).unwrap();
+        let runs = sorted.runs;
+        let rows = sorted
+            .map(|r| r.unwrap().iter().collect::<Vec<_>>().join(","))
+            .collect();
+        (rows, runs)
+    }
+
+    #[test]
+    fn test_kinds_and_direction() {
+        let csv = "v\n10\n9\n\"\"\nx\n100\n";
+        assert_eq!(sorted(csv, "v", usize::MAX).0, ["10", "100", "9", "x", ""]);
+        // Empty and non-numeric values tie last, in input order
+        assert_eq!(
+            sorted(csv, "v:numeric", usize::MAX).0,
+            ["9", "10", "100", "", "x"]
+        );
+        assert_eq!(
+            sorted(csv, "v:numeric:desc", usize::MAX).0,
+            ["100", "10", "9", "", "x"]
+        );
+
+        let files = "f\nfile10\nfile2\nfile02b\nFile1\n";
+        assert_eq!(
+            sorted(files, "f:natural", usize::MAX).0,
+            ["File1", "file2", "file02b", "file10"]
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        let csv = "d\n2024-03-01\n2024-02-29T23:59:59\n2024/01/15\n2024-03-01T00:30:00+01:00\n";
+        assert_eq!(
+            sorted(csv, "d:date", usize::MAX).0,
+            [
+                "2024/01/15",
+                "2024-03-01T00:30:00+01:00",
+                "2024-02-29T23:59:59",
+                "2024-03-01"
+            ]
+        );
+        assert_eq!(parse_date("1970-01-02"), Some((86_400, 0)));
+        assert_eq!(parse_date("1970-01-01T00:00:01.5Z"), Some((1, 500_000_000)));
+        assert_eq!(parse_date("not a date"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+
+    #[test]
+    fn test_multiple_keys_stable() {
+        let csv = "k,n,i\nb,2,1\na,1,2\nb,1,3\na,1,4\nb,2,5\n";
+        assert_eq!(
+            sorted(csv, "k:desc,n:numeric", usize::MAX).0,
+            ["b,1,3", "b,2,1", "b,2,5", "a,1,2", "a,1,4"]
+        );
+    }
+
+    #[test]
+    fn test_spilled_runs_merge_like_in_memory() {
+        let mut csv = String::from("k,i\n");
+        for i in 0..500 {
+            csv.push_str(&format!("{},{}\n", (i * 7919) % 13, i));
+        }
+        let (in_memory, runs) = sorted(&csv, "k:numeric", usize::MAX);
+        assert_eq!(runs, 0);
+        // A tiny budget spills more runs than one merge pass takes
+        let (external, runs) = sorted(&csv, "k:numeric", 1_000);
+        assert!(runs > MERGE_FAN_IN, "{} runs", runs);
+        assert_eq!(external, in_memory);
+    }
+
+    #[test]
+    fn test_infer_kinds() {
+        let headers = StringRecord::from(vec!["n", "s", "m"]);
+        let mut keys = SortKey::parse_list("n:desc,s,m:natural", &headers).unwrap();
+        let records = vec![
+            Ok(StringRecord::from(vec![" 1.5", "x", "a"])),
+            Ok(StringRecord::from(vec!["", "2", "b"])),
+            Ok(StringRecord::from(vec!["-3e2", "3", "c"])),
+        ];
+        SortKey::infer_kinds(&mut keys, records.into_iter()).unwrap();
+        let kinds: Vec<SortKind> = keys.iter().map(|key| key.kind).collect();
+        assert_eq!(
+            kinds,
+            [SortKind::Numeric, SortKind::Lexical, SortKind::Natural]
+        );
+        assert!(keys[0].descending);
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        let headers = StringRecord::from(vec!["a", "b"]);
+        assert!(SortKey::parse_list("a:sideways", &headers).is_err());
+        assert!(SortKey::parse_list("c", &headers).is_err());
+        assert_eq!(
+            SortKey::parse_list("b:desc:numeric", &headers).unwrap(),
+            [SortKey {
+                column: 1,
+                kind: SortKind::Numeric,
+                descending: true
+            }]
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Splitting one input into many files: chunks of a number of rows or about
+//! a number of bytes, or one file per distinct value of a column.
+//!
+//! Every file repeats the header row. Splitting by value keeps at most
+//! `max_open` files open: the least recently written one is closed when
+//! another is needed, and reopened for appending if its value comes back.
+
+use crate::error::{CliError, Result};
+use crate::input::CsvInput;
+use crate::selector::Selector;
+use crate::utils::{CsvOptions, UTF_8_BOM};
+use csv::StringRecord;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+type ChunkWriter = csv::Writer<BufWriter<File>>;
+
+/// Hive's directory name for partitions whose value is empty.
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+#[derive(Debug, Clone)]
+pub enum SplitMode {
+    /// At most this many data rows per file
+    Rows(usize),
+    /// About this many bytes per file, never splitting a row
+    Bytes(usize),
+    /// One file per distinct value of a column
+    By { column: String, hive: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitSpec {
+    pub mode: SplitMode,
+    /// Directory receiving the files
+    pub dir: PathBuf,
+    /// Start of each file name
+    pub prefix: String,
+    /// Files kept open at once when splitting by value
+    pub max_open: usize,
+}
+
+/// Split `input` into files as described by `spec`, returning the number
+/// of files written.
+pub fn split(input: CsvInput, spec: &SplitSpec, opts: &CsvOptions) -> Result<usize> {
+    fs::create_dir_all(&spec.dir).map_err(|source| open_error(&spec.dir, source))?;
+    let mut files = Files {
+        delimiter: input.delimiter,
+        headers: opts.emit_header().then(|| input.headers.clone()),
+        opts,
+        open: HashMap::new(),
+        created: HashSet::new(),
+        max_open: spec.max_open.max(1),
+        tick: 0,
+    };
+
+    match &spec.mode {
+        SplitMode::Rows(max_rows) => split_chunks(input, *max_rows, usize::MAX, spec, &mut files)?,
+        SplitMode::Bytes(max_bytes) => {
+            split_chunks(input, usize::MAX, *max_bytes, spec, &mut files)?
+        }
+        SplitMode::By { column, hive } => {
+            let col_idx = Selector::parse(column)?.select_one(&input.headers)?;
+            let column_name = input.headers[col_idx].to_string();
+            let mut paths: HashMap<String, PathBuf> = HashMap::new();
+            let mut used = HashSet::new();
+            for record in input.into_records() {
+                let record = record?;
+                let value = record.get(col_idx).unwrap_or("");
+                if !paths.contains_key(value) {
+                    let path = if *hive {
+                        let value = match value {
+                            "" => HIVE_DEFAULT_PARTITION.to_string(),
+                            value => escape_name(value),
+                        };
+                        let partition = format!("{}={}", escape_name(&column_name), value);
+                        spec.dir
+                            .join(unique_name(partition, &mut used))
+                            .join(format!("{}0.csv", spec.prefix))
+                    } else {
+                        let name = unique_name(escape_name(value), &mut used);
+                        spec.dir.join(format!("{}{}.csv", spec.prefix, name))
+                    };
+                    paths.insert(value.to_string(), path);
+                }
+                files.write(&paths[value], &record)?;
+            }
+        }
+    }
+    files.close_all()
+}
+
+/// Write `input` in numbered chunks of at most `max_rows` rows and about
+/// `max_bytes` bytes; a chunk always gets at least one row.
+fn split_chunks(
+    input: CsvInput,
+    max_rows: usize,
+    max_bytes: usize,
+    spec: &SplitSpec,
+    files: &mut Files,
+) -> Result<()> {
+    let path = |chunk: usize| spec.dir.join(format!("{}{:04}.csv", spec.prefix, chunk));
+    let header_bytes = files.headers.as_ref().map_or(0, record_size);
+    let mut chunk = 0;
+    let mut rows = 0;
+    let mut bytes = header_bytes;
+    for record in input.into_records() {
+        let record = record?;
+        let size = record_size(&record);
+        if rows > 0 && (rows == max_rows || bytes + size > max_bytes) {
+            files.close(&path(chunk))?;
+            chunk += 1;
+            rows = 0;
+            bytes = header_bytes;
+        }
+        files.write(&path(chunk), &record)?;
+        rows += 1;
+        bytes += size;
+    }
+    Ok(())
+}
+
+/// Size of `record` as written, leaving out any quoting.
+fn record_size(record: &StringRecord) -> usize {
+    record.as_slice().len() + record.len().max(1)
+}
+
+/// `name`, or `name~2`, `name~3`... if it matches one in `used` but for
+/// case, as distinct values such as "US" and "us" would name the same file
+/// on a case-insensitive file system.
+fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut n = 1;
+    while !used.insert(unique.to_lowercase()) {
+        n += 1;
+        unique = format!("{}~{}", name, n);
+    }
+    unique
+}
+
+/// Make `value` safe as a file name, percent-encoding path separators,
+/// characters Windows rejects, '%' itself and a leading '.'.
+fn escape_name(value: &str) -> String {
+    let mut name = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        let special = matches!(
+            c,
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '%' | '='
+        ) || c.is_control()
+            || (i == 0 && c == '.');
+        if special {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                name.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+fn open_error(path: &Path, source: std::io::Error) -> CliError {
+    CliError::Open {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+/// The output files, a bounded number of them open at once.
+struct Files<'a> {
+    delimiter: char,
+    headers: Option<StringRecord>,
+    opts: &'a CsvOptions,
+    /// Open writers with the tick of their last write
+    open: HashMap<PathBuf, (ChunkWriter, u64)>,
+    /// Every file created so far, open or not
+    created: HashSet<PathBuf>,
+    max_open: usize,
+    tick: u64,
+}
+
+impl Files<'_> {
+    fn write(&mut self, path: &Path, record: &StringRecord) -> Result<()> {
+        self.tick += 1;
+        if !self.open.contains_key(path) {
+            if self.open.len() >= self.max_open {
+                let oldest = self
+                    .open
+                    .iter()
+                    .min_by_key(|(_, (_, tick))| *tick)
+                    .map(|(path, _)| path.clone());
+                if let Some(oldest) = oldest {
+                    self.close(&oldest)?;
+                }
+            }
+            let writer = self.open_file(path)?;
+            self.open.insert(path.to_path_buf(), (writer, 0));
+        }
+        let (writer, tick) = self.open.get_mut(path).expect("the file was just opened");
+        *tick = self.tick;
+        writer.write_record(record)?;
+        Ok(())
+    }
+
+    /// Create `path` with the header row, or reopen it for appending. A file
+    /// that already exists before the split is an error, not overwritten.
+    fn open_file(&mut self, path: &Path) -> Result<ChunkWriter> {
+        if self.created.contains(path) {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(|source| open_error(path, source))?;
+            return Ok(self.opts.csv_writer(BufWriter::new(file), self.delimiter));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|source| open_error(parent, source))?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|source| open_error(path, source))?;
+        let mut file = BufWriter::new(file);
+        if self.opts.bom {
+            file.write_all(UTF_8_BOM)?;
+        }
+        let mut writer = self.opts.csv_writer(file, self.delimiter);
+        if let Some(headers) = &self.headers {
+            writer.write_record(headers)?;
+        }
+        self.created.insert(path.to_path_buf());
+        Ok(writer)
+    }
+
+    fn close(&mut self, path: &Path) -> Result<()> {
+        if let Some((writer, _)) = self.open.remove(path) {
+            writer.into_inner().map_err(|e| e.into_error())?.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close every file, returning how many were written.
+    fn close_all(mut self) -> Result<usize> {
+        let paths: Vec<PathBuf> = self.open.keys().cloned().collect();
+        for path in paths {
+            self.close(&path)?;
+        }
+        Ok(self.created.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_name() {
+        assert_eq!(escape_name("US"), "US");
+        assert_eq!(escape_name("a/b c"), "a%2Fb c");
+        assert_eq!(escape_name("..x"), "%2E.x");
+        assert_eq!(escape_name("50%=half"), "50%25%3Dhalf");
+    }
+
+    #[test]
+    fn test_unique_name_ignores_case() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_name("US".to_string(), &mut used), "US");
+        assert_eq!(unique_name("us".to_string(), &mut used), "us~2");
+        assert_eq!(unique_name("Us".to_string(), &mut used), "Us~3");
+        assert_eq!(unique_name("us~2".to_string(), &mut used), "us~2~2");
+        assert_eq!(unique_name("FR".to_string(), &mut used), "FR");
+    }
+}
@@ -1,100 +1,102 @@
-use crate::utils::{csv_writer, detect_delimiter, input_reader};
-use csv::ReaderBuilder;
-use std::error::Error;
-use std::io::{self, BufRead, Cursor, Read};
+use crate::error::{CliError, Result};
+use crate::input::{CsvInput, IntoRecords};
+use crate::utils::{input_reader, CsvOptions};
+use csv::StringRecord;
+use std::io::{BufRead, Cursor, Read};
+
+/// The data records of several inputs with matching headers, one after the other.
+pub struct Stack {
+    pub delimiter: char,
+    pub headers: StringRecord,
+    sources: Sources,
+    current: Option<IntoRecords>,
+    next: usize,
+    /// Repeated header rows are dropped from a single concatenated stream.
+    skip_headers: bool,
+}
+
+/// The inputs of a stack, reopened for reading after validation.
+struct Sources {
+    files: Vec<String>,
+    stdin_content: Option<Vec<u8>>,
+    opts: CsvOptions,
+}
 
-#[allow(unused_mut)]
-pub fn stack(files: &[String]) -> Result<(), Box<dyn Error>> {
+impl Sources {
+    /// A fresh reader for the i-th input.
+    fn reader(&self, i: usize) -> Result<Box<dyn BufRead>> {
+        if self.files[i] == "-" {
+            // Each call gets its own cursor over the shared buffer
+            Ok(Box::new(Cursor::new(
+                self.stdin_content.as_ref().unwrap().clone(),
+            )))
+        } else {
+            input_reader(Some(&self.files[i]), self.opts.encoding)
+        }
+    }
+}
+
+/// Stack `files` vertically, '-' standing for stdin; no files means stdin alone.
+///
+/// All headers are checked to match before any record is read, so a
+/// mismatch is reported before anything is written.
+pub fn stack(files: &[String], opts: &CsvOptions) -> Result<Stack> {
     let stdin_count = files.iter().filter(|s| *s == "-").count();
     if stdin_count > 1 {
-        return Err(
-            "Cannot use '-' for more than one input; at most one piped stream is supported".into(),
-        );
+        return Err(CliError::Usage(
+            "Cannot use '-' for more than one input; at most one piped stream is supported"
+                .to_string(),
+        ));
     }
 
     // Pure stdin mode — single stream, nothing to cross-validate
     if files.is_empty() {
-        let mut reader = input_reader(None);
-        let delimiter = detect_delimiter(&mut *reader)?;
-        let mut csv = ReaderBuilder::new()
-            .has_headers(true)
-            .delimiter(delimiter as u8)
-            .from_reader(reader);
-        let headers = csv.headers()?.clone();
-
-        let stdout = io::stdout();
-        let mut writer = csv_writer(stdout.lock(), delimiter);
-        writer.write_record(headers.iter().collect::<Vec<_>>())?;
-        for result in csv.records() {
-            let record = result?;
-            if record == headers {
-                continue;
-            }
-            writer.write_record(record.iter().collect::<Vec<_>>())?;
-        }
-        writer.flush()?;
-        return Ok(());
+        let input = CsvInput::open(None, opts)?;
+        return Ok(Stack {
+            delimiter: input.delimiter,
+            headers: input.headers.clone(),
+            sources: Sources {
+                files: Vec::new(),
+                stdin_content: None,
+                opts: opts.clone(),
+            },
+            current: Some(input.into_records()),
+            next: 0,
+            skip_headers: !opts.no_header,
+        });
     }
 
     // Buffer stdin content upfront if '-' is used, so we can replay it multiple
-    // times during validation and output.
-    let stdin_content: Option<Vec<u8>> = if stdin_count == 1 {
+    // times during validation and output. The buffer holds decompressed data.
+    let stdin_content = if stdin_count == 1 {
         let mut buf = Vec::new();
-        io::stdin().read_to_end(&mut buf)?;
+        input_reader(None, opts.encoding)?.read_to_end(&mut buf)?;
         Some(buf)
     } else {
         None
     };
-
-    // Helper: get a fresh reader for the i-th input
-    let reader_at = |i: usize| -> Box<dyn BufRead> {
-        if files[i] == "-" {
-            // Each call gets its own cursor over the shared buffer
-            Box::new(Cursor::new(stdin_content.as_ref().unwrap().clone()))
-        } else {
-            input_reader(Some(&files[i]))
-        }
-    };
-
-    // ── Pass 1: validate all headers match before writing anything ──
-
-    let delimiter = {
-        let mut r = reader_at(0);
-        detect_delimiter(&mut *r)?
+    let sources = Sources {
+        files: files.to_vec(),
+        stdin_content,
+        opts: opts.clone(),
     };
 
-    let headers = {
-        let mut r = reader_at(0);
-        let mut csv = ReaderBuilder::new()
-            .has_headers(true)
-            .delimiter(delimiter as u8)
-            .from_reader(r);
-        csv.headers()?.clone()
-    };
+    // Validate all headers match before reading any record
+    let first = CsvInput::from_reader(sources.reader(0)?, opts)?;
+    let (delimiter, headers) = (first.delimiter, first.headers);
 
     for i in 1..files.len() {
-        let file_delimiter = {
-            let mut r = reader_at(i);
-            detect_delimiter(&mut *r)?
-        };
+        let input = CsvInput::from_reader(sources.reader(i)?, opts)?;
 
-        if file_delimiter != delimiter {
+        if input.delimiter != delimiter {
             return Err(format!(
                 "Files have different delimiters: '{}' vs '{}'",
-                delimiter, file_delimiter
+                delimiter, input.delimiter
             )
             .into());
         }
 
-        let file_headers = {
-            let mut r = reader_at(i);
-            let mut csv = ReaderBuilder::new()
-                .has_headers(true)
-                .delimiter(delimiter as u8)
-                .from_reader(r);
-            csv.headers()?.clone()
-        };
-
+        let file_headers = input.headers;
         if file_headers.len() != headers.len() {
             return Err(format!(
                 "Files have different number of columns: {} vs {}",
@@ -115,26 +117,46 @@ pub fn stack(files: &[String]) -> Result<(), Box<dyn Error>> {
         }
     }
 
-    // ── Pass 2: all valid, write output ──
+    Ok(Stack {
+        delimiter,
+        headers,
+        sources,
+        current: None,
+        next: 0,
+        skip_headers: false,
+    })
+}
 
-    let stdout = io::stdout();
-    let mut writer = csv_writer(stdout.lock(), delimiter);
-    writer.write_record(headers.iter().collect::<Vec<_>>())?;
+impl Iterator for Stack {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(records) = &mut self.current {
+                match records.next() {
+                    // Drop repeated header rows from concatenated streams
+                    Some(Ok(record)) if self.skip_headers && record == self.headers => continue,
+                    Some(result) => return Some(result),
+                    None => self.current = None,
+                }
+            }
 
-    for i in 0..files.len() {
-        let mut r = reader_at(i);
-        let mut csv = ReaderBuilder::new()
-            .has_headers(true)
-            .delimiter(delimiter as u8)
-            .from_reader(r);
-        // Consume header (already validated above)
-        csv.headers()?;
-        for result in csv.records() {
-            let record = result?;
-            writer.write_record(record.iter().collect::<Vec<_>>())?;
+            if self.next >= self.sources.files.len() {
+                return None;
+            }
+            // Open the next input; its header was already validated
+            let file = Some(self.sources.files[self.next].as_str()).filter(|f| *f != "-");
+            let input = self.sources.reader(self.next).and_then(|reader| {
+                let mut input =
+                    CsvInput::with_delimiter(reader, self.delimiter, &self.sources.opts)?;
+                input.report_as(file);
+                Ok(input)
+            });
+            self.next += 1;
+            match input {
+                Ok(input) => self.current = Some(input.into_records()),
+                Err(e) => return Some(Err(e)),
+            }
         }
     }
-
-    writer.flush()?;
-    Ok(())
 }
@@ -1,36 +1,139 @@
-use crate::utils::{detect_delimiter, input_reader};
-use colored::*;
-use csv::ReaderBuilder;
+use crate::error::Result;
+use crate::input::CsvInput;
+use crate::parallel::fold_chunks;
+use crate::selector::Selector;
+use crate::utils::CsvOptions;
+use csv::StringRecord;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::error::Error;
-
-pub fn column_stats(path: Option<&str>, column: &str) -> Result<(), Box<dyn Error>> {
-    let mut reader = input_reader(path);
-    let delimiter = detect_delimiter(&mut *reader)?;
-
-    let mut csv = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter as u8)
-        .from_reader(reader);
-
-    let headers = csv.headers()?.clone();
-
-    // Find column index
-    let col_idx = headers.iter().position(|h| h == column).ok_or_else(|| {
-        let available: Vec<&str> = headers.iter().collect();
-        format!(
-            "Column '{}' not found in CSV.\nAvailable columns: {}",
-            column,
-            available.join(", ")
-        )
-    })?;
-
-    // Collect all values with progress indicator
-    let mut values: Vec<String> = Vec::new();
-
-    // Create progress spinner (only shows if stderr is a TTY)
-    let spinner = if atty::is(atty::Stream::Stderr) {
+
+/// Percentiles reported for numeric columns.
+pub const PERCENTILES: [f64; 5] = [1.0, 25.0, 50.0, 75.0, 99.0];
+
+/// Number of most frequent values kept for categorical columns.
+const TOP_VALUES: usize = 3;
+
+/// Summary statistics of one column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    pub column: String,
+    /// Number of values, empty ones included.
+    pub count: usize,
+    /// Number of empty or whitespace-only values.
+    pub null: usize,
+    pub kind: StatsKind,
+}
+
+/// A column is numeric when more than half of its non-empty values parse as numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsKind {
+    Numeric {
+        /// Non-empty values that could not be converted to a number.
+        invalid: usize,
+        /// `None` when the column has no numeric value at all.
+        summary: Option<NumericSummary>,
+    },
+    Categorical {
+        /// Number of distinct non-empty values.
+        unique: usize,
+        /// The most frequent non-empty values with their counts, ties broken by value.
+        top: Vec<(String, usize)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericSummary {
+    pub mean: f64,
+    /// Population standard deviation.
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// `(p, value)` for each of `PERCENTILES`.
+    pub percentiles: Vec<(f64, f64)>,
+}
+
+impl NumericSummary {
+    /// Summarize `values`, sorting them in place. `None` if there are none.
+    pub fn from_values(values: &mut [f64]) -> Option<NumericSummary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+
+        // Standard deviation
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+
+        // Sort for min, max, and percentiles
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        Some(NumericSummary {
+            mean,
+            std_dev: variance.sqrt(),
+            min: values[0],
+            max: values[values.len() - 1],
+            percentiles: PERCENTILES
+                .iter()
+                .map(|&p| (p, percentile(values, p)))
+                .collect(),
+        })
+    }
+}
+
+/// Statistics for each column of `input` selected by `column`.
+pub fn column_stats(
+    mut input: CsvInput,
+    column: &str,
+    opts: &CsvOptions,
+) -> Result<Vec<ColumnStats>> {
+    let headers = input.headers.clone();
+
+    // Find the column indices; each selected column gets its own report
+    let col_indices = Selector::parse(column)?.select(&headers)?;
+
+    // Collect all values, in parallel chunks when the input allows it
+    let collect = |values: &mut Vec<Vec<String>>, record: &StringRecord| {
+        for (column_values, &col_idx) in values.iter_mut().zip(&col_indices) {
+            if let Some(val) = record.get(col_idx) {
+                column_values.push(val.to_string());
+            }
+        }
+    };
+    let init = || vec![Vec::new(); col_indices.len()];
+    let path = input.path.clone();
+    let values = match fold_chunks(
+        path.as_deref(),
+        input.delimiter,
+        Some(headers.len()),
+        opts,
+        init,
+        collect,
+    )? {
+        Some(chunks) => merge_values(chunks),
+        None => collect_rows(&mut input, init(), collect, opts)?,
+    };
+
+    Ok(col_indices
+        .iter()
+        .zip(&values)
+        .map(|(&col_idx, column_values)| {
+            summarize(headers.get(col_idx).unwrap_or(""), column_values)
+        })
+        .collect())
+}
+
+/// Collect the selected columns' values by reading every record of `csv` in turn.
+fn collect_rows(
+    input: &mut CsvInput,
+    mut values: Vec<Vec<String>>,
+    collect: impl Fn(&mut Vec<Vec<String>>, &StringRecord),
+    opts: &CsvOptions,
+) -> Result<Vec<Vec<String>>> {
+    let mut row_count = 0;
+
+    // Create progress spinner (only shows if requested, i.e. stderr is a TTY)
+    let spinner = if opts.progress {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -44,16 +147,15 @@ pub fn column_stats(path: Option<&str>, column: &str) -> Result<(), Box<dyn Erro
         None
     };
 
-    for result in csv.records() {
+    for result in input.records() {
         let record = result?;
-        if let Some(val) = record.get(col_idx) {
-            values.push(val.to_string());
-        }
+        collect(&mut values, &record);
+        row_count += 1;
 
         // Update spinner every 1000 rows
         if let Some(ref pb) = spinner {
-            if values.len().is_multiple_of(1000) {
-                pb.set_message(format!("Reading data... {} rows", values.len()));
+            if row_count % 1000 == 0 {
+                pb.set_message(format!("Reading data... {} rows", row_count));
                 pb.tick();
             }
         }
@@ -64,15 +166,32 @@ pub fn column_stats(path: Option<&str>, column: &str) -> Result<(), Box<dyn Erro
         pb.finish_and_clear();
     }
 
+    Ok(values)
+}
+
+/// Concatenate the values of each chunk, in file order.
+fn merge_values(chunks: Vec<Vec<Vec<String>>>) -> Vec<Vec<String>> {
+    let mut chunks = chunks.into_iter();
+    let mut values = chunks.next().unwrap_or_default();
+    for chunk in chunks {
+        for (column_values, chunk_values) in values.iter_mut().zip(chunk) {
+            column_values.extend(chunk_values);
+        }
+    }
+    values
+}
+
+/// Numeric or categorical statistics for one column's values.
+fn summarize(column: &str, values: &[String]) -> ColumnStats {
     // Try to parse as numeric
     let mut numeric_values: Vec<f64> = Vec::new();
     let mut null_count = 0;
     let mut invalid_count = 0;
 
-    for val in &values {
+    for val in values {
         if val.trim().is_empty() {
             null_count += 1;
-        } else if let Ok(num) = val.trim().parse::<f64>() {
+        } else if let Some(num) = parse_number(val) {
             numeric_values.push(num);
         } else {
             invalid_count += 1;
@@ -86,149 +205,48 @@ pub fn column_stats(path: Option<&str>, column: &str) -> Result<(), Box<dyn Erro
     let non_null_count = total_count - null_count;
     let is_numeric = non_null_count > 0 && (valid_numeric as f64 / non_null_count as f64) > 0.5;
 
-    if is_numeric {
-        print_numeric_stats(
-            column,
-            total_count,
-            null_count,
-            invalid_count,
-            &mut numeric_values,
-        );
+    let kind = if is_numeric {
+        StatsKind::Numeric {
+            invalid: invalid_count,
+            summary: NumericSummary::from_values(&mut numeric_values),
+        }
     } else {
-        print_categorical_stats(column, total_count, null_count, &values);
-    }
-
-    Ok(())
-}
-
-fn print_numeric_stats(
-    column: &str,
-    total: usize,
-    null_count: usize,
-    invalid_count: usize,
-    values: &mut [f64],
-) {
-    println!(
-        "\n{}",
-        format!("Column '{}' Statistics (Numeric)", column)
-            .green()
-            .bold()
-    );
-    println!();
-    println!("{:<13} {}", "Count:".green(), total);
-    println!("{:<13} {}", "Null/Empty:".green(), null_count);
-    if invalid_count > 0 {
-        println!(
-            "{:<13} {} (could not convert to number)",
-            "Invalid:".green(),
-            invalid_count
-        );
-    }
-
-    if values.is_empty() {
-        println!("\nNo numeric values to analyze.");
-        return;
-    }
-
-    // Calculate statistics
-    let n = values.len() as f64;
-    let mean = values.iter().sum::<f64>() / n;
-
-    // Standard deviation
-    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
-    let std_dev = variance.sqrt();
-
-    // Sort for min, max, and percentiles
-    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-    let min = values[0];
-    let max = values[values.len() - 1];
-
-    println!();
-    println!("{:<13} {:.2}", "Mean:".green(), mean);
-    println!("{:<13} {:.2}", "Std Dev:".green(), std_dev);
-    println!("{:<13} {:.2}", "Min:".green(), min);
-    println!("{:<13} {:.2}", "Max:".green(), max);
-
-    // Percentiles
-    println!();
-    println!("{}:", "Percentiles".green());
-    println!(
-        "  {}{:.2}",
-        format!("{:<12}", "1%:").green(),
-        percentile(values, 1.0)
-    );
-    println!(
-        "  {}{:.2}",
-        format!("{:<12}", "25%:").green(),
-        percentile(values, 25.0)
-    );
-    println!(
-        "  {}{:.2}",
-        format!("{:<12}", "50%:").green(),
-        percentile(values, 50.0)
-    );
-    println!(
-        "  {}{:.2}",
-        format!("{:<12}", "75%:").green(),
-        percentile(values, 75.0)
-    );
-    println!(
-        "  {}{:.2}",
-        format!("{:<12}", "99%:").green(),
-        percentile(values, 99.0)
-    );
-    println!();
-}
-
-fn print_categorical_stats(column: &str, total: usize, null_count: usize, values: &[String]) {
-    println!(
-        "\n{}",
-        format!("Column '{}' Statistics (Categorical)", column)
-            .green()
-            .bold()
-    );
-    println!();
-    println!("{:<13} {}", "Total Count:".green(), total);
-    println!("{:<13} {}", "Null/Empty:".green(), null_count);
-
-    // Count frequencies (excluding empty values)
-    let mut freq_map: HashMap<String, usize> = HashMap::new();
-    for val in values {
-        if !val.trim().is_empty() {
-            *freq_map.entry(val.clone()).or_insert(0) += 1;
+        // Count frequencies (excluding empty values)
+        let mut freq_map: HashMap<&str, usize> = HashMap::new();
+        for val in values {
+            if !val.trim().is_empty() {
+                *freq_map.entry(val).or_insert(0) += 1;
+            }
         }
-    }
 
-    let unique_count = freq_map.len();
-    println!("{:<13} {}", "Unique:".green(), unique_count);
+        let mut freq_vec: Vec<(&str, usize)> = freq_map.into_iter().collect();
+        freq_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        StatsKind::Categorical {
+            unique: freq_vec.len(),
+            top: freq_vec
+                .into_iter()
+                .take(TOP_VALUES)
+                .map(|(val, count)| (val.to_string(), count))
+                .collect(),
+        }
+    };
 
-    if unique_count == 0 {
-        println!("\nNo non-empty values to analyze.");
-        return;
+    ColumnStats {
+        column: column.to_string(),
+        count: total_count,
+        null: null_count,
+        kind,
     }
+}
 
-    // Get top 3
-    let mut freq_vec: Vec<(&String, &usize)> = freq_map.iter().collect();
-    freq_vec.sort_by(|a, b| b.1.cmp(a.1));
-
-    println!();
-    println!("{}:", "Top 3 Most Frequent".green());
-    let non_null_total = (total - null_count) as f64;
-    for (i, (val, count)) in freq_vec.iter().take(3).enumerate() {
-        let percentage = (**count as f64 / non_null_total) * 100.0;
-        println!(
-            "  {}. {:<20} {:>6}  ({:.1}%)",
-            i + 1,
-            val,
-            count,
-            percentage
-        );
-    }
-    println!();
+/// The number in `value`, ignoring surrounding whitespace.
+pub fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse().ok()
 }
 
-fn percentile(sorted_values: &[f64], p: f64) -> f64 {
+/// Value at percentile `p` (0-100) of `sorted_values`, interpolating
+/// linearly between the closest ranks.
+pub fn percentile(sorted_values: &[f64], p: f64) -> f64 {
     let n = sorted_values.len();
     if n == 0 {
         return 0.0;
@@ -245,3 +263,51 @@ fn percentile(sorted_values: &[f64], p: f64) -> f64 {
 
     sorted_values[lower] + fraction * (sorted_values[upper] - sorted_values[lower])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_summarize_numeric() {
+        let stats = summarize("price", &strings(&["1", "2", "", "3", "4", "n/a"]));
+        assert_eq!(stats.count, 6);
+        assert_eq!(stats.null, 1);
+        let StatsKind::Numeric { invalid, summary } = stats.kind else {
+            panic!("expected a numeric column");
+        };
+        assert_eq!(invalid, 1);
+        let summary = summary.unwrap();
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!((summary.min, summary.max), (1.0, 4.0));
+        assert_eq!(summary.percentiles[2], (50.0, 2.5));
+    }
+
+    #[test]
+    fn test_summarize_categorical_top_values() {
+        let stats = summarize("city", &strings(&["b", "a", "c", "a", "b", "d", " "]));
+        assert_eq!(stats.null, 1);
+        assert_eq!(
+            stats.kind,
+            StatsKind::Categorical {
+                unique: 4,
+                top: vec![
+                    ("a".to_string(), 2),
+                    ("b".to_string(), 2),
+                    ("c".to_string(), 1)
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_percentile_interpolates() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(percentile(&[7.0], 99.0), 7.0);
+        assert_eq!(percentile(&[0.0, 10.0], 25.0), 2.5);
+    }
+}
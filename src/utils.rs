@@ -1,27 +1,436 @@
+use crate::bad_rows::{BadRows, OnError, Records};
+use crate::error::{CliError, Result};
+use crate::index::StaleIndexes;
+use crate::output::Output;
 use atty::{is, Stream};
-use csv::WriterBuilder;
-use std::error::Error;
+use bzip2::bufread::MultiBzDecoder;
+use clap::ValueEnum;
+use csv::{ReaderBuilder, StringRecord, Terminator, WriterBuilder};
+use encoding_rs::{Encoding, UTF_8};
+use flate2::bufread::MultiGzDecoder;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use xz2::bufread::XzDecoder;
 
-pub fn input_reader(path: Option<&str>) -> Box<dyn BufRead> {
-    match path {
-        Some(p) => Box::new(BufReader::new(File::open(p).expect("Cannot open file"))),
+/// Compression formats that `input_reader` decodes transparently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// Guess the compression format from a file extension (`.gz`, `.zst`, `.bz2`, `.xz`).
+    pub fn from_path(path: &str) -> Option<Compression> {
+        let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Recognise the compression format from the leading magic bytes of a stream.
+    pub fn from_magic(buf: &[u8]) -> Option<Compression> {
+        if buf.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if buf.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if buf.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if is_bzip2_header(buf) {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+}
+
+// "BZh" + block size digit, followed by either a block magic (pi) or the
+// end-of-stream magic (sqrt(pi)). Checking all 10 bytes keeps a plain text
+// file that happens to start with "BZh1" from being treated as bzip2.
+fn is_bzip2_header(buf: &[u8]) -> bool {
+    const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+    const EOS_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+    buf.len() >= 10
+        && buf.starts_with(b"BZh")
+        && (b'1'..=b'9').contains(&buf[3])
+        && (buf[4..10] == BLOCK_MAGIC || buf[4..10] == EOS_MAGIC)
+}
+
+/// CSV dialect settings given on the command line. Anything left as `None`
+/// falls back to auto-detection (delimiter) or the csv crate defaults.
+#[derive(Debug, Clone, Default)]
+pub struct CsvOptions {
+    pub delimiter: Option<char>,
+    pub quote: Option<char>,
+    pub escape: Option<char>,
+    pub no_double_quote: bool,
+    /// Delimiters to choose from during auto-detection (default: `DEFAULT_DELIMITERS`).
+    pub delimiter_candidates: Option<Vec<char>>,
+    /// The input has no header row; columns are named "1".."N".
+    pub no_header: bool,
+    /// In `no_header` mode, still write a "1".."N" header row on output.
+    pub synthetic_header: bool,
+    /// Input encoding when there is no byte order mark (default: UTF-8).
+    pub encoding: Option<&'static Encoding>,
+    /// What to do with ragged or non-UTF-8 rows (`--on-error`).
+    pub bad_rows: BadRows,
+    /// Indexes found stale, for the caller to warn about.
+    pub stale_indexes: StaleIndexes,
+    /// Threads for commands that parse whole files in chunks (`--jobs`).
+    pub jobs: usize,
+    /// Show a spinner on stderr while scanning whole inputs.
+    pub progress: bool,
+    /// File receiving the CSV written by writer commands, instead of stdout (`--output`).
+    pub output: Option<String>,
+    /// Delimiter of written CSV, instead of the input's (`--out-delimiter`).
+    pub out_delimiter: Option<char>,
+    /// Which fields of written CSV are quoted (`--quote-style`).
+    pub quote_style: QuoteStyle,
+    /// End written records with "\r\n" instead of "\n".
+    pub crlf: bool,
+    /// Start written CSV with a UTF-8 byte order mark, for Excel.
+    pub bom: bool,
+}
+
+/// When writer commands quote a field (`--quote-style`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    /// Quote every field
+    Always,
+    /// Quote only fields containing a delimiter, quote or line break
+    #[default]
+    Necessary,
+    /// Never quote fields, even if the output can no longer be parsed back
+    Never,
+    /// Quote every field that does not parse as a number
+    NonNumeric,
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(style: QuoteStyle) -> csv::QuoteStyle {
+        match style {
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Return the delimiter override, or detect it from the start of `reader`.
+    pub fn resolve_delimiter(&self, reader: &mut dyn BufRead) -> Result<char> {
+        match self.delimiter {
+            Some(d) => Ok(d),
+            None => detect_delimiter_with(
+                reader,
+                self.delimiter_candidates
+                    .as_deref()
+                    .unwrap_or(DEFAULT_DELIMITERS),
+                self.quote.unwrap_or('"'),
+            ),
+        }
+    }
+
+    /// A `ReaderBuilder` configured with `delimiter`, the quoting overrides
+    /// and header handling.
+    pub fn reader_builder(&self, delimiter: char) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
+            .has_headers(!self.no_header)
+            .delimiter(delimiter as u8)
+            .double_quote(!self.no_double_quote)
+            .escape(self.escape.map(|c| c as u8))
+            // Ragged rows are checked by `records` unless the policy is to fail
+            .flexible(self.bad_rows.policy != OnError::Fail);
+        if let Some(q) = self.quote {
+            builder.quote(q as u8);
+        }
+        builder
+    }
+
+    /// The header row of `csv`. In `no_header` mode this is a synthetic
+    /// "1".."N" record sized to the first row, which stays in the data.
+    pub fn headers<R: io::Read>(&self, csv: &mut csv::Reader<R>) -> csv::Result<StringRecord> {
+        let headers = csv.headers()?;
+        if self.no_header {
+            Ok(synthetic_headers(headers.len()))
+        } else {
+            Ok(headers.clone())
+        }
+    }
+
+    /// The data records of `csv`, with malformed rows handled per `--on-error`.
+    /// `expected` is the required number of fields per row, if any.
+    pub fn records<'r, R: io::Read>(
+        &'r self,
+        csv: &'r mut csv::Reader<R>,
+        expected: Option<usize>,
+    ) -> Records<'r, R> {
+        Records::new(csv, expected, &self.bad_rows)
+    }
+
+    /// A CSV writer onto `--output` or stdout, in the output dialect: the
+    /// input's `delimiter` unless `--out-delimiter` is given, `--quote-style`,
+    /// `--crlf` and `--bom`. Pass it to `finish_output` once everything is written.
+    pub fn output_writer(&self, delimiter: char) -> Result<csv::Writer<Output>> {
+        let mut output = Output::create(self.output.as_deref())?;
+        if self.bom {
+            output.write_all(UTF_8_BOM)?;
+        }
+        Ok(self.csv_writer(output, delimiter))
+    }
+
+    /// A CSV writer onto `writer` in the output dialect, for commands that
+    /// write files of their own. The byte order mark is left to the caller.
+    pub fn csv_writer<W: Write>(&self, writer: W, delimiter: char) -> csv::Writer<W> {
+        let terminator = if self.crlf {
+            Terminator::CRLF
+        } else {
+            Terminator::Any(b'\n')
+        };
+        WriterBuilder::new()
+            .delimiter(self.out_delimiter.unwrap_or(delimiter) as u8)
+            .quote_style(self.quote_style.into())
+            .terminator(terminator)
+            .from_writer(writer)
+    }
+
+    /// Whether writer commands should emit a header row.
+    pub fn emit_header(&self) -> bool {
+        !self.no_header || self.synthetic_header
+    }
+}
+
+pub(crate) const UTF_8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Column names "1".."N" used when the input has no header row.
+pub fn synthetic_headers(len: usize) -> StringRecord {
+    (1..=len).map(|i| i.to_string()).collect()
+}
+
+/// Parse a single-byte dialect character for the CLI. Accepts the character
+/// itself or one of the names `tab`, `comma`, `pipe`, `space`, `semicolon`
+/// and the escape `\t`.
+pub fn parse_dialect_char(s: &str) -> std::result::Result<char, String> {
+    let c = match s {
+        "tab" | "\\t" => '\t',
+        "comma" => ',',
+        "pipe" => '|',
+        "space" => ' ',
+        "semicolon" => ';',
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(format!("expected a single character, got '{}'", s)),
+            }
+        }
+    };
+    if !c.is_ascii() {
+        return Err(format!("'{}' is not an ASCII character", c));
+    }
+    Ok(c)
+}
+
+/// Parse the list of delimiter candidates for the CLI, e.g. `;,|`. The
+/// escape `\t` stands for a tab.
+pub fn parse_delimiter_candidates(s: &str) -> std::result::Result<Vec<char>, String> {
+    let chars: Vec<char> = s.replace("\\t", "\t").chars().collect();
+    if chars.is_empty() {
+        return Err("expected at least one delimiter candidate".to_string());
+    }
+    if let Some(c) = chars.iter().find(|c| !c.is_ascii()) {
+        return Err(format!("'{}' is not an ASCII character", c));
+    }
+    Ok(chars)
+}
+
+/// Parse an `--encoding` label such as "utf-16le", "latin1" or "windows-1252".
+pub fn parse_encoding(s: &str) -> std::result::Result<&'static Encoding, String> {
+    Encoding::for_label(s.trim().as_bytes()).ok_or_else(|| format!("Unknown encoding '{}'", s))
+}
+
+/// Parse a byte size such as "512M", "4G", "64k" or "1000000" (binary units).
+pub fn parse_size(s: &str) -> std::result::Result<usize, String> {
+    let s = s.trim();
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier: f64 = match s[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1024.0,
+        "M" | "MB" => 1024.0 * 1024.0,
+        "G" | "GB" => 1024.0 * 1024.0 * 1024.0,
+        "T" | "TB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        unit => return Err(format!("Unknown size unit '{}' in '{}'", unit, s)),
+    };
+    match digits.trim().parse::<f64>() {
+        Ok(n) if n > 0.0 => Ok((n * multiplier) as usize),
+        _ => Err(format!("Invalid size '{}' (e.g. 512M, 4G)", s)),
+    }
+}
+
+/// Open `path` (or stdin), decompress it if needed and transcode it to UTF-8.
+///
+/// A byte order mark selects UTF-8, UTF-16LE or UTF-16BE and is stripped;
+/// otherwise `encoding` is used, and without it the bytes pass through as-is.
+pub fn input_reader(
+    path: Option<&str>,
+    encoding: Option<&'static Encoding>,
+) -> Result<Box<dyn BufRead>> {
+    let reader: Box<dyn BufRead> = match path {
+        Some(p) => {
+            let file = File::open(p).map_err(|source| CliError::Open {
+                path: p.to_string(),
+                source,
+            })?;
+            Box::new(BufReader::new(file))
+        }
         None => {
             if is(Stream::Stdin) {
-                panic!("Expected a file or piped input, but stdin is a TTY");
+                return Err(CliError::Usage(
+                    "Expected a file or piped input, but stdin is a TTY".to_string(),
+                ));
             }
             Box::new(BufReader::new(io::stdin()))
         }
+    };
+    Ok(transcode(decompress(reader, path)?, encoding)?)
+}
+
+/// Decode `reader` to UTF-8, honouring a byte order mark over `encoding`.
+///
+/// UTF-8 input is passed through rather than validated, so that rows with
+/// invalid bytes still reach the `--on-error` policy instead of turning into U+FFFD.
+pub fn transcode(
+    mut reader: Box<dyn BufRead>,
+    encoding: Option<&'static Encoding>,
+) -> io::Result<Box<dyn BufRead>> {
+    let bom = Encoding::for_bom(reader.fill_buf()?);
+    if let Some((_, bom_len)) = bom {
+        reader.consume(bom_len);
+    }
+
+    match bom.map(|(e, _)| e).or(encoding) {
+        None => Ok(reader),
+        Some(e) if e == UTF_8 => Ok(reader),
+        Some(e) => Ok(Box::new(Transcoder {
+            inner: reader,
+            decoder: e.new_decoder_without_bom_handling(),
+            out: Vec::new(),
+            pos: 0,
+            done: false,
+        })),
+    }
+}
+
+/// Streaming decoder from any `encoding_rs` encoding to UTF-8. Each chunk of
+/// the inner reader is decoded whole, so `fill_buf` exposes as much data as
+/// the inner buffer did, which delimiter detection relies on.
+struct Transcoder {
+    inner: Box<dyn BufRead>,
+    decoder: encoding_rs::Decoder,
+    out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Read for Transcoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for Transcoder {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos >= self.out.len() && !self.done {
+            let input = self.inner.fill_buf()?;
+            let last = input.is_empty();
+            let max_len = self
+                .decoder
+                .max_utf8_buffer_length(input.len())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::OutOfMemory, "input chunk too large")
+                })?;
+            self.out.resize(max_len, 0);
+            let (_, read, written, _) = self.decoder.decode_to_utf8(input, &mut self.out, last);
+            self.out.truncate(written);
+            self.pos = 0;
+            self.inner.consume(read);
+            self.done = last;
+        }
+        Ok(&self.out[self.pos..])
     }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+/// Wrap `reader` in a streaming decoder if it holds compressed data.
+///
+/// The format is taken from the file extension when there is one, otherwise
+/// from the magic bytes at the start of the stream. Uncompressed input is
+/// returned as-is.
+pub fn decompress(
+    mut reader: Box<dyn BufRead>,
+    path: Option<&str>,
+) -> io::Result<Box<dyn BufRead>> {
+    let compression = match path.and_then(Compression::from_path) {
+        Some(c) => Some(c),
+        None => Compression::from_magic(reader.fill_buf()?),
+    };
+
+    let decoded: Box<dyn BufRead> = match compression {
+        None => return Ok(reader),
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Compression::Zstd) => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        Some(Compression::Bzip2) => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Some(Compression::Xz) => Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))),
+    };
+    Ok(decoded)
+}
+
+/// Delimiters considered by auto-detection, in tie-breaking priority order.
+pub const DEFAULT_DELIMITERS: &[char] = &['\t', '|', ',', ';', '^', '\x1f', ' '];
+
+/// Maximum number of records sampled by delimiter detection.
+const SAMPLE_RECORDS: usize = 100;
+
+/// Detect the delimiter among `DEFAULT_DELIMITERS` with `"` as the quote.
+#[cfg(test)]
+fn detect_delimiter(reader: &mut dyn BufRead) -> Result<char> {
+    detect_delimiter_with(reader, DEFAULT_DELIMITERS, '"')
 }
 
-pub fn detect_delimiter(reader: &mut dyn BufRead) -> Result<char, Box<dyn Error>> {
+/// Detect the delimiter among `candidates` by sampling the first records of
+/// `reader` without consuming them.
+///
+/// Each candidate splits the sample into fields (delimiters inside quoted
+/// fields are ignored) and is scored by how consistent the field count is
+/// across records, then by the number of fields. Remaining ties go to the
+/// candidate listed first. A candidate must split the first record (the
+/// header) to be considered. Falls back to comma when no candidate does.
+pub fn detect_delimiter_with(
+    reader: &mut dyn BufRead,
+    candidates: &[char],
+    quote: char,
+) -> Result<char> {
     // Peek at buffer without consuming it
     let buffer = reader.fill_buf()?;
 
     if buffer.is_empty() {
-        return Err("File is empty or contains no data".into());
+        return Err(CliError::EmptyInput);
     }
 
     // Find first newline position
@@ -30,57 +439,164 @@ pub fn detect_delimiter(reader: &mut dyn BufRead) -> Result<char, Box<dyn Error>
         .position(|&b| b == b'\n')
         .unwrap_or(buffer.len());
 
-    // Convert bytes to string (still just viewing, not consuming)
-    let first_line =
-        std::str::from_utf8(&buffer[..first_line_end]).map_err(|_| "Invalid UTF-8 in file")?;
+    // A multi-byte character cut off by the end of the buffer is fine;
+    // anything else that is not UTF-8 is an error.
+    let first_line = match std::str::from_utf8(&buffer[..first_line_end]) {
+        Ok(line) => line,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&buffer[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => {
+            return Err(CliError::Malformed(
+                "Invalid UTF-8 in file; use --encoding to read other encodings".to_string(),
+            ))
+        }
+    };
 
     if first_line.trim().is_empty() {
-        return Err("File is empty or contains no data".into());
+        return Err(CliError::EmptyInput);
     }
 
-    // Detect delimiter by counting occurrences of common delimiters
-    let delimiters = [('\t', "tab"), ('|', "pipe"), (',', "comma"), (' ', "space")];
-    let mut counts: Vec<(char, usize, &str)> = delimiters
-        .iter()
-        .map(|&(delim, name)| (delim, first_line.matches(delim).count(), name))
-        .collect();
-
-    // Sort by count (descending), with tie-breaking by delimiter priority
-    counts.sort_by(|a, b| {
-        match b.1.cmp(&a.1) {
-            std::cmp::Ordering::Equal => {
-                // If counts are equal, prioritize: tab > pipe > comma > space
-                let priority = |c: char| match c {
-                    '\t' => 0,
-                    '|' => 1,
-                    ',' => 2,
-                    ' ' => 3,
-                    _ => 4,
-                };
-                priority(a.0).cmp(&priority(b.0))
+    let mut best: Option<(char, DelimiterScore)> = None;
+    for (priority, &delim) in candidates.iter().enumerate() {
+        if !delim.is_ascii() {
+            continue;
+        }
+        let counts = sample_field_counts(buffer, delim as u8, quote as u8);
+        let score = DelimiterScore::new(&counts, priority);
+        let better = match &best {
+            Some((_, b)) => score > *b,
+            None => true,
+        };
+        let splits_header = counts.first().is_some_and(|&n| n > 1);
+        if splits_header && score.fields > 1 && better {
+            best = Some((delim, score));
+        }
+    }
+
+    // If no candidate splits the sample, default to comma
+    Ok(best.map(|(delim, _)| delim).unwrap_or(','))
+}
+
+/// How well a delimiter candidate explains the sampled records. Compares
+/// by consistency bucket, then field count, then (reversed) priority.
+#[derive(Debug, PartialEq, Eq)]
+struct DelimiterScore {
+    consistency: u32,
+    fields: usize,
+    priority: std::cmp::Reverse<usize>,
+}
+
+impl DelimiterScore {
+    fn new(counts: &[usize], priority: usize) -> DelimiterScore {
+        // Most common field count; ties go to the larger count
+        let mut histogram: Vec<(usize, usize)> = Vec::new();
+        for &c in counts {
+            match histogram.iter_mut().find(|(n, _)| *n == c) {
+                Some((_, freq)) => *freq += 1,
+                None => histogram.push((c, 1)),
             }
-            other => other,
         }
-    });
+        let (fields, freq) = histogram
+            .into_iter()
+            .max_by_key(|&(n, freq)| (freq, n))
+            .unwrap_or((0, 0));
+
+        // Bucket the share of records matching the mode into 5% steps, so a
+        // single ragged row does not hand the win to a delimiter that merely
+        // occurs the same number of times on every line.
+        let share = if counts.is_empty() {
+            0.0
+        } else {
+            freq as f64 / counts.len() as f64
+        };
+
+        DelimiterScore {
+            consistency: (share * 20.0).round() as u32,
+            fields,
+            priority: std::cmp::Reverse(priority),
+        }
+    }
+}
+
+impl PartialOrd for DelimiterScore {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DelimiterScore {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.consistency, self.fields, self.priority).cmp(&(
+            other.consistency,
+            other.fields,
+            other.priority,
+        ))
+    }
+}
+
+/// Count the fields of each record in `buffer` when split on `delim`,
+/// honouring quoted fields (which may span lines) and skipping blank lines.
+/// A trailing record without a line terminator is only counted when it is
+/// the sole record, since the buffer may have cut it short.
+fn sample_field_counts(buffer: &[u8], delim: u8, quote: u8) -> Vec<usize> {
+    let mut counts = Vec::new();
+    let mut fields = 1;
+    let mut in_quotes = false;
+    let mut field_start = true;
+    let mut line_empty = true;
+    let mut i = 0;
 
-    // Return the delimiter with the highest count
-    // If no delimiter found (all counts are 0), default to comma
-    let delimiter = if counts[0].1 > 0 { counts[0].0 } else { ',' };
+    while i < buffer.len() && counts.len() < SAMPLE_RECORDS {
+        let b = buffer[i];
+        if in_quotes {
+            if b == quote {
+                if buffer.get(i + 1) == Some(&quote) {
+                    i += 1; // escaped quote
+                } else {
+                    in_quotes = false;
+                }
+            }
+        } else if b == b'\n' {
+            if !line_empty {
+                counts.push(fields);
+            }
+            fields = 1;
+            field_start = true;
+            line_empty = true;
+            i += 1;
+            continue;
+        } else if b == delim {
+            fields += 1;
+            field_start = true;
+            line_empty = false;
+            i += 1;
+            continue;
+        } else if b == quote && field_start {
+            in_quotes = true;
+        }
+        if b != b'\r' {
+            line_empty = false;
+        }
+        field_start = false;
+        i += 1;
+    }
 
-    Ok(delimiter)
+    if !line_empty && counts.is_empty() {
+        counts.push(fields);
+    }
+    counts
 }
 
-/// Create a CSV writer with the specified delimiter, writing to the given writer.
-pub fn csv_writer<W: Write>(writer: W, delimiter: char) -> csv::Writer<W> {
-    WriterBuilder::new()
-        .delimiter(delimiter as u8)
-        .from_writer(writer)
+/// Flush `writer` and move its output file into place.
+pub fn finish_output(writer: csv::Writer<Output>) -> Result<()> {
+    writer.into_inner().map_err(|e| e.into_error())?.finish()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read};
 
     #[test]
     fn test_detect_delimiter_comma() {
@@ -147,4 +663,208 @@ mod tests {
             "Should return error for whitespace-only file"
         );
     }
+
+    #[test]
+    fn test_detect_delimiter_ignores_quoted_commas() {
+        let data = "\"last, first\"|age\n\"Doe, John\"|42\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), '|');
+    }
+
+    #[test]
+    fn test_detect_delimiter_semicolon_with_decimal_commas() {
+        let data = "name;price\nWidget;9,99\nGadget;19,50\nGizmo;5\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), ';');
+    }
+
+    #[test]
+    fn test_detect_delimiter_caret_and_unit_separator() {
+        let mut reader = Cursor::new("a^b^c\n1^2^3\n");
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), '^');
+        let mut reader = Cursor::new("a\x1fb\n1\x1f2\n");
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), '\x1f');
+    }
+
+    #[test]
+    fn test_detect_delimiter_prefers_consistent_field_count() {
+        // The header alone has more spaces than commas
+        let data = "full name,home town\nAlice Smith,New York\nBob,LA\nCharlie Brown,San Jose\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), ',');
+    }
+
+    #[test]
+    fn test_detect_delimiter_tolerates_one_ragged_row() {
+        let data = "a,b,c,d,e\n1,2,3,4,5\n6,7,8,9,10\n11,12,13,14,15\n16,17,18,19,20\n\
+                    21,22,23,24,25\n26,27,28,29,30\n31,32,33,34,35\n36,37,38,39,40\n\
+                    41,42,43,44,45\n46,47,48,49,50\n51,52,53,54,55\n56,57,58,59,60\n\
+                    61,62,63,64,65\n66,67,68,69,70\n71,72,73,74,75\n76,77,78,79,80\n\
+                    81,82,83,84,85\n86,87,88,89,90\n91,92,93,94\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), ',');
+    }
+
+    #[test]
+    fn test_detect_delimiter_multiline_quoted_field() {
+        let data = "id|note\n1|\"line one, with comma\nline two, with comma\"\n2|plain\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(detect_delimiter(&mut reader).unwrap(), '|');
+    }
+
+    #[test]
+    fn test_detect_delimiter_custom_candidates() {
+        let data = "a:b,c\n1:2,3\n";
+        let mut reader = Cursor::new(data);
+        assert_eq!(
+            detect_delimiter_with(&mut reader, &[':', ','], '"').unwrap(),
+            ':'
+        );
+        let mut reader = Cursor::new(data);
+        assert_eq!(
+            detect_delimiter_with(&mut reader, &['|'], '"').unwrap(),
+            ','
+        );
+    }
+
+    #[test]
+    fn test_detect_delimiter_does_not_consume_input() {
+        let mut reader = Cursor::new("a;b\n1;2\n");
+        detect_delimiter(&mut reader).unwrap();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "a;b\n1;2\n");
+    }
+
+    #[test]
+    fn test_parse_delimiter_candidates() {
+        assert_eq!(parse_delimiter_candidates(";,"), Ok(vec![';', ',']));
+        assert_eq!(parse_delimiter_candidates("\\t|"), Ok(vec!['\t', '|']));
+        assert!(parse_delimiter_candidates("").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1000"), Ok(1000));
+        assert_eq!(parse_size("64k"), Ok(64 * 1024));
+        assert_eq!(parse_size("512M"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_size("1.5GB"), Ok(3 * 512 * 1024 * 1024));
+        assert!(parse_size("12Q").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_dialect_char() {
+        assert_eq!(parse_dialect_char(";"), Ok(';'));
+        assert_eq!(parse_dialect_char("tab"), Ok('\t'));
+        assert_eq!(parse_dialect_char("\\t"), Ok('\t'));
+        assert_eq!(parse_dialect_char("\t"), Ok('\t'));
+        assert!(parse_dialect_char(",,").is_err());
+        assert!(parse_dialect_char("").is_err());
+        assert!(parse_dialect_char("§").is_err());
+    }
+
+    #[test]
+    fn test_resolve_delimiter_prefers_override() {
+        let opts = CsvOptions {
+            delimiter: Some(';'),
+            ..Default::default()
+        };
+        let mut reader = Cursor::new("a,b,c\n1,2,3\n");
+        assert_eq!(opts.resolve_delimiter(&mut reader).unwrap(), ';');
+        let mut reader = Cursor::new("a,b,c\n1,2,3\n");
+        assert_eq!(
+            CsvOptions::default()
+                .resolve_delimiter(&mut reader)
+                .unwrap(),
+            ','
+        );
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("a.csv.gz"), Some(Compression::Gzip));
+        assert_eq!(Compression::from_path("a.csv.zst"), Some(Compression::Zstd));
+        assert_eq!(
+            Compression::from_path("a.csv.bz2"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::from_path("a.csv.XZ"), Some(Compression::Xz));
+        assert_eq!(Compression::from_path("a.csv"), None);
+        assert_eq!(Compression::from_path("gz"), None);
+    }
+
+    #[test]
+    fn test_compression_from_magic() {
+        assert_eq!(
+            Compression::from_magic(&[0x1f, 0x8b, 0x08, 0x00]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_magic(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::from_magic(b"BZh91AY&SY..."),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::from_magic(b"BZh1,name,age\n"), None);
+        assert_eq!(Compression::from_magic(b"name,age\n"), None);
+    }
+
+    #[test]
+    fn test_decompress_passes_plain_input_through() {
+        let reader: Box<dyn BufRead> = Box::new(Cursor::new(b"name,age\nAlice,30\n".to_vec()));
+        let mut decoded = decompress(reader, None).unwrap();
+        let mut out = String::new();
+        decoded.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "name,age\nAlice,30\n");
+    }
+
+    fn transcoded(bytes: &[u8], encoding: Option<&'static Encoding>) -> Vec<u8> {
+        let reader: Box<dyn BufRead> = Box::new(Cursor::new(bytes.to_vec()));
+        let mut out = Vec::new();
+        transcode(reader, encoding)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_transcode_strips_utf8_bom() {
+        let out = transcoded(b"\xEF\xBB\xBFid,name\n1,a\n", None);
+        assert_eq!(out, b"id,name\n1,a\n");
+    }
+
+    #[test]
+    fn test_transcode_utf16le_bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "id;näme\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let out = transcoded(&bytes, None);
+        assert_eq!(String::from_utf8(out).unwrap(), "id;näme\n");
+    }
+
+    #[test]
+    fn test_transcode_windows_1252_override() {
+        let encoding = parse_encoding("windows-1252").unwrap();
+        let out = transcoded(b"name\nCaf\xe9 \x80\n", Some(encoding));
+        assert_eq!(String::from_utf8(out).unwrap(), "name\nCafé €\n");
+    }
+
+    #[test]
+    fn test_transcode_passes_invalid_utf8_through() {
+        let out = transcoded(b"a\n\xff\n", None);
+        assert_eq!(out, b"a\n\xff\n");
+    }
+
+    #[test]
+    fn test_parse_encoding_labels() {
+        assert_eq!(parse_encoding("latin1").unwrap().name(), "windows-1252");
+        assert_eq!(parse_encoding("UTF-16LE").unwrap().name(), "UTF-16LE");
+        assert!(parse_encoding("klingon").is_err());
+    }
 }
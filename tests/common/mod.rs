@@ -0,0 +1,24 @@
+//! Helpers shared by the integration tests of commands reading CSV files.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// A temporary file holding `content`.
+pub fn csv_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+/// Run `clw` with `args` followed by the path of `file`, returning its
+/// standard output after checking that it succeeded.
+pub fn run(args: &[&str], file: &NamedTempFile) -> String {
+    let output = cargo_bin_cmd!("clw")
+        .args(args)
+        .arg(file.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "clw {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
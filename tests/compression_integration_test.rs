@@ -0,0 +1,169 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+use tempfile::{Builder, NamedTempFile};
+
+const CSV: &str = "name,age,city\nAlice,30,New York\nBob,25,Los Angeles\nCharlie,35,Chicago\n";
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn zstd(data: &[u8]) -> Vec<u8> {
+    zstd::encode_all(data, 0).unwrap()
+}
+
+fn bzip2(data: &[u8]) -> Vec<u8> {
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn xz(data: &[u8]) -> Vec<u8> {
+    let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn temp_with_suffix(suffix: &str, content: &[u8]) -> NamedTempFile {
+    let mut file = Builder::new().suffix(suffix).tempfile().unwrap();
+    file.write_all(content).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+#[test]
+fn test_peek_gzip_file() {
+    let file = temp_with_suffix(".csv.gz", &gzip(CSV.as_bytes()));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("peek")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Alice"))
+        .stdout(predicate::str::contains("Charlie"));
+}
+
+#[test]
+fn test_info_zstd_file() {
+    let file = temp_with_suffix(".csv.zst", &zstd(CSV.as_bytes()));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("info")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    3"))
+        .stdout(predicate::str::contains("Columns: 3"));
+}
+
+#[test]
+fn test_stats_bzip2_file() {
+    let file = temp_with_suffix(".csv.bz2", &bzip2(CSV.as_bytes()));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("stats")
+        .arg("-c")
+        .arg("age")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mean:"))
+        .stdout(predicate::str::contains("30.00"));
+}
+
+#[test]
+fn test_header_xz_file() {
+    let file = temp_with_suffix(".csv.xz", &xz(CSV.as_bytes()));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("header")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0: name"))
+        .stdout(predicate::str::contains("2: city"));
+}
+
+#[test]
+fn test_compressed_stdin_detected_by_magic_bytes() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("name")
+        .write_stdin(gzip(CSV.as_bytes()))
+        .assert()
+        .success()
+        .stdout("name\nAlice\nBob\nCharlie\n");
+}
+
+#[test]
+fn test_compressed_file_without_extension_detected_by_magic_bytes() {
+    let file = temp_with_suffix(".data", &zstd(CSV.as_bytes()));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("city")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("city\nNew York\nLos Angeles\nChicago\n");
+}
+
+#[test]
+fn test_stack_compressed_files_and_compressed_stdin() {
+    let file1 = temp_with_suffix(".csv.gz", &gzip(b"id|v\n1|a\n"));
+    let file2 = temp_with_suffix(".csv.zst", &zstd(b"id|v\n2|b\n"));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("stack")
+        .arg(file1.path())
+        .arg("-")
+        .arg(file2.path())
+        .write_stdin(xz(b"id|v\n3|c\n"))
+        .assert()
+        .success()
+        .stdout("id|v\n1|a\n3|c\n2|b\n");
+}
+
+#[test]
+fn test_paste_compressed_files() {
+    let file1 = temp_with_suffix(".csv.gz", &gzip(b"a,b\n1,2\n"));
+    let file2 = temp_with_suffix(".csv.bz2", &bzip2(b"c\n3\n"));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("paste")
+        .arg(file1.path())
+        .arg(file2.path())
+        .assert()
+        .success()
+        .stdout("a,b,c\n1,2,3\n");
+}
+
+#[test]
+fn test_multi_member_gzip() {
+    let mut data = gzip(b"name,age\nAlice,30\n");
+    data.extend(gzip(b"Bob,25\n"));
+    let file = temp_with_suffix(".csv.gz", &data);
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("name")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("name\nAlice\nBob\n");
+}
+
+#[test]
+fn test_corrupt_gzip_file_fails() {
+    let file = temp_with_suffix(".csv.gz", b"name,age\nAlice,30\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("peek").arg(file.path()).assert().failure();
+}
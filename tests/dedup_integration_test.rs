@@ -0,0 +1,109 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use predicates::prelude::*;
+
+const PEOPLE: &str = "id,name,city\n1,Ann,Oslo\n2,Bob,Rome\n1,Ann,Oslo\n3,Cy,Oslo\n2,Bob,Paris\n";
+
+fn dedup_output(args: &[&str]) -> String {
+    run(&[&["dedup"], args].concat(), &csv_file(PEOPLE))
+}
+
+#[test]
+fn test_dedup_whole_rows() {
+    assert_eq!(
+        dedup_output(&[]),
+        "id,name,city\n1,Ann,Oslo\n2,Bob,Rome\n3,Cy,Oslo\n2,Bob,Paris\n"
+    );
+}
+
+#[test]
+fn test_dedup_key_keep_first() {
+    assert_eq!(
+        dedup_output(&["-c", "id"]),
+        "id,name,city\n1,Ann,Oslo\n2,Bob,Rome\n3,Cy,Oslo\n"
+    );
+}
+
+#[test]
+fn test_dedup_key_keep_last() {
+    assert_eq!(
+        dedup_output(&["-c", "id", "--keep", "last"]),
+        "id,name,city\n1,Ann,Oslo\n3,Cy,Oslo\n2,Bob,Paris\n"
+    );
+}
+
+#[test]
+fn test_dedup_keep_none_drops_every_duplicate() {
+    assert_eq!(
+        dedup_output(&["-c", "city", "--keep", "none"]),
+        "id,name,city\n2,Bob,Rome\n2,Bob,Paris\n"
+    );
+}
+
+#[test]
+fn test_dedup_spilled_matches_memory() {
+    for keep in ["first", "last", "none"] {
+        assert_eq!(
+            dedup_output(&["-c", "name,city", "--keep", keep, "--memory", "1"]),
+            dedup_output(&["-c", "name,city", "--keep", keep]),
+            "--keep {}",
+            keep
+        );
+    }
+}
+
+#[test]
+fn test_dedup_report() {
+    assert_eq!(
+        dedup_output(&["-c", "id,name", "--report"]),
+        "id,name,count\n1,Ann,2\n2,Bob,2\n"
+    );
+    assert_eq!(
+        dedup_output(&["--report", "--memory", "1"]),
+        "id,name,city,count\n1,Ann,Oslo,2\n"
+    );
+}
+
+#[test]
+fn test_dedup_keep_last_from_stdin() {
+    cargo_bin_cmd!("clw")
+        .args(["dedup", "-c", "city", "--keep", "last", "--memory", "1"])
+        .write_stdin(PEOPLE)
+        .assert()
+        .success()
+        .stdout("id,name,city\n2,Bob,Rome\n3,Cy,Oslo\n2,Bob,Paris\n");
+}
+
+#[test]
+fn test_dedup_keys_do_not_run_together() {
+    let file = csv_file("a,b\nab,c\na,bc\n");
+    cargo_bin_cmd!("clw")
+        .arg("dedup")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a,b\nab,c\na,bc\n");
+}
+
+#[test]
+fn test_dedup_unknown_column() {
+    let file = csv_file(PEOPLE);
+    cargo_bin_cmd!("clw")
+        .args(["dedup", "-c", "email"])
+        .arg(file.path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("email"));
+}
+
+#[test]
+fn test_dedup_report_conflicts_with_keep() {
+    let file = csv_file(PEOPLE);
+    cargo_bin_cmd!("clw")
+        .args(["dedup", "--report", "--keep", "last"])
+        .arg(file.path())
+        .assert()
+        .code(2);
+}
@@ -0,0 +1,110 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+#[test]
+fn test_delimiter_override_semicolon() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--delimiter")
+        .arg(";")
+        .arg("select")
+        .arg("-c")
+        .arg("price")
+        .write_stdin("name;price\nWidget;9,99\nGadget;19,50\n")
+        .assert()
+        .success()
+        .stdout("price\n9,99\n19,50\n");
+}
+
+#[test]
+fn test_delimiter_override_beats_detection() {
+    // Detection would pick the comma (two commas vs one pipe)
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("-d")
+        .arg("pipe")
+        .arg("header")
+        .write_stdin("last, first, middle|age\nDoe, John, Q|42\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0: last, first, middle"))
+        .stdout(predicate::str::contains("1: age"));
+}
+
+#[test]
+fn test_delimiter_override_by_name() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--delimiter")
+        .arg("tab")
+        .arg("select")
+        .arg("-c")
+        .arg("b")
+        .write_stdin("a b\tb\n1 2\t3\n")
+        .assert()
+        .success()
+        .stdout("b\n3\n");
+}
+
+#[test]
+fn test_quote_override() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--quote")
+        .arg("'")
+        .arg("filter")
+        .arg("-c")
+        .arg("age")
+        .arg("-v")
+        .arg("42")
+        .write_stdin("name,age\n'Doe, John',42\nSmith,30\n")
+        .assert()
+        .success()
+        .stdout("\"Doe, John\",42\n");
+}
+
+#[test]
+fn test_escape_with_no_double_quote() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--escape")
+        .arg("\\")
+        .arg("--no-double-quote")
+        .arg("select")
+        .arg("-c")
+        .arg("quote")
+        .write_stdin("id,quote\n1,\"say \\\"hi\\\"\"\n")
+        .assert()
+        .success()
+        .stdout("quote\n\"say \"\"hi\"\"\"\n");
+}
+
+#[test]
+fn test_stack_with_delimiter_override() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    writeln!(file1, "a;b").unwrap();
+    writeln!(file1, "1;x,y").unwrap();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    writeln!(file2, "a;b").unwrap();
+    writeln!(file2, "2;p,q,r").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--delimiter")
+        .arg(";")
+        .arg("stack")
+        .arg(file1.path())
+        .arg(file2.path())
+        .assert()
+        .success()
+        .stdout("a;b\n1;x,y\n2;p,q,r\n");
+}
+
+#[test]
+fn test_invalid_delimiter_rejected() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--delimiter")
+        .arg(";;")
+        .arg("header")
+        .write_stdin("a;b\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected a single character"));
+}
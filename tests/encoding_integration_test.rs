@@ -0,0 +1,102 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn utf16le_with_bom(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_le_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn test_utf8_bom_is_stripped_from_first_header() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("id")
+        .write_stdin(b"\xEF\xBB\xBFid,name\n1,alice\n".to_vec())
+        .assert()
+        .success()
+        .stdout("id\n1\n");
+}
+
+#[test]
+fn test_utf16le_excel_export_is_transcoded() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("export.txt");
+    fs::write(
+        &path,
+        utf16le_with_bom("id\tcity\r\n1\tMünchen\r\n2\tZürich\r\n"),
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("city")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("city\nMünchen\nZürich\n");
+}
+
+#[test]
+fn test_windows_1252_override() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--encoding")
+        .arg("windows-1252")
+        .arg("freq")
+        .arg("-c")
+        .arg("name")
+        .write_stdin(b"id,name\n1,Caf\xe9\n2,Caf\xe9\n".to_vec())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Café"));
+}
+
+#[test]
+fn test_windows_1252_without_override_suggests_encoding() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("header")
+        .write_stdin(b"caf\xe9,price\n1,2\n".to_vec())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("--encoding"));
+}
+
+#[test]
+fn test_compressed_utf16_input() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("export.csv.gz");
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&utf16le_with_bom("a,b\n1,2\n")).unwrap();
+    fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("b")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("b\n2\n");
+}
+
+#[test]
+fn test_unknown_encoding_is_usage_error() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--encoding")
+        .arg("klingon")
+        .arg("header")
+        .write_stdin("a,b\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown encoding 'klingon'"));
+}
@@ -0,0 +1,130 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_missing_file_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("peek")
+        .arg("tests/fixtures/does_not_exist.csv")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Cannot open file 'tests/fixtures/does_not_exist.csv'",
+        ))
+        .stderr(predicate::str::contains("panicked").not());
+}
+
+#[test]
+fn test_missing_column_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("stats")
+        .arg("-c")
+        .arg("nope")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "Error: Column 'nope' not found in CSV. Available columns: name, age, city, occupation\n",
+        ));
+}
+
+#[test]
+fn test_malformed_csv_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("a")
+        .write_stdin("a,b\n1,2\n3,4,5\n")
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("found record with 3 fields"));
+}
+
+#[test]
+fn test_empty_input_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("info")
+        .arg("tests/fixtures/empty.csv")
+        .assert()
+        .code(6)
+        .stderr("Error: File is empty or contains no data\n");
+}
+
+#[test]
+fn test_invalid_selector_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("0")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_mismatched_inputs_exit_code() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("paste")
+        .arg("tests/fixtures/sample_comma.csv")
+        .arg("tests/fixtures/sample_pipe.csv")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Files have different delimiters"));
+}
+
+#[test]
+fn test_broken_pipe_is_quiet_success() {
+    let mut data = String::from("id,value\n");
+    for i in 0..200_000 {
+        data.push_str(&format!("{},{}\n", i, i * 2));
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_clw"))
+        .arg("select")
+        .arg("-c")
+        .arg("value")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Close the read end of stdout before any output is consumed
+    drop(child.stdout.take());
+    let mut stdin = child.stdin.take().unwrap();
+    let _ = stdin.write_all(data.as_bytes());
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn test_broken_pipe_in_report_command() {
+    let mut data = String::from("id\n");
+    for i in 0..50_000 {
+        data.push_str(&format!("{}\n", i));
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_clw"))
+        .arg("freq")
+        .arg("-c")
+        .arg("id")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    drop(child.stdout.take());
+    let mut stdin = child.stdin.take().unwrap();
+    let _ = stdin.write_all(data.as_bytes());
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
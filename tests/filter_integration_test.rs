@@ -1,6 +1,7 @@
 use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 use std::fs;
+use std::io::Write;
 
 #[test]
 fn test_filter_single_value() {
@@ -359,3 +360,281 @@ fn test_filter_missing_value_flag() {
         .failure()
         .stderr(predicate::str::contains("required"));
 }
+
+#[test]
+fn test_filter_where_expression() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("--where")
+        .arg("age > 26 && city in ('New York', 'Chicago')")
+        .arg("--keep-header")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .success()
+        .stdout(
+            "name,age,city,occupation\nAlice,30,New York,Engineer\nCharlie,35,Chicago,Manager\n",
+        );
+}
+
+#[test]
+fn test_filter_where_string_functions_and_nulls() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-w")
+        .arg("!is_empty(email) and contains(lower(name), 'smith')")
+        .write_stdin("name,email\nAnn Smith,ann@x.com\nBob Smith,\nCy Jones,cy@x.com\n")
+        .assert()
+        .success()
+        .stdout("Ann Smith,ann@x.com\n");
+}
+
+#[test]
+fn test_filter_where_numeric_not_lexicographic() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-w")
+        .arg("qty >= 9")
+        .write_stdin("id,qty\na,9\nb,10\nc,100\nd,\ne,n/a\n")
+        .assert()
+        .success()
+        .stdout("a,9\nb,10\nc,100\n");
+}
+
+#[test]
+fn test_filter_where_parse_error_points_at_token() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-w")
+        .arg("age >> 3")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Invalid expression: expected a value, found '>'\n  age >> 3\n       ^",
+        ));
+}
+
+#[test]
+fn test_filter_where_unknown_column() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-w")
+        .arg("salary > 3")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Column 'salary' not found"));
+}
+
+#[test]
+fn test_filter_where_conflicts_with_column() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-w")
+        .arg("age > 3")
+        .arg("-c")
+        .arg("name")
+        .arg("-v")
+        .arg("Alice")
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_filter_regex_ignore_case() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "city", "-v", "^(new|los) ", "--regex", "-i"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .success()
+        .stdout("Alice,30,New York,Engineer\nBob,25,Los Angeles,Designer\n");
+}
+
+#[test]
+fn test_filter_ignore_case_values() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "filter",
+        "-c",
+        "name",
+        "-v",
+        "ALICE,charlie",
+        "--ignore-case",
+    ])
+    .arg("tests/fixtures/sample_comma.csv")
+    .assert()
+    .success()
+    .stdout("Alice,30,New York,Engineer\nCharlie,35,Chicago,Manager\n");
+}
+
+#[test]
+fn test_filter_invert() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "name", "-v", "Alice,Bob", "--invert"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .success()
+        .stdout("Charlie,35,Chicago,Manager\n");
+}
+
+#[test]
+fn test_filter_multiple_conditions_and_or() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "filter", "-c", "age", "-v", "30,35", "-c", "city", "-v", "Chicago",
+    ])
+    .arg("tests/fixtures/sample_comma.csv")
+    .assert()
+    .success()
+    .stdout("Charlie,35,Chicago,Manager\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "filter", "-c", "age", "-v", "25", "-c", "city", "-v", "Chicago",
+    ])
+    .arg("--any")
+    .arg("tests/fixtures/sample_comma.csv")
+    .assert()
+    .success()
+    .stdout("Bob,25,Los Angeles,Designer\nCharlie,35,Chicago,Manager\n");
+}
+
+#[test]
+fn test_filter_no_match_warning_lists_all_criteria() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "filter", "-c", "name", "-v", "Zed", "-c", "city", "-v", "^X",
+    ])
+    .args(["--regex", "--any"])
+    .arg("tests/fixtures/sample_comma.csv")
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(
+        "Column: 'name', Pattern: /Zed/\nOR Column: 'city', Pattern: /^X/",
+    ));
+}
+
+#[test]
+fn test_filter_unpaired_column() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "name", "-c", "age", "-v", "Alice"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Each --column needs its own --value",
+        ));
+}
+
+#[test]
+fn test_filter_invalid_regex() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "name", "-v", "(", "--regex"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid regex '('"));
+}
+
+#[test]
+fn test_filter_values_file_list() {
+    let mut ids = tempfile::NamedTempFile::new().unwrap();
+    ids.write_all(b"Alice\n\n  Charlie \r\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "name", "--values-file"])
+        .arg(ids.path())
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .success()
+        .stdout("Alice,30,New York,Engineer\nCharlie,35,Chicago,Manager\n");
+}
+
+#[test]
+fn test_filter_values_file_csv_column_anti_join() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "city", "--anti"])
+        .args(["--values-file", "tests/fixtures/sample_comma.csv"])
+        .args(["--values-column", "city"])
+        .write_stdin("city,n\nChicago,1\nParis,2\nNew York,3\n")
+        .assert()
+        .success()
+        .stdout("Paris,2\n");
+}
+
+#[test]
+fn test_filter_values_file_bloom() {
+    let mut ids = tempfile::NamedTempFile::new().unwrap();
+    for i in 0..1000 {
+        writeln!(ids, "{}", i * 2).unwrap();
+    }
+
+    let rows: String = (0..20).map(|i| format!("{}\n", i)).collect();
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--no-header", "filter", "-c", "1"])
+        .args(["--bloom", "--bloom-fp-rate", "0.0001"])
+        .arg("--values-file")
+        .arg(ids.path())
+        .write_stdin(rows)
+        .assert()
+        .success()
+        .stdout("0\n2\n4\n6\n8\n10\n12\n14\n16\n18\n");
+}
+
+#[test]
+fn test_filter_bloom_is_exact() {
+    let mut ids = tempfile::NamedTempFile::new().unwrap();
+    for i in 0..1000 {
+        writeln!(ids, "{}", i * 2).unwrap();
+    }
+
+    // A filter this loose lets most odd numbers through on its own
+    let rows: String = (0..20).map(|i| format!("{}\n", i)).collect();
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--no-header", "filter", "-c", "1"])
+        .args(["--bloom", "--bloom-fp-rate", "0.9"])
+        .arg("--values-file")
+        .arg(ids.path())
+        .write_stdin(rows.clone())
+        .assert()
+        .success()
+        .stdout("0\n2\n4\n6\n8\n10\n12\n14\n16\n18\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--no-header", "filter", "-c", "1", "--anti"])
+        .args(["--bloom", "--bloom-fp-rate", "0.9"])
+        .arg("--values-file")
+        .arg(ids.path())
+        .write_stdin(rows)
+        .assert()
+        .success()
+        .stdout("1\n3\n5\n7\n9\n11\n13\n15\n17\n19\n");
+}
+
+#[test]
+fn test_filter_bloom_ignore_case() {
+    let mut cities = tempfile::NamedTempFile::new().unwrap();
+    write!(cities, "NEW YORK\nchicago\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "city", "--bloom", "-i", "--anti"])
+        .arg("--values-file")
+        .arg(cities.path())
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .success()
+        .stdout("Bob,25,Los Angeles,Designer\n");
+}
+
+#[test]
+fn test_filter_values_file_conflicts_with_value() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["filter", "-c", "name", "-v", "Alice"])
+        .args(["--values-file", "tests/fixtures/sample_comma.csv"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
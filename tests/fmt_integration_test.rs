@@ -0,0 +1,103 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+
+#[test]
+fn test_fmt_without_options_keeps_input() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["fmt", "tests/fixtures/sample_min_quoted.csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "name,age,\"address, street\",city,state\nAlice,35,\"123 Main St., apt 10\",San Diego,CA\n",
+        ));
+}
+
+#[test]
+fn test_fmt_pipe_to_comma() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--out-delimiter", "comma", "fmt"])
+        .write_stdin("id|name\n1|Smith, J\n2|Doe\n")
+        .assert()
+        .success()
+        .stdout("id,name\n1,\"Smith, J\"\n2,Doe\n");
+}
+
+#[test]
+fn test_quote_style_always() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--quote-style", "always", "fmt"])
+        .write_stdin("a,b\n1,x\n")
+        .assert()
+        .success()
+        .stdout("\"a\",\"b\"\n\"1\",\"x\"\n");
+}
+
+#[test]
+fn test_quote_style_non_numeric() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--quote-style", "non-numeric", "fmt"])
+        .write_stdin("name,price\nWidget,9.99\n")
+        .assert()
+        .success()
+        .stdout("\"name\",\"price\"\n\"Widget\",9.99\n");
+}
+
+#[test]
+fn test_quote_style_never() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--quote-style", "never", "--out-delimiter", "tab", "fmt"])
+        .write_stdin("a,b\n\"x, y\",z\n")
+        .assert()
+        .success()
+        .stdout("a\tb\nx, y\tz\n");
+}
+
+#[test]
+fn test_crlf_and_bom_for_excel() {
+    let output = cargo_bin_cmd!("clw")
+        .args(["--crlf", "--bom", "fmt"])
+        .write_stdin("a,b\n1,2\n")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\xEF\xBB\xBFa,b\r\n1,2\r\n");
+}
+
+#[test]
+fn test_bom_output_reads_back() {
+    let output = cargo_bin_cmd!("clw")
+        .args(["--bom", "fmt", "tests/fixtures/sample_comma.csv"])
+        .output()
+        .expect("Failed to execute command");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["select", "-c", "name"])
+        .write_stdin(output.stdout)
+        .assert()
+        .success()
+        .stdout("name\nAlice\nBob\nCharlie\n");
+}
+
+#[test]
+fn test_dialect_applies_to_writer_commands() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "--out-delimiter",
+        ";",
+        "--crlf",
+        "select",
+        "-c",
+        "name,age",
+        "tests/fixtures/sample_comma.csv",
+    ])
+    .assert()
+    .success()
+    .stdout("name;age\r\nAlice;30\r\nBob;25\r\nCharlie;35\r\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--quote-style", "always", "transpose"])
+        .write_stdin("a,b\n1,2\n")
+        .assert()
+        .success()
+        .stdout("\"a\",\"1\"\n\"b\",\"2\"\n");
+}
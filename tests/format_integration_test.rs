@@ -0,0 +1,117 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use serde_json::Value;
+
+fn json_output(args: &[&str]) -> Value {
+    let output = cargo_bin_cmd!("clw")
+        .args(["--format", "json"])
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON")
+}
+
+#[test]
+fn test_header_json() {
+    let json = json_output(&["header", "tests/fixtures/sample_comma.csv"]);
+    assert_eq!(json["columns"][0]["index"], 0);
+    assert_eq!(json["columns"][0]["name"], "name");
+    assert_eq!(json["columns"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_header_tsv() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--format", "tsv", "header", "tests/fixtures/sample_comma.csv"])
+        .assert()
+        .success()
+        .stdout("index\tname\n0\tname\n1\tage\n2\tcity\n3\toccupation\n");
+}
+
+#[test]
+fn test_info_json_lists_inconsistent_rows() {
+    let json = json_output(&["info", "tests/fixtures/dirty_inconsistent.csv"]);
+    assert_eq!(json["rows"], 8);
+    let inconsistent = json["inconsistent_rows"].as_array().unwrap();
+    assert!(!inconsistent.is_empty());
+    assert!(inconsistent[0]["line"].is_u64());
+    assert!(inconsistent[0]["fields"].is_u64());
+}
+
+#[test]
+fn test_info_csv() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["--format", "csv", "info", "tests/fixtures/sample_comma.csv"])
+        .assert()
+        .success()
+        .stdout("rows,columns,inconsistent_rows\n3,4,0\n");
+}
+
+#[test]
+fn test_stats_json_numeric_and_categorical() {
+    let json = json_output(&["stats", "-c", "name,age", "tests/fixtures/sample_comma.csv"]);
+    let name = &json[0];
+    assert_eq!(name["type"], "categorical");
+    assert_eq!(name["unique"], 3);
+    assert_eq!(name["top"].as_array().unwrap().len(), 3);
+
+    let age = &json[1];
+    assert_eq!(age["type"], "numeric");
+    assert_eq!(age["count"], 3);
+    assert_eq!(age["mean"], 30.0);
+    assert_eq!(age["percentiles"]["p50"], 30.0);
+}
+
+#[test]
+fn test_stats_csv_one_row_per_column() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "--format",
+        "csv",
+        "stats",
+        "-c",
+        "age",
+        "tests/fixtures/sample_comma.csv",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::starts_with(
+        "column,type,count,null,invalid,unique,mean,std_dev,min,max,p1,p25,p50,p75,p99\n",
+    ))
+    .stdout(predicate::str::contains("\nage,numeric,3,0,0,,30,"));
+}
+
+#[test]
+fn test_freq_csv_pipes_back_into_clw() {
+    let output = cargo_bin_cmd!("clw")
+        .args([
+            "--format",
+            "csv",
+            "freq",
+            "-c",
+            "city",
+            "--sort-index",
+            "tests/fixtures/sample_comma.csv",
+        ])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("value,count,pct,cumpct\nChicago,1,"));
+    assert!(stdout.ends_with("New York,1,33.33333333333333,100\n"));
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["select", "-c", "value,count"])
+        .write_stdin(stdout)
+        .assert()
+        .success()
+        .stdout("value,count\nChicago,1\nLos Angeles,1\nNew York,1\n");
+}
+
+#[test]
+fn test_freq_json_ignores_plot() {
+    let json = json_output(&["freq", "-c", "city", "--plot", "tests/fixtures/sample_comma.csv"]);
+    assert_eq!(json["column"], "city");
+    assert_eq!(json["total"], 3);
+    assert_eq!(json["values"][2]["cumpct"], 100.0);
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use predicates::prelude::*;
+
+const PRODUCTS: &str = "sku,category,region,stock,price,latency
+a1,tools,eu,5,10.5,100
+a2,toys,us,3,4,200
+a3,tools,eu,7,9.5,300
+a1,tools,us,,12,
+a4,toys,us,1,n/a,50
+";
+
+fn groupby_output(args: &[&str]) -> String {
+    run(&[&["groupby"], args].concat(), &csv_file(PRODUCTS))
+}
+
+#[test]
+fn test_groupby_aggregations() {
+    let output = groupby_output(&[
+        "-g",
+        "category,region",
+        "-a",
+        "sum(stock),mean(price),count(),n_unique(sku),p90(latency)",
+    ]);
+    assert_eq!(
+        output,
+        "category,region,sum_stock,mean_price,count,n_unique_sku,p90_latency\n\
+         tools,eu,12,10,2,2,280\n\
+         toys,us,4,4,2,2,185\n\
+         tools,us,,12,1,1,\n"
+    );
+}
+
+#[test]
+fn test_groupby_empty_numeric_column() {
+    let file = csv_file("k,v\na,\na,n/a\nb,\n");
+    cargo_bin_cmd!("clw")
+        .args([
+            "groupby",
+            "-g",
+            "k",
+            "-a",
+            "sum(v),mean(v),min(v),max(v),median(v)",
+        ])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("k,sum_v,mean_v,min_v,max_v,median_v\na,,,,,\nb,,,,,\n");
+}
+
+#[test]
+fn test_groupby_text_aggregations() {
+    let output = groupby_output(&["-g", "category", "-a", "first(sku),last(sku),count(stock)"]);
+    assert_eq!(
+        output,
+        "category,first_sku,last_sku,count_stock\ntools,a1,a1,2\ntoys,a2,a4,2\n"
+    );
+}
+
+#[test]
+fn test_groupby_sorted_rejects_unsorted_input() {
+    let file = csv_file(PRODUCTS);
+    cargo_bin_cmd!("clw")
+        .args(["groupby", "-g", "category", "-a", "count()", "--sorted"])
+        .arg(file.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "not sorted by the group columns: 'tools' comes after 'toys'",
+        ));
+}
+
+#[test]
+fn test_groupby_sorted_accepts_sort_orders() {
+    let cases = [
+        ("n\n2\n10\n10\n", "n,count\n2,1\n10,2\n"),
+        ("n\n10\n2\n", "n,count\n10,1\n2,1\n"),
+        ("n\nb\nb\na\n", "n,count\nb,2\na,1\n"),
+        ("n,x\n,1\na,2\n", "n,count\n,1\na,1\n"),
+        ("n\nfile2\nfile10\n", "n,count\nfile2,1\nfile10,1\n"),
+    ];
+    for (input, expected) in cases {
+        cargo_bin_cmd!("clw")
+            .args(["groupby", "-g", "n", "-a", "count()", "--sorted"])
+            .write_stdin(input)
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+}
+
+#[test]
+fn test_groupby_sorted_checks_later_columns_within_a_group() {
+    cargo_bin_cmd!("clw")
+        .args(["groupby", "-g", "a,b", "-a", "count()", "--sorted"])
+        .write_stdin("a,b\nx,2\ny,1\ny,2\n")
+        .assert()
+        .success()
+        .stdout("a,b,count\nx,2,1\ny,1,1\ny,2,1\n");
+    cargo_bin_cmd!("clw")
+        .args(["groupby", "-g", "a,b", "-a", "count()", "--sorted"])
+        .write_stdin("a,b\nx,1\nx,2\nx,1\n")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_groupby_sorted_matches_hashed_after_sort() {
+    let file = csv_file(PRODUCTS);
+    let sorted = cargo_bin_cmd!("clw")
+        .args(["sort", "-c", "category"])
+        .arg(file.path())
+        .output()
+        .unwrap()
+        .stdout;
+    cargo_bin_cmd!("clw")
+        .args([
+            "groupby",
+            "-g",
+            "category",
+            "-a",
+            "sum(stock),median(price)",
+            "--sorted",
+        ])
+        .write_stdin(sorted)
+        .assert()
+        .success()
+        .stdout("category,sum_stock,median_price\ntools,12,10.5\ntoys,4,4\n");
+}
+
+#[test]
+fn test_groupby_invalid_aggregation() {
+    let file = csv_file(PRODUCTS);
+    cargo_bin_cmd!("clw")
+        .args(["groupby", "-g", "category", "-a", "total(stock)"])
+        .arg(file.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown function 'total'"));
+}
+
+#[test]
+fn test_groupby_unknown_column() {
+    let file = csv_file(PRODUCTS);
+    cargo_bin_cmd!("clw")
+        .args(["groupby", "-g", "category", "-a", "sum(weight)"])
+        .arg(file.path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("weight"));
+}
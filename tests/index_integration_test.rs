@@ -0,0 +1,222 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_rows(path: &Path, rows: usize) {
+    let mut data = String::from("id,name,score\n");
+    for i in 0..rows {
+        data.push_str(&format!("{},\"name, {}\",{}\n", i, i, i * 7 % 100));
+    }
+    fs::write(path, data).unwrap();
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let output = cargo_bin_cmd!("clw").args(args).output().unwrap();
+    assert!(output.status.success(), "clw {:?} failed", args);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_index_writes_sidecar() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 50);
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("index")
+        .arg(&csv)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Indexed 50 rows"));
+    assert!(dir.path().join("data.csv.idx").exists());
+}
+
+#[test]
+fn test_indexed_output_matches_full_scan() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 1000);
+    let path = csv.to_str().unwrap();
+
+    let commands: [&[&str]; 4] = [
+        &["sample", "-r", "7", "--seed", "3", path],
+        &["sample", "-r", "2000", path],
+        &["peek", "--start", "990", "-n", "5", path],
+        &["info", path],
+    ];
+    let before: Vec<_> = commands.iter().map(|args| run(args)).collect();
+    run(&["index", path]);
+    let after: Vec<_> = commands.iter().map(|args| run(args)).collect();
+
+    assert_eq!(before, after);
+    assert!(after[2].0.contains("990"));
+    assert!(after[2].0.contains("994"));
+    assert!(!after[2].0.contains("995"));
+}
+
+#[test]
+fn test_info_uses_index_for_inconsistent_rows() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("ragged.csv");
+    fs::write(&csv, "a,b\n1,2\n3\n4,5,6\n7,8\n").unwrap();
+    let path = csv.to_str().unwrap();
+
+    let before = run(&["info", path]);
+    run(&["index", path]);
+    let after = run(&["info", path]);
+    assert_eq!(before, after);
+    assert!(after.0.contains("Row 3: 1 fields"));
+    assert!(after.0.contains("Row 4: 3 fields"));
+}
+
+#[test]
+fn test_indexed_sample_fails_on_ragged_row_outside_sample() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("ragged.csv");
+    let mut data = String::from("a,b\n");
+    for i in 0..100 {
+        data.push_str(&format!("{},{}\n", i, i));
+    }
+    data.push_str("oops\n");
+    fs::write(&csv, data).unwrap();
+    let path = csv.to_str().unwrap();
+
+    let sample = |code: i32| {
+        cargo_bin_cmd!("clw")
+            .args(["sample", "-r", "1", "--seed", "1", path])
+            .assert()
+            .code(code);
+    };
+    sample(5);
+    run(&["index", path]);
+    sample(5);
+}
+
+#[test]
+fn test_stale_index_is_ignored() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 10);
+    let path = csv.to_str().unwrap();
+    run(&["index", path]);
+
+    write_rows(&csv, 20);
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("info")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    20"))
+        .stderr(predicate::str::contains("Ignoring index"));
+
+    // Commands looking for the index more than once still warn once
+    let (_, stderr) = run(&["info", path]);
+    assert_eq!(stderr.matches("Ignoring index").count(), 1);
+}
+
+#[test]
+fn test_index_leaves_no_temporary_file() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 10);
+    run(&["index", csv.to_str().unwrap()]);
+
+    let mut names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["data.csv", "data.csv.idx"]);
+}
+
+#[test]
+fn test_truncated_index_is_ignored() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 100);
+    let path = csv.to_str().unwrap();
+    run(&["index", path]);
+
+    // Cut the index short, keeping its header and the CSV unchanged
+    let idx = dir.path().join("data.csv.idx");
+    let bytes = fs::read(&idx).unwrap();
+    fs::write(&idx, &bytes[..bytes.len() / 2]).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["peek", "--start", "95", "-n", "2", path])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("95"))
+        .stdout(predicate::str::contains("96"))
+        .stderr(predicate::str::contains("Ignoring index"));
+}
+
+#[test]
+fn test_index_ignored_for_other_dialect() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    write_rows(&csv, 10);
+    let path = csv.to_str().unwrap();
+    run(&["index", path]);
+
+    // Counting the header as data gives one more row than the index has
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("info")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    11"));
+}
+
+#[test]
+fn test_peek_start_without_index() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("peek")
+        .arg("--start")
+        .arg("2")
+        .arg("-n")
+        .arg("1")
+        .write_stdin("id\n1\n2\n3\n4\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3"))
+        .stdout(predicate::str::contains("4").not());
+}
+
+#[test]
+fn test_index_with_utf8_bom() {
+    let dir = tempdir().unwrap();
+    let csv = dir.path().join("bom.csv");
+    fs::write(&csv, "\u{feff}id,name\n1,a\n2,b\n3,c\n").unwrap();
+    let path = csv.to_str().unwrap();
+
+    run(&["index", path]);
+    let (out, _) = run(&["peek", "--start", "1", "-n", "1", path]);
+    assert!(out.contains('2'));
+    assert!(out.contains('b'));
+    assert!(!out.contains('c'));
+}
+
+#[test]
+fn test_index_rejects_compressed_input() {
+    use std::io::Write;
+
+    let dir = tempdir().unwrap();
+    let gz = dir.path().join("data.csv.gz");
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"a,b\n1,2\n").unwrap();
+    fs::write(&gz, encoder.finish().unwrap()).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("index")
+        .arg(&gz)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Cannot index compressed file"));
+}
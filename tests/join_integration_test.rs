@@ -0,0 +1,133 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use predicates::prelude::*;
+use tempfile::NamedTempFile;
+
+fn users() -> NamedTempFile {
+    csv_file("id,name,city\n1,Ann,NYC\n2,Bob,LA\n3,Cy,SF\n")
+}
+
+fn orders() -> NamedTempFile {
+    csv_file("user_id,amount,city\n1,10,Boston\n1,20,NYC\n4,5,LA\n")
+}
+
+fn join_output(kind: &str) -> String {
+    let (users, orders) = (users(), orders());
+    let left = users.path().to_str().unwrap();
+    let args = [
+        "join",
+        "--left-key",
+        "id",
+        "--right-key",
+        "user_id",
+        "-t",
+        kind,
+        left,
+    ];
+    run(&args, &orders)
+}
+
+#[test]
+fn test_join_inner_one_to_many() {
+    assert_eq!(
+        join_output("inner"),
+        "id,name,city,amount,right_city\n1,Ann,NYC,10,Boston\n1,Ann,NYC,20,NYC\n"
+    );
+}
+
+#[test]
+fn test_join_left() {
+    assert_eq!(
+        join_output("left"),
+        "id,name,city,amount,right_city\n1,Ann,NYC,10,Boston\n1,Ann,NYC,20,NYC\n2,Bob,LA,,\n3,Cy,SF,,\n"
+    );
+}
+
+#[test]
+fn test_join_right_fills_key_from_right() {
+    assert_eq!(
+        join_output("right"),
+        "id,name,city,amount,right_city\n1,Ann,NYC,10,Boston\n1,Ann,NYC,20,NYC\n4,,,5,LA\n"
+    );
+}
+
+#[test]
+fn test_join_full() {
+    assert_eq!(
+        join_output("full"),
+        "id,name,city,amount,right_city\n1,Ann,NYC,10,Boston\n1,Ann,NYC,20,NYC\n2,Bob,LA,,\n3,Cy,SF,,\n4,,,5,LA\n"
+    );
+}
+
+#[test]
+fn test_join_semi_and_anti() {
+    assert_eq!(join_output("semi"), "id,name,city\n1,Ann,NYC\n");
+    assert_eq!(join_output("anti"), "id,name,city\n2,Bob,LA\n3,Cy,SF\n");
+}
+
+#[test]
+fn test_join_composite_key() {
+    let (users, orders) = (users(), orders());
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["join", "-l", "id,city", "-r", "user_id,city"])
+        .arg(users.path())
+        .arg(orders.path())
+        .assert()
+        .success()
+        .stdout("id,name,city,amount\n1,Ann,NYC,20\n");
+}
+
+#[test]
+fn test_join_prefixes_colliding_names() {
+    let (users, orders) = (users(), orders());
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["join", "-l", "id", "-r", "user_id"])
+        .args(["--left-prefix", "user_", "--right-prefix", "order_"])
+        .arg(users.path())
+        .arg(orders.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "id,name,user_city,amount,order_city\n",
+        ));
+}
+
+#[test]
+fn test_join_left_from_stdin_with_other_delimiter() {
+    let orders = orders();
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["join", "-l", "id", "-r", "user_id", "-"])
+        .arg(orders.path())
+        .write_stdin("id|tier\n4|gold\n1|free\n")
+        .assert()
+        .success()
+        .stdout("id|tier|amount|city\n4|gold|5|LA\n1|free|10|Boston\n1|free|20|NYC\n");
+}
+
+#[test]
+fn test_join_key_length_mismatch() {
+    let (users, orders) = (users(), orders());
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["join", "-l", "id,city", "-r", "user_id"])
+        .arg(users.path())
+        .arg(orders.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "The left key has 2 columns but the right key has 1",
+        ));
+}
+
+#[test]
+fn test_join_unknown_key_column() {
+    let (users, orders) = (users(), orders());
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["join", "-l", "id"])
+        .arg(users.path())
+        .arg(orders.path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Column 'id' not found"));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use predicates::prelude::*;
+
+const WIDE: &str = "id,name,q1,q2,q3,q4\n1,ann,10,20,30,40\n2,bob,5,,7,8\n";
+
+fn melt_output(args: &[&str]) -> String {
+    run(&[&["melt"], args].concat(), &csv_file(WIDE))
+}
+
+#[test]
+fn test_melt_range_with_names() {
+    let output = melt_output(&[
+        "--id",
+        "id,name",
+        "--value-cols",
+        "q1-q4",
+        "--var-name",
+        "quarter",
+        "--value-name",
+        "revenue",
+    ]);
+    assert_eq!(
+        output,
+        "id,name,quarter,revenue\n\
+         1,ann,q1,10\n1,ann,q2,20\n1,ann,q3,30\n1,ann,q4,40\n\
+         2,bob,q1,5\n2,bob,q2,\n2,bob,q3,7\n2,bob,q4,8\n"
+    );
+}
+
+#[test]
+fn test_melt_regex_value_columns() {
+    assert_eq!(
+        melt_output(&["--id", "name", "--value-cols", "/^q[12]$/"]),
+        "name,variable,value\nann,q1,10\nann,q2,20\nbob,q1,5\nbob,q2,\n"
+    );
+}
+
+#[test]
+fn test_melt_defaults_to_non_id_columns() {
+    assert_eq!(
+        melt_output(&["--id", "id,q1,q2,q3"]),
+        "id,q1,q2,q3,variable,value\n1,10,20,30,name,ann\n1,10,20,30,q4,40\n2,5,,7,name,bob\n2,5,,7,q4,8\n"
+    );
+}
+
+#[test]
+fn test_melt_then_pivot_round_trips() {
+    let file = csv_file(WIDE);
+    let long = cargo_bin_cmd!("clw")
+        .args(["melt", "--id", "id,name"])
+        .arg(file.path())
+        .output()
+        .unwrap()
+        .stdout;
+    cargo_bin_cmd!("clw")
+        .args(["pivot", "-i", "id,name", "-c", "variable", "-v", "value"])
+        .write_stdin(long)
+        .assert()
+        .success()
+        .stdout(WIDE);
+}
+
+#[test]
+fn test_melt_without_value_columns() {
+    let file = csv_file(WIDE);
+    cargo_bin_cmd!("clw")
+        .args(["melt", "--id", "1-6"])
+        .arg(file.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("No value columns"));
+}
@@ -0,0 +1,182 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+const DUMP: &str = "Alice,30,NYC\nBob,25,LA\nCharlie,35,NYC\n";
+
+#[test]
+fn test_no_header_select_by_index() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("select")
+        .arg("-c")
+        .arg("3,1")
+        .write_stdin(DUMP)
+        .assert()
+        .success()
+        .stdout("NYC,Alice\nLA,Bob\nNYC,Charlie\n");
+}
+
+#[test]
+fn test_no_header_synthetic_header_on_request() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("--synthetic-header")
+        .arg("select")
+        .arg("-c")
+        .arg("3,1")
+        .write_stdin(DUMP)
+        .assert()
+        .success()
+        .stdout("3,1\nNYC,Alice\nLA,Bob\nNYC,Charlie\n");
+}
+
+#[test]
+fn test_no_header_filter_keeps_first_row() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("filter")
+        .arg("-c")
+        .arg("1")
+        .arg("-v")
+        .arg("Alice")
+        .arg("--keep-header")
+        .write_stdin(DUMP)
+        .assert()
+        .success()
+        .stdout("Alice,30,NYC\n");
+}
+
+#[test]
+fn test_no_header_freq_counts_first_row() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("freq")
+        .arg("-c")
+        .arg("3")
+        .write_stdin(DUMP)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("NYC"))
+        .stdout(predicate::str::contains("2"));
+}
+
+#[test]
+fn test_no_header_stats_by_index() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("stats")
+        .arg("-c")
+        .arg("2")
+        .write_stdin(DUMP)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Count:        3"))
+        .stdout(predicate::str::contains("Mean:         30.00"));
+}
+
+#[test]
+fn test_no_header_unknown_index() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("stats")
+        .arg("-c")
+        .arg("7")
+        .write_stdin(DUMP)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Column '7' not found"));
+}
+
+#[test]
+fn test_no_header_sample_all_rows_without_header() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    let output = cmd
+        .arg("--no-header")
+        .arg("sample")
+        .arg("-r")
+        .arg("10")
+        .write_stdin(DUMP)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.contains("Alice,30,NYC"));
+}
+
+#[test]
+fn test_no_header_info_counts_all_rows() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("info")
+        .write_stdin("a,b\nc,d,e\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    2"))
+        .stdout(predicate::str::contains("Row 2: 3 fields"));
+}
+
+#[test]
+fn test_no_header_stack_keeps_all_rows() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    writeln!(file1, "1,a").unwrap();
+    writeln!(file1, "2,b").unwrap();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    writeln!(file2, "3,c").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("stack")
+        .arg(file1.path())
+        .arg(file2.path())
+        .assert()
+        .success()
+        .stdout("1,a\n2,b\n3,c\n");
+}
+
+#[test]
+fn test_no_header_stack_stdin_keeps_identical_rows() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("stack")
+        .write_stdin("1,2\n1,2\n")
+        .assert()
+        .success()
+        .stdout("1,2\n1,2\n");
+}
+
+#[test]
+fn test_no_header_paste_synthetic_header_renumbered() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    writeln!(file1, "a,b").unwrap();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    writeln!(file2, "c").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--no-header")
+        .arg("--synthetic-header")
+        .arg("paste")
+        .arg(file1.path())
+        .arg(file2.path())
+        .assert()
+        .success()
+        .stdout("1,2,3\na,b,c\n");
+}
+
+#[test]
+fn test_sample_omit_header_excludes_header() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    let output = cmd
+        .arg("sample")
+        .arg("-r")
+        .arg("10")
+        .arg("--omit-header")
+        .write_stdin("name\nAlice\n")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Alice\n");
+}
@@ -0,0 +1,211 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+const RAGGED: &str = "id,name\n1,alice\n2,bob,extra\n3,carol\n";
+
+#[test]
+fn test_fail_is_default() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("name")
+        .write_stdin(RAGGED)
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("found record with 3 fields"));
+}
+
+#[test]
+fn test_skip_drops_ragged_row_with_warning() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("skip")
+        .arg("select")
+        .arg("-c")
+        .arg("name")
+        .write_stdin(RAGGED)
+        .assert()
+        .success()
+        .stdout("name\nalice\ncarol\n")
+        .stderr("Warning: Skipped 1 malformed row\n");
+}
+
+#[test]
+fn test_skip_drops_invalid_utf8_row() {
+    let mut input = b"id,name\n1,alice\n2,".to_vec();
+    input.extend_from_slice(b"b\xffb\n3,carol\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("skip")
+        .arg("freq")
+        .arg("-c")
+        .arg("name")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alice"))
+        .stdout(predicate::str::contains("carol"))
+        .stderr(predicate::str::contains("Skipped 1 malformed row"));
+}
+
+#[test]
+fn test_report_writes_bad_rows_with_offsets() {
+    let dir = tempdir().unwrap();
+    let report = dir.path().join("bad.csv");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("report")
+        .arg("--error-report")
+        .arg(&report)
+        .arg("stats")
+        .arg("-c")
+        .arg("id")
+        .write_stdin(RAGGED)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Count:        2"))
+        .stderr(predicate::str::contains("Skipped 1 malformed row; see"));
+
+    let written = fs::read_to_string(&report).unwrap();
+    assert_eq!(
+        written,
+        "file,line,byte,error,record\n\
+         -,3,16,\"found record with 3 fields, but the header has 2 fields\",\"2,bob,extra\"\n"
+    );
+}
+
+#[test]
+fn test_report_requires_file() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("report")
+        .arg("peek")
+        .write_stdin(RAGGED)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--error-report"));
+}
+
+#[test]
+fn test_report_file_requires_report_policy() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("skip")
+        .arg("--error-report")
+        .arg("bad.csv")
+        .arg("peek")
+        .write_stdin(RAGGED)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--on-error report"));
+}
+
+#[test]
+fn test_report_offsets_stay_file_relative_after_index_seek() {
+    let dir = tempdir().unwrap();
+    let data = dir.path().join("data.csv");
+    let report = dir.path().join("bad.csv");
+    fs::write(&data, RAGGED).unwrap();
+    cargo_bin_cmd!("clw")
+        .arg("index")
+        .arg(&data)
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("report")
+        .arg("--error-report")
+        .arg(&report)
+        .arg("slice")
+        .arg("-s")
+        .arg("1")
+        .arg(&data)
+        .assert()
+        .success()
+        .stdout("id,name\n3,carol\n");
+
+    let written = fs::read_to_string(&report).unwrap();
+    assert_eq!(
+        written,
+        format!(
+            "file,line,byte,error,record\n\
+             {},3,16,\"found record with 3 fields, but the header has 2 fields\",\"2,bob,extra\"\n",
+            data.display()
+        )
+    );
+}
+
+#[test]
+fn test_skip_in_stack_counts_across_files() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a.csv");
+    let b = dir.path().join("b.csv");
+    fs::write(&a, RAGGED).unwrap();
+    fs::write(&b, "id,name\n4,dave,x\n5,erin\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("skip")
+        .arg("stack")
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .success()
+        .stdout("id,name\n1,alice\n3,carol\n5,erin\n")
+        .stderr("Warning: Skipped 2 malformed rows\n");
+}
+
+#[test]
+fn test_report_in_stack_names_each_file() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a.csv");
+    let b = dir.path().join("b.csv");
+    let report = dir.path().join("bad.csv");
+    fs::write(&a, RAGGED).unwrap();
+    fs::write(&b, "id,name\n4,dave,x\n5,erin\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("report")
+        .arg("--error-report")
+        .arg(&report)
+        .arg("stack")
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .success()
+        .stdout("id,name\n1,alice\n3,carol\n5,erin\n");
+
+    let error = "\"found record with 3 fields, but the header has 2 fields\"";
+    let written = fs::read_to_string(&report).unwrap();
+    assert_eq!(
+        written,
+        format!(
+            "file,line,byte,error,record\n\
+             {},3,16,{},\"2,bob,extra\"\n\
+             {},2,8,{},\"4,dave,x\"\n",
+            a.display(),
+            error,
+            b.display(),
+            error
+        )
+    );
+}
+
+#[test]
+fn test_info_still_reports_ragged_rows_under_skip() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--on-error")
+        .arg("skip")
+        .arg("info")
+        .write_stdin(RAGGED)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    3"))
+        .stdout(predicate::str::contains("Row 3: 3 fields"));
+}
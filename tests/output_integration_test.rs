@@ -0,0 +1,126 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::io::Read;
+
+#[test]
+fn test_output_writes_file_instead_of_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("selected.csv");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("-o")
+        .arg(&out)
+        .args([
+            "select",
+            "-c",
+            "name,city",
+            "tests/fixtures/sample_comma.csv",
+        ])
+        .assert()
+        .success()
+        .stdout("");
+
+    let written = fs::read_to_string(&out).unwrap();
+    assert!(written.starts_with("name,city\nAlice,New York\n"));
+}
+
+#[test]
+fn test_output_failed_run_keeps_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("result.csv");
+    fs::write(&out, "previous\n").unwrap();
+
+    // The ragged row aborts the run after some rows were already written
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--output")
+        .arg(&out)
+        .args(["filter", "-c", "1", "-v", "1,2,3,4,5,6,7,8"])
+        .arg("tests/fixtures/dirty_inconsistent.csv")
+        .assert()
+        .failure();
+
+    assert_eq!(fs::read_to_string(&out).unwrap(), "previous\n");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_output_can_replace_its_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.csv");
+    fs::copy("tests/fixtures/sample_comma.csv", &path).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("-o")
+        .arg(&path)
+        .args(["select", "-c", "age"])
+        .arg(&path)
+        .assert()
+        .success();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "age\n30\n25\n35\n");
+}
+
+#[test]
+fn test_output_gzip_by_extension_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("stacked.csv.gz");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("-o")
+        .arg(&out)
+        .args([
+            "stack",
+            "tests/fixtures/sample_comma.csv",
+            "tests/fixtures/sample_comma.csv",
+        ])
+        .assert()
+        .success();
+
+    let mut decoded = String::new();
+    flate2::read::MultiGzDecoder::new(fs::File::open(&out).unwrap())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded.lines().count(), 7);
+
+    // clw reads its own compressed output back
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("info")
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rows:    6"));
+}
+
+#[test]
+fn test_output_zstd_by_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("t.csv.zst");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("-o")
+        .arg(&out)
+        .args(["transpose", "tests/fixtures/sample_comma.csv"])
+        .assert()
+        .success();
+
+    let decoded = zstd::decode_all(fs::File::open(&out).unwrap()).unwrap();
+    assert!(String::from_utf8(decoded)
+        .unwrap()
+        .starts_with("name,Alice,Bob,Charlie\n"));
+}
+
+#[test]
+fn test_output_rejected_for_report_commands() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args([
+        "-o",
+        "unused.csv",
+        "info",
+        "tests/fixtures/sample_comma.csv",
+    ])
+    .assert()
+    .code(2)
+    .stderr(predicate::str::contains("--output only applies to"));
+    assert!(!std::path::Path::new("unused.csv").exists());
+}
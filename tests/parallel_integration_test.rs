@@ -0,0 +1,142 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Roughly 4 MB, large enough to be split into several chunks.
+const ROWS: usize = 60_000;
+
+fn write_csv(path: &Path, row: impl Fn(usize) -> String) {
+    let mut data = String::from("id,text,cat,val\r\n");
+    for i in 0..ROWS {
+        data.push_str(&row(i));
+    }
+    fs::write(path, data).unwrap();
+}
+
+fn output(args: &[&str]) -> std::process::Output {
+    cargo_bin_cmd!("clw").args(args).output().unwrap()
+}
+
+fn assert_same_output(path: &Path, command: &[&str]) {
+    let path = path.to_str().unwrap();
+    let sequential = output(&[&["--jobs", "1"], command, &[path]].concat());
+    let parallel = output(&[&["--jobs", "4"], command, &[path]].concat());
+    assert_eq!(sequential.status.code(), parallel.status.code());
+    assert_eq!(
+        String::from_utf8_lossy(&sequential.stdout),
+        String::from_utf8_lossy(&parallel.stdout)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&sequential.stderr),
+        String::from_utf8_lossy(&parallel.stderr)
+    );
+}
+
+#[test]
+fn test_parallel_matches_sequential_with_quoted_newlines() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("multiline.csv");
+    write_csv(&path, |i| {
+        let text = if i % 3 == 0 {
+            "first line\nsecond \"\"quoted\"\", line\nthird line"
+        } else {
+            "plain text padding the row out a little"
+        };
+        format!(
+            "{},\"{}\",c{},{}.{}\r\n",
+            i,
+            text,
+            i * 7 % 23,
+            i % 1000,
+            i % 7
+        )
+    });
+
+    assert_same_output(&path, &["freq", "-c", "cat"]);
+    assert_same_output(&path, &["stats", "-c", "val,cat"]);
+    assert_same_output(&path, &["info"]);
+}
+
+#[test]
+fn test_parallel_matches_sequential_with_stray_quotes() {
+    // Quotes inside unquoted fields throw off quote parity; chunks that start
+    // in the wrong place must be parsed again
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("stray.csv");
+    write_csv(&path, |i| {
+        format!(
+            "{},5'{}\" tall \"{}\",c{},{}\r\n",
+            i,
+            i % 8,
+            "padding padding padding padding",
+            i % 5,
+            i
+        )
+    });
+
+    assert_same_output(&path, &["freq", "-c", "cat"]);
+    assert_same_output(&path, &["stats", "-c", "val"]);
+    assert_same_output(&path, &["info"]);
+}
+
+#[test]
+fn test_parallel_info_reports_inconsistent_rows_in_order() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ragged.csv");
+    write_csv(&path, |i| {
+        if i % 9_000 == 4_000 {
+            format!("{},short\r\n", i)
+        } else {
+            format!("{},\"some text for row {}\",c{},{}\r\n", i, i, i % 3, i)
+        }
+    });
+
+    assert_same_output(&path, &["info"]);
+    let info = output(&["--jobs", "4", "info", path.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&info.stdout);
+    assert!(stdout.contains("Row 4002: 2 fields"));
+    assert!(stdout.contains("Total inconsistent rows: 7/60000"));
+}
+
+#[test]
+fn test_parallel_error_matches_sequential() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ragged.csv");
+    write_csv(&path, |i| {
+        if i == 50_000 {
+            format!("{},short\r\n", i)
+        } else {
+            format!("{},\"some text for row {}\",c{},{}\r\n", i, i, i % 3, i)
+        }
+    });
+
+    assert_same_output(&path, &["freq", "-c", "cat"]);
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("--jobs")
+        .arg("4")
+        .arg("freq")
+        .arg("-c")
+        .arg("cat")
+        .arg(&path)
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("record 50001"));
+}
+
+#[test]
+fn test_parallel_uses_index_boundaries() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("indexed.csv");
+    write_csv(&path, |i| format!("{},\"a\nb\",c{},{}\r\n", i, i % 11, i));
+    let before = output(&["--jobs", "1", "freq", "-c", "cat", path.to_str().unwrap()]);
+
+    cargo_bin_cmd!("clw")
+        .arg("index")
+        .arg(&path)
+        .assert()
+        .success();
+    let after = output(&["--jobs", "4", "freq", "-c", "cat", path.to_str().unwrap()]);
+    assert_eq!(before.stdout, after.stdout);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use predicates::prelude::*;
+
+const METRICS: &str = "date,metric,value
+2024-01-01,cpu,10
+2024-01-01,mem,70
+2024-01-02,cpu,20
+2024-01-02,cpu,30
+2024-01-03,disk,5
+";
+
+fn pivot_output(args: &[&str]) -> String {
+    let pivot = ["pivot", "--index", "date", "--columns", "metric"];
+    run(&[&pivot, args].concat(), &csv_file(METRICS))
+}
+
+#[test]
+fn test_pivot_first_by_default() {
+    assert_eq!(
+        pivot_output(&["--values", "value"]),
+        "date,cpu,disk,mem\n2024-01-01,10,,70\n2024-01-02,20,,\n2024-01-03,,5,\n"
+    );
+}
+
+#[test]
+fn test_pivot_mean_with_fill() {
+    assert_eq!(
+        pivot_output(&["--values", "value", "--agg", "mean", "--fill", "0"]),
+        "date,cpu,disk,mem\n2024-01-01,10,0,70\n2024-01-02,25,0,0\n2024-01-03,0,5,0\n"
+    );
+}
+
+#[test]
+fn test_pivot_count_without_values() {
+    assert_eq!(
+        pivot_output(&["--agg", "count"]),
+        "date,cpu,disk,mem\n2024-01-01,1,,1\n2024-01-02,2,,\n2024-01-03,,1,\n"
+    );
+}
+
+#[test]
+fn test_pivot_column_order() {
+    let file = csv_file("id,q,v\na,q10,1\na,q2,2\na,q1,3\n");
+    let run = |order: &str| {
+        let output = cargo_bin_cmd!("clw")
+            .args([
+                "pivot",
+                "-i",
+                "id",
+                "-c",
+                "q",
+                "-v",
+                "v",
+                "--column-order",
+                order,
+            ])
+            .arg(file.path())
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(run("natural"), "id,q1,q2,q10\na,3,2,1\n");
+    assert_eq!(run("lexical"), "id,q1,q10,q2\na,3,1,2\n");
+    assert_eq!(run("appearance"), "id,q10,q2,q1\na,1,2,3\n");
+}
+
+#[test]
+fn test_pivot_sum_needs_values() {
+    let file = csv_file(METRICS);
+    cargo_bin_cmd!("clw")
+        .args(["pivot", "-i", "date", "-c", "metric", "--agg", "sum"])
+        .arg(file.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "--agg sum needs a --values column",
+        ));
+}
@@ -111,12 +111,12 @@ fn test_sample_zero_rows() {
 }
 
 #[test]
-fn test_sample_with_no_header_flag() {
+fn test_sample_with_omit_header_flag() {
     let mut cmd = cargo_bin_cmd!("clw");
     cmd.arg("sample")
         .arg("--rows")
         .arg("2")
-        .arg("--no-header")
+        .arg("--omit-header")
         .arg("tests/fixtures/sample_comma.csv")
         .assert()
         .success();
@@ -153,12 +153,12 @@ fn test_sample_with_header_default() {
 }
 
 #[test]
-fn test_sample_no_header_pipe_delimited() {
+fn test_sample_omit_header_pipe_delimited() {
     let mut cmd = cargo_bin_cmd!("clw");
     cmd.arg("sample")
         .arg("--rows")
         .arg("1")
-        .arg("--no-header")
+        .arg("--omit-header")
         .arg("tests/fixtures/sample_pipe.csv")
         .assert()
         .success();
@@ -177,12 +177,12 @@ fn test_sample_no_header_pipe_delimited() {
 }
 
 #[test]
-fn test_sample_no_header_zero_rows() {
+fn test_sample_omit_header_zero_rows() {
     let mut cmd = cargo_bin_cmd!("clw");
     cmd.arg("sample")
         .arg("--rows")
         .arg("0")
-        .arg("--no-header")
+        .arg("--omit-header")
         .arg("tests/fixtures/sample_comma.csv")
         .assert()
         .success();
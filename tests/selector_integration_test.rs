@@ -0,0 +1,124 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+
+const WIDE: &str = "id,name,price,score_a,score_b,comment\n\
+                    1,Widget,9.99,10,20,ok\n\
+                    2,Gadget,19.99,30,40,\"late, damaged\"\n";
+
+#[test]
+fn test_select_index_range() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("2-3")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout("name,price\nWidget,9.99\nGadget,19.99\n");
+}
+
+#[test]
+fn test_select_name_range_and_regex() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("id-name,/^score_/")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout("id,name,score_a,score_b\n1,Widget,10,20\n2,Gadget,30,40\n");
+}
+
+#[test]
+fn test_select_exclusion() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("!comment,!/^score_/")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout("id,name,price\n1,Widget,9.99\n2,Gadget,19.99\n");
+}
+
+#[test]
+fn test_select_quoted_name_with_comma() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("\"last, first\",id")
+        .write_stdin("id,\"last, first\"\n1,\"Doe, John\"\n")
+        .assert()
+        .success()
+        .stdout("\"last, first\",id\n\"Doe, John\",1\n");
+}
+
+#[test]
+fn test_filter_by_index() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("filter")
+        .arg("-c")
+        .arg("2")
+        .arg("-v")
+        .arg("Gadget")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout("2,Gadget,19.99,30,40,\"late, damaged\"\n");
+}
+
+#[test]
+fn test_freq_by_regex_single_match() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("freq")
+        .arg("-c")
+        .arg("/^nam/")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Widget"))
+        .stdout(predicate::str::contains("Gadget"));
+}
+
+#[test]
+fn test_freq_rejects_multiple_columns() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("freq")
+        .arg("-c")
+        .arg("/^score_/")
+        .write_stdin(WIDE)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Expected exactly one column"));
+}
+
+#[test]
+fn test_stats_multiple_columns() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("stats")
+        .arg("-c")
+        .arg("/^score_/")
+        .write_stdin(WIDE)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Column 'score_a' Statistics (Numeric)",
+        ))
+        .stdout(predicate::str::contains(
+            "Column 'score_b' Statistics (Numeric)",
+        ))
+        .stdout(predicate::str::contains("Mean:         20.00"))
+        .stdout(predicate::str::contains("Mean:         30.00"));
+}
+
+#[test]
+fn test_invalid_selector_reports_error() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.arg("select")
+        .arg("-c")
+        .arg("/[unclosed/")
+        .write_stdin(WIDE)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid regex selector"));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+
+const NOTES: &str = "id,note\n1,\"a\nb\"\n2,plain\n3,\"x\n\"\"y\"\"\"\n4,z\n";
+
+#[test]
+fn test_slice_start_end() {
+    let file = csv_file(NOTES);
+    assert_eq!(
+        run(&["slice", "--start", "1", "--end", "3"], &file),
+        "id,note\n2,plain\n3,\"x\n\"\"y\"\"\"\n"
+    );
+}
+
+#[test]
+fn test_slice_start_len() {
+    let file = csv_file(NOTES);
+    assert_eq!(
+        run(&["slice", "-s", "3", "-l", "5"], &file),
+        "id,note\n4,z\n"
+    );
+    assert_eq!(run(&["slice", "-s", "9"], &file), "id,note\n");
+}
+
+#[test]
+fn test_slice_uses_index() {
+    let file = csv_file(NOTES);
+    run(&["index"], &file);
+    assert_eq!(
+        run(&["slice", "-s", "2", "-l", "1"], &file),
+        "id,note\n3,\"x\n\"\"y\"\"\"\n"
+    );
+    std::fs::remove_file(format!("{}.idx", file.path().display())).unwrap();
+}
+
+#[test]
+fn test_slice_end_before_start() {
+    let file = csv_file(NOTES);
+    cargo_bin_cmd!("clw")
+        .args(["slice", "-s", "3", "-e", "1"])
+        .arg(file.path())
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_tail_reads_quoted_line_breaks_backwards() {
+    let file = csv_file(NOTES);
+    assert_eq!(
+        run(&["tail", "-n", "2"], &file),
+        "id,note\n3,\"x\n\"\"y\"\"\"\n4,z\n"
+    );
+    assert_eq!(
+        run(&["tail", "-n", "3"], &file),
+        "id,note\n2,plain\n3,\"x\n\"\"y\"\"\"\n4,z\n"
+    );
+}
+
+#[test]
+fn test_tail_with_stray_quotes_in_unquoted_fields() {
+    let data = "id,desc\n1,a\n2,5\" x\n3,b\n4,6\" y\n5,c\n";
+    let file = csv_file(data);
+    assert_eq!(
+        run(&["tail", "-n", "2"], &file),
+        "id,desc\n4,\"6\"\" y\"\n5,c\n"
+    );
+    for n in ["1", "3", "4"] {
+        let from_stdin = cargo_bin_cmd!("clw")
+            .args(["tail", "-n", n])
+            .write_stdin(data)
+            .output()
+            .unwrap();
+        assert_eq!(
+            run(&["tail", "-n", n], &file),
+            String::from_utf8(from_stdin.stdout).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_tail_more_rows_than_the_file() {
+    let file = csv_file(NOTES);
+    assert_eq!(run(&["tail", "-n", "50"], &file), NOTES);
+}
+
+#[test]
+fn test_tail_from_stdin() {
+    cargo_bin_cmd!("clw")
+        .args(["tail", "-n", "2"])
+        .write_stdin(NOTES)
+        .assert()
+        .success()
+        .stdout("id,note\n3,\"x\n\"\"y\"\"\"\n4,z\n");
+}
+
+#[test]
+fn test_tail_without_header() {
+    let file = csv_file("a,1\nb,2\nc,3\n");
+    assert_eq!(
+        run(&["--no-header", "tail", "-n", "2"], &file),
+        "b,2\nc,3\n"
+    );
+}
@@ -0,0 +1,124 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::Write;
+
+#[test]
+fn test_sort_numeric_desc_then_lexical() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "price:numeric:desc,name"])
+        .write_stdin("name,price\nb,9.5\nc,10\na,9.5\nd,\n")
+        .assert()
+        .success()
+        .stdout("name,price\nc,10\na,9.5\nb,9.5\nd,\n");
+}
+
+#[test]
+fn test_sort_infers_numeric_kind() {
+    let input = "name,price\nb,9.5\nc,10\na,9.5\nd,\ne,100\n";
+    let expected = "name,price\ne,100\nc,10\na,9.5\nb,9.5\nd,\n";
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "price:desc,name"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(input.as_bytes()).unwrap();
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "price:desc,name"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(expected);
+}
+
+#[test]
+fn test_sort_mixed_column_stays_lexical() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "code"])
+        .write_stdin("code\n10\n9\nA1\n")
+        .assert()
+        .success()
+        .stdout("code\n10\n9\nA1\n");
+}
+
+#[test]
+fn test_sort_keeps_quoted_fields() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "city"])
+        .arg("tests/fixtures/sample_min_quoted.csv")
+        .assert()
+        .success()
+        .stdout(
+            "name,age,\"address, street\",city,state\n\
+             Jack,25,101 Jaskson St.,Austin,TX\n\
+             Bob,48,\"666 Lonepine Dr., North\",Los Angeles,CA\n\
+             Alice,35,\"123 Main St., apt 10\",San Diego,CA\n",
+        );
+}
+
+#[test]
+fn test_sort_natural_and_date() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "file:natural"])
+        .write_stdin("file\nlog10\nlog9\nlog100\n")
+        .assert()
+        .success()
+        .stdout("file\nlog9\nlog10\nlog100\n");
+
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "day:date:desc"])
+        .write_stdin("day\n2023-12-31\n2024-01-02T08:00\n2024/01/01\n")
+        .assert()
+        .success()
+        .stdout("day\n2024-01-02T08:00\n2024/01/01\n2023-12-31\n");
+}
+
+#[test]
+fn test_sort_is_stable() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "k:desc"])
+        .write_stdin("k,i\na,1\nb,2\na,3\nb,4\n")
+        .assert()
+        .success()
+        .stdout("k,i\nb,2\nb,4\na,1\na,3\n");
+}
+
+#[test]
+fn test_sort_spills_to_disk_with_same_result() {
+    let mut input = String::from("id,group\n");
+    for i in 0..20_000 {
+        input.push_str(&format!("{},{}\n", i, (i * 31) % 97));
+    }
+    let run = |memory: &str| {
+        let dir = tempfile::tempdir().unwrap();
+        let output = cargo_bin_cmd!("clw")
+            .args(["sort", "-c", "group:numeric", "-m", memory, "--temp-dir"])
+            .arg(dir.path())
+            .write_stdin(input.clone())
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success());
+        // Runs are anonymous temporary files, gone once the command ends
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        output.stdout
+    };
+    let in_memory = run("1G");
+    let external = run("64K");
+    assert_eq!(in_memory, external);
+    assert!(String::from_utf8(in_memory)
+        .unwrap()
+        .starts_with("id,group\n0,0\n97,0\n194,0\n"));
+}
+
+#[test]
+fn test_sort_invalid_key_modifier() {
+    let mut cmd = cargo_bin_cmd!("clw");
+    cmd.args(["sort", "-c", "age:upwards"])
+        .arg("tests/fixtures/sample_comma.csv")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown modifier 'upwards'"));
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::{csv_file, run};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const ORDERS: &str =
+    "id;country;amount\n1;US;10\n2;FR;20\n3;US;30\n4;;40\n5;DE;50\n6;FR;60\n7;US;70\n";
+
+fn split(dir: &Path, args: &[&str]) {
+    let dir = dir.to_str().unwrap();
+    run(
+        &[&["split"], args, &["--dir", dir]].concat(),
+        &csv_file(ORDERS),
+    );
+}
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_split_rows_repeats_header_and_delimiter() {
+    let dir = tempdir().unwrap();
+    split(dir.path(), &["--rows", "3", "--prefix", "part_"]);
+    assert_eq!(
+        files(dir.path()),
+        ["part_0000.csv", "part_0001.csv", "part_0002.csv"]
+    );
+    let first = fs::read_to_string(dir.path().join("part_0000.csv")).unwrap();
+    assert_eq!(first, "id;country;amount\n1;US;10\n2;FR;20\n3;US;30\n");
+    let last = fs::read_to_string(dir.path().join("part_0002.csv")).unwrap();
+    assert_eq!(last, "id;country;amount\n7;US;70\n");
+}
+
+#[test]
+fn test_split_bytes_never_splits_rows() {
+    let dir = tempdir().unwrap();
+    split(dir.path(), &["--bytes", "30"]);
+    let mut rows = Vec::new();
+    for name in files(dir.path()) {
+        let content = fs::read_to_string(dir.path().join(name)).unwrap();
+        assert!(content.len() <= 30, "{:?}", content);
+        let mut lines = content.lines();
+        assert_eq!(lines.next(), Some("id;country;amount"));
+        rows.extend(lines.map(str::to_string));
+    }
+    assert_eq!(rows.len(), 7);
+}
+
+#[test]
+fn test_split_by_value_with_few_open_files() {
+    let dir = tempdir().unwrap();
+    split(dir.path(), &["--by", "country", "--max-open", "1"]);
+    assert_eq!(
+        files(dir.path()),
+        ["part-.csv", "part-DE.csv", "part-FR.csv", "part-US.csv"]
+    );
+    let us = fs::read_to_string(dir.path().join("part-US.csv")).unwrap();
+    assert_eq!(us, "id;country;amount\n1;US;10\n3;US;30\n7;US;70\n");
+}
+
+#[test]
+fn test_split_by_values_differing_in_case() {
+    let dir = tempdir().unwrap();
+    let file = csv_file("id,country\n1,US\n2,us\n3,US\n4,us\n");
+    let out = dir.path().to_str().unwrap();
+    run(&["split", "--by", "country", "--dir", out], &file);
+    assert_eq!(files(dir.path()), ["part-US.csv", "part-us~2.csv"]);
+    let upper = fs::read_to_string(dir.path().join("part-US.csv")).unwrap();
+    assert_eq!(upper, "id,country\n1,US\n3,US\n");
+    let lower = fs::read_to_string(dir.path().join("part-us~2.csv")).unwrap();
+    assert_eq!(lower, "id,country\n2,us\n4,us\n");
+}
+
+#[test]
+fn test_split_does_not_overwrite_existing_files() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("part-US.csv"), "keep me\n").unwrap();
+    let file = csv_file(ORDERS);
+    cargo_bin_cmd!("clw")
+        .args(["split", "--by", "country", "--dir"])
+        .arg(dir.path())
+        .arg(file.path())
+        .assert()
+        .code(3);
+    let kept = fs::read_to_string(dir.path().join("part-US.csv")).unwrap();
+    assert_eq!(kept, "keep me\n");
+}
+
+#[test]
+fn test_split_hive_layout() {
+    let dir = tempdir().unwrap();
+    split(dir.path(), &["--by", "country", "--hive"]);
+    assert_eq!(
+        files(dir.path()),
+        [
+            "country=DE",
+            "country=FR",
+            "country=US",
+            "country=__HIVE_DEFAULT_PARTITION__"
+        ]
+    );
+    let fr = fs::read_to_string(dir.path().join("country=FR/part-0.csv")).unwrap();
+    assert_eq!(fr, "id;country;amount\n2;FR;20\n6;FR;60\n");
+}
+
+#[test]
+fn test_split_requires_a_mode() {
+    let file = csv_file(ORDERS);
+    cargo_bin_cmd!("clw")
+        .arg("split")
+        .arg(file.path())
+        .assert()
+        .code(2);
+    cargo_bin_cmd!("clw")
+        .args(["split", "--rows", "2", "--hive"])
+        .arg(file.path())
+        .assert()
+        .code(2);
+}